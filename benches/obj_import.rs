@@ -0,0 +1,213 @@
+//! Measures `Obj::import` on a large synthetic mesh, against the
+//! `String` importer of the first release, which it replaced.
+//!
+//! Run with `cargo bench --bench obj_import`. The triangle count
+//! defaults to 10 million and can be changed with the
+//! `SIMP_BENCH_TRIANGLES` environment variable. Each importer runs
+//! `SIMP_BENCH_RUNS` times, 5 by default, and the speed-ups compare
+//! the median times, as single runs vary too much to be repeatable.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::time::{Duration, Instant};
+
+use suoi_simp::face::{Face, FaceElement};
+use suoi_simp::obj_mesh::ObjMesh;
+use suoi_simp::*;
+use suoi_types::{Vector2, Vector3};
+
+const DEFAULT_TRIANGLES: usize = 10_000_000;
+const DEFAULT_RUNS: usize = 5;
+
+/// Writes a wavy grid with positions, uvs, normals and `triangles` faces
+fn generate(path: &Path, triangles: usize) -> std::io::Result<()> {
+    let quads = triangles.div_ceil(2);
+    let side = (quads as f64).sqrt().ceil() as usize + 1;
+    let mut out = BufWriter::new(File::create(path)?);
+
+    writeln!(out, "# synthetic benchmark mesh, {} triangles", triangles)?;
+    writeln!(out, "o grid")?;
+    for j in 0..side {
+        for i in 0..side {
+            let (u, v) = (i as f32 / side as f32, j as f32 / side as f32);
+            let height = (u * 40.0).sin() * (v * 40.0).cos() * 0.05;
+            writeln!(out, "v {:.6} {:.6} {:.6}", u * 10.0, height, v * 10.0)?;
+            writeln!(out, "vt {:.6} {:.6}", u, v)?;
+            writeln!(out, "vn {:.6} {:.6} {:.6}", -height, 0.998_749, height)?;
+        }
+    }
+
+    let mut written = 0;
+    'rows: for j in 0..side - 1 {
+        for i in 0..side - 1 {
+            let a = j * side + i + 1;
+            let (b, c, d) = (a + 1, a + side + 1, a + side);
+            for [x, y, z] in [[a, b, c], [a, c, d]] {
+                if written == triangles {
+                    break 'rows;
+                }
+                writeln!(out, "f {x}/{x}/{x} {y}/{y}/{y} {z}/{z}/{z}")?;
+                written += 1;
+            }
+        }
+    }
+
+    out.flush()
+}
+
+/**
+The `String` importer of the first release, reproduced from it:
+`read_to_string`, `str::lines`, `str::parse` for every number and a
+`Vec` per face element. Only the statements of the benchmark mesh
+are kept, and meshes are built through today's `ObjMesh` API.
+*/
+fn import_baseline(path: &Path) -> Result<Vec<ObjMesh>, ImportError> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .or(Err(ImportError::InvalidPath(path.to_owned())))?;
+
+    let parse_float = |tokens: &mut Tokens| {
+        tokens
+            .next()
+            .ok_or(ImportError::InvalidData)?
+            .parse::<f32>()
+            .or(Err(ImportError::InvalidData))
+    };
+    let parse_element = |element: &str| {
+        let tokens: Vec<u32> = element
+            .split('/')
+            .map(|token| token.parse::<u32>().unwrap_or(0))
+            .collect();
+        FaceElement::new(tokens[0], tokens[2], tokens[1])
+    };
+
+    let mut meshes: Vec<ObjMesh> = vec![];
+    for line in text.lines() {
+        let mut tokens = line.split_ascii_whitespace();
+        let cmd = tokens.next().unwrap_or("");
+
+        match cmd {
+            "o" => {
+                let mut mesh = ObjMesh::empty();
+                mesh.set_name(line.trim_start()[cmd.len()..].trim());
+                meshes.push(mesh);
+            }
+            "v" => {
+                let x = parse_float(&mut tokens)?;
+                let y = parse_float(&mut tokens)?;
+                let z = parse_float(&mut tokens)?;
+                meshes
+                    .last_mut()
+                    .ok_or(ImportError::InvalidData)?
+                    .load_position(Vector3::new(x, y, z));
+            }
+            "vn" => {
+                let x = parse_float(&mut tokens)?;
+                let y = parse_float(&mut tokens)?;
+                let z = parse_float(&mut tokens)?;
+                meshes
+                    .last_mut()
+                    .ok_or(ImportError::InvalidData)?
+                    .load_normal(Vector3::new(x, y, z));
+            }
+            "vt" => {
+                let x = parse_float(&mut tokens)?;
+                let y = parse_float(&mut tokens)?;
+                meshes
+                    .last_mut()
+                    .ok_or(ImportError::InvalidData)?
+                    .load_uv(Vector2 { x, y });
+            }
+            "f" => {
+                let face = Face::new(tokens.map(parse_element).collect());
+                meshes
+                    .last_mut()
+                    .ok_or(ImportError::InvalidData)?
+                    .load_face(face);
+            }
+            "#" | "" => (),
+            _ => return Err(ImportError::UnrecognisedToken(cmd.to_owned())),
+        }
+    }
+
+    Ok(meshes)
+}
+
+/// Runs `f` `runs` times, printing the fastest and median times, and
+/// returns the result of the last run along with the median time
+fn time<T>(name: &str, runs: usize, mut f: impl FnMut() -> T) -> (T, Duration) {
+    let mut result = None;
+    let mut times = vec![];
+    for _ in 0..runs.max(1) {
+        // drop the previous result first, so that runs start from the same state
+        drop(result.take());
+        let start = Instant::now();
+        result = Some(f());
+        times.push(start.elapsed());
+    }
+
+    times.sort();
+    let median = times[times.len() / 2];
+    println!(
+        "{:<26} {:>8.2?} median {:>8.2?} fastest",
+        name, median, times[0]
+    );
+    (result.unwrap(), median)
+}
+
+fn main() {
+    let triangles = std::env::var("SIMP_BENCH_TRIANGLES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_TRIANGLES);
+    let runs = std::env::var("SIMP_BENCH_RUNS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_RUNS);
+
+    let path = std::env::temp_dir().join(format!("suoi_simp_bench_{}.obj", triangles));
+    if !path.is_file() {
+        println!("generating {}", path.display());
+        generate(&path, triangles).expect("failed to write the benchmark mesh");
+    }
+    let size = std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+    println!(
+        "{} triangles, {:.1} MB, {} runs",
+        triangles,
+        size as f64 / 1e6,
+        runs
+    );
+
+    let (baseline, before) = time("first release", runs, || import_baseline(&path).unwrap());
+    let (obj, after) = time("Obj::import", runs, || Obj::import(&path).unwrap());
+    assert_eq!(baseline, *obj.meshes());
+    drop(baseline);
+
+    let mut options = ObjImportOptions::default();
+    options.set_memory_map(true);
+    options.set_threads(1);
+    let (_, mapped) = time("Obj::import (threads = 1)", runs, || {
+        Obj::import_with_options(&path, &options).unwrap()
+    });
+
+    // one thread per core, which is what threads = 0 picks
+    options.set_threads(0);
+    let (threaded_obj, threaded) = time("Obj::import (threads = 0)", runs, || {
+        Obj::import_with_options(&path, &options).unwrap()
+    });
+    assert_eq!(obj.meshes(), threaded_obj.meshes());
+
+    let cores = std::thread::available_parallelism().map_or(1, |count| count.get());
+    println!(
+        "speed-up: {:.2}x read, {:.2}x mapped, {:.2}x threaded",
+        before.as_secs_f64() / after.as_secs_f64(),
+        before.as_secs_f64() / mapped.as_secs_f64(),
+        before.as_secs_f64() / threaded.as_secs_f64()
+    );
+    println!(
+        "threads = 0 against threads = 1: {:.2}x, with {} threads available",
+        mapped.as_secs_f64() / threaded.as_secs_f64(),
+        cores
+    );
+}
@@ -0,0 +1,442 @@
+use std::borrow::Cow;
+
+use crate::Fs;
+use crate::ImportError;
+
+/// Exact powers of ten, the ones usable by the fast path of `Fs::parse_float_bytes`
+const POW10: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+/**
+`fs::ByteLines`
+---
+Iterator over the statements of a text file held as raw bytes,
+with the same rules as `Fs::parse_lines`: lines end with `\n` or
+`\r\n`, a `#` at the start of a line or after whitespace starts a
+comment and a trailing backslash continues the statement on the next
+line. A `#` right after a token is part of that token, so a comment
+needs whitespace before it: `v 1 2 3#note` is read as a vertex with
+the coordinate `3#note`, and fails to parse. Yields the 1-based number of the line each statement starts on,
+along with its bytes.
+*/
+#[derive(Debug, Clone)]
+pub struct ByteLines<'a> {
+    bytes: &'a [u8],
+    line: usize,
+}
+
+impl<'a> ByteLines<'a> {
+    /// Creates a `ByteLines` over `bytes`, skipping a leading UTF-8 byte order mark
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes: bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes),
+            line: 0,
+        }
+    }
+
+    /// Creates a `ByteLines` over part of a file, which starts at a statement boundary
+    pub(crate) fn part(bytes: &'a [u8]) -> Self {
+        Self { bytes, line: 0 }
+    }
+
+    /**
+    `ByteLines::split`
+    ---
+    Splits `bytes` into at most `count` parts of similar size that can be
+    parsed independently, after skipping a leading byte order mark. Parts
+    are only cut between statements, never inside a continued one.
+    */
+    pub fn split(bytes: &'a [u8], count: usize) -> Vec<&'a [u8]> {
+        let bytes = Self::new(bytes).bytes;
+        let count = count.max(1);
+        let mut parts = vec![];
+        let mut start = 0;
+
+        for part in 1..count {
+            let mut cut = (bytes.len() * part / count).max(start);
+            loop {
+                match bytes[cut..].iter().position(|&b| b == b'\n') {
+                    Some(end) => cut += end + 1,
+                    None => {
+                        cut = bytes.len();
+                        break;
+                    }
+                }
+                if !Self::continues(&bytes[start..cut]) {
+                    break;
+                }
+            }
+
+            if cut > start {
+                parts.push(&bytes[start..cut]);
+                start = cut;
+            }
+        }
+        parts.push(&bytes[start..]);
+
+        parts
+    }
+
+    /// Checks whether the last line of `bytes` continues on the next one
+    pub(crate) fn continues(bytes: &[u8]) -> bool {
+        let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+        let start = bytes
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |end| end + 1);
+
+        ByteLines::part(&bytes[start..])
+            .next_line()
+            .is_some_and(|line| line.trim_ascii_end().ends_with(b"\\"))
+    }
+
+    /// Returns the next physical line, without its line ending or comment
+    fn next_line(&mut self) -> Option<&'a [u8]> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        let mut line = self.bytes;
+        let mut comment = None;
+        self.bytes = &[];
+
+        let mut start = 0;
+        while let Some(found) = Self::find_line_end(&line[start..]) {
+            let at = start + found;
+            if line[at] == b'#' {
+                if comment.is_none() && Fs::starts_comment(line, at) {
+                    comment = Some(at);
+                }
+                start = at + 1;
+                continue;
+            }
+            self.bytes = &line[at + 1..];
+            line = &line[..at];
+            break;
+        }
+        self.line += 1;
+
+        let line = match comment {
+            Some(start) => line[..start].trim_ascii_end(),
+            None => line.strip_suffix(b"\r").unwrap_or(line),
+        };
+        Some(line)
+    }
+
+    /**
+    `ByteLines::find_line_end`
+    ---
+    Finds the first `\n` or `#` in `bytes`. Scans eight bytes at a
+    time, since this search touches every byte of the file.
+    */
+    pub(crate) fn find_line_end(bytes: &[u8]) -> Option<usize> {
+        const ONES: u64 = 0x0101_0101_0101_0101;
+        const HIGHS: u64 = 0x8080_8080_8080_8080;
+        let has_byte = |word: u64, byte: u8| {
+            let x = word ^ (ONES * byte as u64);
+            x.wrapping_sub(ONES) & !x & HIGHS
+        };
+
+        let mut chunks = bytes.chunks_exact(8);
+        let mut offset = 0;
+        for chunk in &mut chunks {
+            let word = u64::from_le_bytes(chunk.try_into().unwrap());
+            let found = has_byte(word, b'\n') | has_byte(word, b'#');
+            if found != 0 {
+                return Some(offset + found.trailing_zeros() as usize / 8);
+            }
+            offset += 8;
+        }
+
+        chunks
+            .remainder()
+            .iter()
+            .position(|&b| b == b'\n' || b == b'#')
+            .map(|index| offset + index)
+    }
+}
+
+impl<'a> Iterator for ByteLines<'a> {
+    type Item = (usize, Cow<'a, [u8]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = Cow::Borrowed(self.next_line()?);
+        let number = self.line;
+
+        // join continuation lines, replacing the backslash with a space
+        while let Some(head) = line.trim_ascii_end().strip_suffix(b"\\") {
+            let mut joined = head.to_vec();
+            joined.push(b' ');
+            if let Some(next) = self.next_line() {
+                joined.extend_from_slice(next);
+            }
+            line = Cow::Owned(joined);
+        }
+
+        Some((number, line))
+    }
+}
+
+/**
+`fs::ByteTokens`
+---
+Splits a statement into its ASCII whitespace separated tokens,
+the byte counterpart of `fs::Tokens`
+*/
+#[derive(Debug, Clone)]
+pub struct ByteTokens<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteTokens<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /**
+    `ByteTokens.remainder`
+    ---
+    Returns the rest of the statement, from the start of the next token
+    to the end of the line, or `None` if there are no tokens left
+    */
+    pub fn remainder(&self) -> Option<&'a [u8]> {
+        match self.bytes.trim_ascii_start() {
+            [] => None,
+            rest => Some(rest),
+        }
+    }
+
+    /**
+    `ByteTokens.next_float`
+    ---
+    Parses the next token as a float, like `Fs::parse_float_bytes`.
+    Plain decimals are converted in the same pass that finds the end
+    of the token, since this runs for every number of the file.
+    */
+    pub fn next_float(&mut self) -> Result<f32, ImportError> {
+        let bytes = self.bytes.trim_ascii_start();
+        if let Some((value, end)) = Fs::fast_float(bytes) {
+            if bytes.get(end).is_none_or(|b| b.is_ascii_whitespace()) {
+                self.bytes = &bytes[end..];
+                return Ok(value);
+            }
+        }
+
+        Fs::parse_float_bytes(self.next().ok_or(ImportError::InvalidData)?)
+    }
+}
+
+impl<'a> Iterator for ByteTokens<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.bytes.trim_ascii_start();
+        if bytes.is_empty() {
+            self.bytes = bytes;
+            return None;
+        }
+
+        let end = bytes
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .unwrap_or(bytes.len());
+        self.bytes = &bytes[end..];
+        Some(&bytes[..end])
+    }
+}
+
+impl Fs {
+    /**
+    Fs::decode_line
+    ---
+    Decodes a single statement as UTF-8, or as Latin-1 if it
+    isn't valid UTF-8, like `Fs::decode_text` does for whole files
+    */
+    pub fn decode_line(bytes: &[u8]) -> Cow<'_, str> {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(_) => Cow::Owned(bytes.iter().map(|&b| b as char).collect()),
+        }
+    }
+
+    /**
+    Fs::parse_float_bytes
+    ---
+    Parses `token` as a float, accepting the same syntax as `str::parse`.
+    Plain decimals of up to 15 significant digits, which is what mesh
+    files are made of, are converted directly with exact arithmetic;
+    anything else goes through `str::parse`. The result is correctly
+    rounded either way.
+    */
+    pub fn parse_float_bytes(token: &[u8]) -> Result<f32, ImportError> {
+        match Self::fast_float(token) {
+            Some((value, end)) if end == token.len() => Ok(value),
+            _ => std::str::from_utf8(token)
+                .or(Err(ImportError::InvalidData))?
+                .parse::<f32>()
+                .or(Err(ImportError::InvalidData)),
+        }
+    }
+
+    /**
+    Fs::fast_float
+    ---
+    The fast path of `Fs::parse_float_bytes`, which reads the number at the
+    start of `bytes` and returns it with the number of bytes it spans.
+    The significand and the power of ten are both exact in an `f64`, so
+    the `f64` product is correctly rounded; rounding that again to `f32`
+    is only wrong when the product lands exactly halfway between two
+    `f32`s, which is left to `str::parse`.
+    */
+    fn fast_float(bytes: &[u8]) -> Option<(f32, usize)> {
+        let (negative, digits) = match bytes.first()? {
+            b'-' => (true, &bytes[1..]),
+            b'+' => (false, &bytes[1..]),
+            _ => (false, bytes),
+        };
+
+        let mut mantissa: u64 = 0;
+        let mut significant = 0;
+        let mut exponent: i32 = 0;
+        let mut any_digit = false;
+        let mut i = 0;
+
+        while let Some(&b) = digits.get(i).filter(|b| b.is_ascii_digit()) {
+            mantissa = mantissa.wrapping_mul(10) + (b - b'0') as u64;
+            significant += (mantissa != 0) as u32;
+            any_digit = true;
+            i += 1;
+        }
+        if digits.get(i) == Some(&b'.') {
+            i += 1;
+            while let Some(&b) = digits.get(i).filter(|b| b.is_ascii_digit()) {
+                mantissa = mantissa.wrapping_mul(10) + (b - b'0') as u64;
+                significant += (mantissa != 0) as u32;
+                exponent -= 1;
+                any_digit = true;
+                i += 1;
+            }
+        }
+        if matches!(digits.get(i), Some(b'e' | b'E')) {
+            i += 1;
+            let sign = match digits.get(i) {
+                Some(b'-') => {
+                    i += 1;
+                    -1
+                }
+                Some(b'+') => {
+                    i += 1;
+                    1
+                }
+                _ => 1,
+            };
+            let start = i;
+            let mut value: i32 = 0;
+            while let Some(&b) = digits.get(i).filter(|b| b.is_ascii_digit()) {
+                value = value.saturating_mul(10).saturating_add((b - b'0') as i32);
+                i += 1;
+            }
+            if i == start {
+                return None;
+            }
+            exponent = exponent.saturating_add(sign * value);
+        }
+
+        // anything unusual, such as `inf` or a long significand, takes the slow path
+        if !any_digit || significant > 15 || exponent.unsigned_abs() > 22 {
+            return None;
+        }
+
+        let value = match exponent < 0 {
+            true => mantissa as f64 / POW10[exponent.unsigned_abs() as usize],
+            false => mantissa as f64 * POW10[exponent as usize],
+        };
+
+        // the product must be a normal f32 that isn't a rounding midpoint
+        let bits = value.to_bits();
+        if value != 0.0 && (value < f32::MIN_POSITIVE as f64 || bits & 0x1FFF_FFFF == 0x1000_0000) {
+            return None;
+        }
+
+        let value = value as f32;
+        let end = bytes.len() - digits.len() + i;
+        Some((if negative { -value } else { value }, end))
+    }
+
+    /**
+    Fs::parse_uint_bytes
+    ---
+    Parses `token` as an unsigned integer,
+    the byte counterpart of `Fs::parse_uint`
+    */
+    pub fn parse_uint_bytes(token: &[u8]) -> Result<u32, ImportError> {
+        let digits = token.strip_prefix(b"+").unwrap_or(token);
+        if digits.is_empty() {
+            return Err(ImportError::InvalidData);
+        }
+
+        let mut value: u32 = 0;
+        for &b in digits {
+            let digit = b.wrapping_sub(b'0');
+            if digit > 9 {
+                return Err(ImportError::InvalidData);
+            }
+            value = value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(digit as u32))
+                .ok_or(ImportError::InvalidData)?;
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statements(text: &str) -> Vec<String> {
+        ByteLines::new(text.as_bytes())
+            .map(|(_, line)| String::from_utf8(line.into_owned()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_hashes_inside_tokens() {
+        assert_eq!(
+            statements("o Part#2\nnewmtl Mat#1\nmap_Kd tex#1.png\n"),
+            vec!["o Part#2", "newmtl Mat#1", "map_Kd tex#1.png"]
+        );
+        assert_eq!(statements("v 1 2 3#note\n"), vec!["v 1 2 3#note"]);
+    }
+
+    #[test]
+    fn drops_comments() {
+        assert_eq!(
+            statements("#comment\nv 1 2 3 # note\r\nv 4 5 6\t#note\n"),
+            vec!["", "v 1 2 3", "v 4 5 6"]
+        );
+    }
+
+    #[test]
+    fn matches_parse_lines() {
+        let text = "#comment\no Part#2 # note\nusemtl Mat#1\nf 1 2 \\\n 3 #tail\n";
+        let mut parsed = vec![];
+        Fs::parse_lines(text.to_owned(), |tokens, cmd| {
+            let mut statement = vec![cmd];
+            statement.extend(tokens);
+            parsed.push(statement.join(" "));
+            Ok(())
+        })
+        .unwrap();
+
+        let read: Vec<String> = statements(text)
+            .iter()
+            .map(|line| line.split_ascii_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(read, parsed[..read.len()]);
+        assert_eq!(read, vec!["", "o Part#2", "usemtl Mat#1", "f 1 2 3"]);
+    }
+}
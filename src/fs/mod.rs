@@ -1,11 +1,53 @@
-use std::{fs::File, io::Read};
+use std::{borrow::Cow, fs::File, io::Read, ops::Deref};
 
 use crate::ImportError;
 
+pub mod byte_lines;
+pub use byte_lines::*;
+
 pub type Path = std::path::Path;
 pub type PathBuf = std::path::PathBuf;
 pub type Tokens<'a> = std::str::SplitAsciiWhitespace<'a>;
 
+/**
+`fs::FloatFormat`
+---
+Controls how floats are written by the exporters.
+`Shortest` writes the shortest text that parses back
+to the exact same value, `Fixed` writes a fixed
+number of decimal places
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatFormat {
+    #[default]
+    Shortest,
+    Fixed(usize),
+}
+
+/**
+`fs::FileData`
+---
+Contents of a file loaded by `Fs::load_bytes`,
+either read into memory or memory-mapped
+*/
+pub enum FileData {
+    Read(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Deref for FileData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileData::Read(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            FileData::Mapped(map) => map,
+        }
+    }
+}
+
 pub struct Fs;
 
 impl Fs {
@@ -21,16 +63,44 @@ impl Fs {
         }
     }
 
+    /**
+    Fs::create_file
+    ---
+    Wrapper for File::create
+    */
+    pub fn create_file(path: &Path) -> Result<File, ImportError> {
+        match File::create(path) {
+            Ok(file) => Ok(file),
+            Err(_) => Err(ImportError::InvalidPath(path.to_owned())),
+        }
+    }
+
     /**
     Fs::read_file
     ---
-    Wrapper for File::read_to_string
+    Reads the whole file as text, see `Fs::decode_text`
     */
     pub fn read_file(file: &mut File) -> Result<String, ImportError> {
-        let mut t = String::new();
-        match file.read_to_string(&mut t) {
-            Ok(_) => Ok(t),
-            Err(_) => Err(ImportError::InvalidData),
+        Ok(Self::decode_text(Self::read_bytes(file)?))
+    }
+
+    /**
+    Fs::decode_text
+    ---
+    Decodes `bytes` as UTF-8, dropping a leading byte order mark.
+    Files that aren't valid UTF-8 are decoded as Latin-1 instead,
+    so that names written by older tools in a legacy encoding
+    still load, with every byte mapped to the matching character.
+    */
+    pub fn decode_text(bytes: Vec<u8>) -> String {
+        let bytes = match bytes.strip_prefix(b"\xEF\xBB\xBF") {
+            Some(rest) => rest.to_vec(),
+            None => bytes,
+        };
+
+        match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(err) => err.into_bytes().iter().map(|&b| b as char).collect(),
         }
     }
 
@@ -47,6 +117,31 @@ impl Fs {
         }
     }
 
+    /**
+    Fs::load_bytes
+    ---
+    Loads the contents of `file`, memory-mapping it when `memory_map`
+    is set and the crate is built with the `mmap` feature, and reading
+    it into memory otherwise. Mapping avoids copying very large files,
+    but the file must not be modified while the `FileData` is alive.
+    */
+    pub fn load_bytes(file: &mut File, memory_map: bool) -> Result<FileData, ImportError> {
+        #[cfg(feature = "mmap")]
+        if memory_map {
+            // SAFETY: the map is read-only, and concurrent modification
+            // of the file is documented as unsupported above
+            return match unsafe { memmap2::Mmap::map(&*file) } {
+                Ok(map) => Ok(FileData::Mapped(map)),
+                Err(_) => Err(ImportError::InvalidData),
+            };
+        }
+
+        #[cfg(not(feature = "mmap"))]
+        let _ = memory_map;
+
+        Ok(FileData::Read(Self::read_bytes(file)?))
+    }
+
     /**
     Fs::parse_float
     ---
@@ -62,6 +157,18 @@ impl Fs {
             .or(Err(ImportError::InvalidData))
     }
 
+    /**
+    Fs::format_float
+    ---
+    Formats `value` as text according to `format`
+    */
+    pub fn format_float(value: f32, format: FloatFormat) -> String {
+        match format {
+            FloatFormat::Shortest => format!("{}", value),
+            FloatFormat::Fixed(precision) => format!("{:.*}", precision, value),
+        }
+    }
+
     pub fn parse_uint(tokens: &mut Tokens) -> Result<u32, ImportError> {
         tokens
             .next()
@@ -70,17 +177,116 @@ impl Fs {
             .or(Err(ImportError::InvalidData))
     }
 
+    /**
+    Fs::parse_lines
+    ---
+    Splits `text` into statements and calls `f` with the tokens
+    and command token of each one. Lines may end with `\n` or `\r\n`,
+    and a line ending with a backslash continues on the next one.
+    A `#` that starts a line or follows whitespace begins a comment,
+    which is dropped up to the end of its line; a `#` inside a token,
+    as in `newmtl Mat#1`, is kept.
+    Errors returned by `f` are wrapped in `ImportError::AtLine`
+    with the number of the line the statement starts on.
+    */
     pub fn parse_lines<F>(text: String, mut f: F) -> Result<(), ImportError>
     where
         F: FnMut(Tokens, &str) -> Result<(), ImportError>,
     {
-        for line in text.lines() {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+        let mut lines = text.split('\n').enumerate();
+
+        while let Some((index, line)) = lines.next() {
+            let mut line = Cow::Borrowed(Self::strip_comment(line));
+
+            // join continuation lines, replacing the backslash with a space
+            while let Some(head) = line.trim_end().strip_suffix('\\') {
+                let mut joined = head.to_owned();
+                joined.push(' ');
+                if let Some((_, next)) = lines.next() {
+                    joined.push_str(Self::strip_comment(next));
+                }
+                line = Cow::Owned(joined);
+            }
+
             let mut tokens = line.split_ascii_whitespace();
-            let cmd_token = tokens.next().or(Some("")).unwrap();
+            let cmd_token = tokens.next().unwrap_or("");
 
-            (f)(tokens, cmd_token)?;
+            (f)(tokens, cmd_token).map_err(|err| ImportError::AtLine(index + 1, Box::new(err)))?;
         }
 
         Ok(())
     }
+
+    /// Drops the line ending and any `#` comment from `line`
+    fn strip_comment(line: &str) -> &str {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match line
+            .bytes()
+            .enumerate()
+            .position(|(at, _)| Self::starts_comment(line.as_bytes(), at))
+        {
+            Some(start) => line[..start].trim_end(),
+            None => line,
+        }
+    }
+
+    /**
+    `Fs::starts_comment`
+    ---
+    Whether the byte at `at` of `line` is a `#` that begins a comment,
+    which it does at the start of the line or after whitespace
+    */
+    pub(crate) fn starts_comment(line: &[u8], at: usize) -> bool {
+        line[at] == b'#' && (at == 0 || line[at - 1].is_ascii_whitespace())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statements(text: &str) -> Result<Vec<(String, Vec<String>)>, ImportError> {
+        let mut parsed = vec![];
+        Fs::parse_lines(text.to_owned(), |tokens, cmd| {
+            parsed.push((cmd.to_owned(), tokens.map(str::to_owned).collect()));
+            Ok(())
+        })?;
+        Ok(parsed)
+    }
+
+    #[test]
+    fn decodes_utf8_without_its_byte_order_mark() {
+        let text = Fs::decode_text(b"\xEF\xBB\xBFnewmtl caf\xC3\xA9\n".to_vec());
+        assert_eq!(text, "newmtl café\n");
+        assert_eq!(statements(&text).unwrap()[0].0, "newmtl");
+    }
+
+    #[test]
+    fn decodes_invalid_utf8_as_latin1() {
+        let text = Fs::decode_text(b"newmtl caf\xE9\n".to_vec());
+        assert_eq!(text, "newmtl café\n");
+        assert_eq!(statements(&text).unwrap()[0].1, vec!["café"]);
+    }
+
+    #[test]
+    fn joins_continued_lines_ending_with_crlf() {
+        let parsed = statements("f 1 2 \\\r\n  3 4\r\ng top\r\n").unwrap();
+        assert_eq!(parsed[0].0, "f");
+        assert_eq!(parsed[0].1, ["1", "2", "3", "4"]);
+        assert_eq!(parsed[1], ("g".to_owned(), vec!["top".to_owned()]));
+    }
+
+    #[test]
+    fn reports_the_line_a_statement_starts_on() {
+        let text = "o a\nf 1 \\\n2 \\\n3\nbad statement\n".to_owned();
+        let result = Fs::parse_lines(text, |_, cmd| match cmd {
+            "bad" => Err(ImportError::InvalidData),
+            _ => Ok(()),
+        });
+        match result {
+            Err(ImportError::AtLine(line, _)) => assert_eq!(line, 5),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
 }
@@ -12,6 +12,8 @@ pub use png::*;
 pub mod fs;
 pub use fs::*;
 
+mod math;
+
 /**
 crate::ImportError
 ---
@@ -24,6 +26,22 @@ pub enum ImportError {
     InvalidData,
     InvalidPath(PathBuf),
     UnrecognisedToken(String),
+    WriteFailed(PathBuf),
+    AtLine(usize, Box<ImportError>),
+}
+
+/**
+crate::ImportWarning
+---
+Problems that an importer recovered from,
+reported alongside the imported resource
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportWarning {
+    MissingMaterialLibrary(PathBuf),
+    UnknownMaterial(String),
+    SpectralColor(String),
+    VertexColorsIgnored,
 }
 
 pub trait Resource {
@@ -31,3 +49,7 @@ pub trait Resource {
     where
         Self: Sized;
 }
+
+pub trait Export {
+    fn export(&self, path: &Path, format: FloatFormat) -> Result<(), ImportError>;
+}
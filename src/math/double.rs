@@ -0,0 +1,32 @@
+//! The vector helpers of `math` on `[f64; 3]` arrays,
+//! for the passes that can't afford the rounding of `f32`
+
+pub(crate) type Vec3d = [f64; 3];
+
+pub(crate) fn add(a: Vec3d, b: Vec3d) -> Vec3d {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn sub(a: Vec3d, b: Vec3d) -> Vec3d {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn scale(a: Vec3d, s: f64) -> Vec3d {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub(crate) fn dot(a: Vec3d, b: Vec3d) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: Vec3d, b: Vec3d) -> Vec3d {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn length(a: Vec3d) -> f64 {
+    dot(a, a).sqrt()
+}
@@ -0,0 +1,120 @@
+//! Small vector helpers on plain `[f32; 3]` arrays,
+//! shared by the geometry passes of the crate
+
+use std::collections::HashMap;
+
+use suoi_types::Vector3;
+
+pub(crate) mod double;
+
+pub(crate) type Vec3 = [f32; 3];
+
+pub(crate) fn from_vector(v: &Vector3) -> Vec3 {
+    [v.x, v.y, v.z]
+}
+
+pub(crate) fn to_vector(v: Vec3) -> Vector3 {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+pub(crate) fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn scale(a: Vec3, s: f32) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub(crate) fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn length(a: Vec3) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Returns `a` scaled to unit length, or `None` for a zero-length vector
+pub(crate) fn normalize(a: Vec3) -> Option<Vec3> {
+    let len = length(a);
+    if len > f32::EPSILON && len.is_finite() {
+        Some(scale(a, 1.0 / len))
+    } else {
+        None
+    }
+}
+
+/// Checks that every component of `a` is finite
+pub(crate) fn is_finite(a: Vec3) -> bool {
+    a.iter().all(|c| c.is_finite())
+}
+
+/**
+`math::weld`
+---
+Groups `points` that lie within `epsilon` of each other, using a spatial
+hash with cells of size `epsilon`. Returns the new index of every point:
+the first point of each group gets the next free index, and later points
+within `epsilon` of it share that index. Points that aren't finite are
+never welded. An `epsilon` of `0` only welds identical points.
+*/
+pub(crate) fn weld(points: &[Vec3], epsilon: f32) -> Vec<u32> {
+    let cell = |p: Vec3| match epsilon > 0.0 {
+        true => p.map(|c| (c / epsilon).floor() as i64),
+        false => p.map(|c| (c + 0.0).to_bits() as i64),
+    };
+
+    // representatives of each group, by cell
+    let mut cells: HashMap<[i64; 3], Vec<(Vec3, u32)>> = HashMap::new();
+    let mut map = Vec::with_capacity(points.len());
+    let mut count = 0;
+
+    for &p in points {
+        if !is_finite(p) {
+            map.push(count);
+            count += 1;
+            continue;
+        }
+
+        let [x, y, z] = cell(p);
+        let reach = (epsilon > 0.0) as i64;
+        let mut found = None;
+        'search: for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    let Some(points) = cells.get(&[x + dx, y + dy, z + dz]) else {
+                        continue;
+                    };
+                    if let Some(&(_, index)) =
+                        points.iter().find(|(q, _)| length(sub(p, *q)) <= epsilon)
+                    {
+                        found = Some(index);
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        match found {
+            Some(index) => map.push(index),
+            None => {
+                cells.entry([x, y, z]).or_default().push((p, count));
+                map.push(count);
+                count += 1;
+            }
+        }
+    }
+
+    map
+}
@@ -3,3 +3,11 @@ pub use mtl::*;
 
 pub mod mtl_material;
 pub use mtl_material::*;
+
+pub mod texture_map;
+pub use texture_map::*;
+
+pub mod pbr;
+pub use pbr::*;
+
+pub mod mtl_writer;
@@ -1,16 +1,74 @@
 use suoi_types::Vector3;
 
 use crate::Path;
-use crate::{Fs, ImportError, MtlMaterial, Resource};
+use crate::{Fs, ImportError, ImportWarning, MtlMaterial, Resource, TextureMap, Tokens};
 
-#[derive(Debug)]
+/**
+`mtl::MaterialHandle`
+---
+Index of a material inside an `Mtl` library,
+cheap to copy and store per mesh instead of
+a clone of the material itself
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialHandle(usize);
+
+impl MaterialHandle {
+    /// Returns the position of the material inside its library
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Mtl {
     materials: Vec<MtlMaterial>,
+    warnings: Vec<ImportWarning>,
 }
 
 impl Mtl {
     pub fn empty() -> Self {
-        Self { materials: vec![] }
+        Self {
+            materials: vec![],
+            warnings: vec![],
+        }
+    }
+
+    /// Immutable getter for `materials` of `Mtl`
+    pub fn materials(&self) -> &Vec<MtlMaterial> {
+        &self.materials
+    }
+
+    /// Immutable getter for `warnings` of `Mtl`
+    pub fn warnings(&self) -> &Vec<ImportWarning> {
+        &self.warnings
+    }
+
+    /**
+    `Mtl.take_warnings`
+    ---
+    Moves the warnings out of `self`, used when a library is merged
+    into an `Obj`, which reports them through `Obj::warnings` instead
+    */
+    pub(crate) fn take_warnings(&mut self) -> Vec<ImportWarning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /**
+    `Mtl.merge`
+    ---
+    Appends the materials of `other` to `self`.
+    When both libraries define a material with the same name,
+    the definition already in `self` wins and the one from
+    `other` is dropped, so the first library loaded takes priority.
+    */
+    pub fn merge(&mut self, other: Mtl) {
+        self.warnings.extend(other.warnings);
+        for mat in other.materials {
+            if self.get_material(mat.get_name()).is_none() {
+                self.materials.push(mat);
+            }
+        }
     }
 
     pub fn get_material(&self, material_name: &str) -> Option<&MtlMaterial> {
@@ -22,6 +80,88 @@ impl Mtl {
 
         None
     }
+
+    /**
+    `Mtl.get_handle`
+    ---
+    Returns the `MaterialHandle` of the first material named `material_name`
+    */
+    pub fn get_handle(&self, material_name: &str) -> Option<MaterialHandle> {
+        self.materials
+            .iter()
+            .position(|mat| mat.get_name() == material_name)
+            .map(MaterialHandle)
+    }
+
+    /// Returns the material referenced by `handle`, if it belongs to this library
+    pub fn get(&self, handle: MaterialHandle) -> Option<&MtlMaterial> {
+        self.materials.get(handle.0)
+    }
+
+    /// Returns the material referenced by `handle` for editing
+    pub fn get_mut(&mut self, handle: MaterialHandle) -> Option<&mut MtlMaterial> {
+        self.materials.get_mut(handle.0)
+    }
+
+    /**
+    `Mtl.add_material`
+    ---
+    Appends `material` to the library and returns its handle.
+    Unlike `Mtl.merge`, the material is added even if its name is taken.
+    */
+    pub fn add_material(&mut self, material: MtlMaterial) -> MaterialHandle {
+        self.materials.push(material);
+        MaterialHandle(self.materials.len() - 1)
+    }
+}
+
+impl Mtl {
+    /**
+    `Mtl::parse_color`
+    ---
+    Parses the arguments of a color statement: `r [g b]`, where `r` alone
+    is used for all three channels, or `xyz x [y z]`, converted to RGB.
+    Returns `None` for `spectral` curves, which are not supported.
+    */
+    fn parse_color(tokens: &mut Tokens) -> Result<Option<Vector3>, ImportError> {
+        match tokens.clone().next() {
+            Some("spectral") => return Ok(None),
+            Some("xyz") => {
+                tokens.next();
+                return Self::parse_rgb(tokens).map(|xyz| Some(Self::xyz_to_rgb(xyz)));
+            }
+            _ => (),
+        }
+
+        Self::parse_rgb(tokens).map(Some)
+    }
+
+    /// Parses `r [g b]`, using `r` for all three channels when it is alone
+    fn parse_rgb(tokens: &mut Tokens) -> Result<Vector3, ImportError> {
+        let r = Fs::parse_float(tokens)?;
+        if tokens.clone().next().is_none() {
+            return Ok(Vector3::new(r, r, r));
+        }
+
+        Ok(Vector3::new(
+            r,
+            Fs::parse_float(tokens)?,
+            Fs::parse_float(tokens)?,
+        ))
+    }
+
+    /**
+    `Mtl::xyz_to_rgb`
+    ---
+    Converts a CIE XYZ color to linear RGB with sRGB (D65) primaries
+    */
+    fn xyz_to_rgb(xyz: Vector3) -> Vector3 {
+        Vector3::new(
+            3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+            -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+            0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+        )
+    }
 }
 
 impl Resource for Mtl {
@@ -37,6 +177,7 @@ impl Resource for Mtl {
         let text = Fs::read_file(&mut file)?;
 
         let mut mats: Vec<MtlMaterial> = vec![];
+        let mut warnings: Vec<ImportWarning> = vec![];
 
         Fs::parse_lines(text, |mut tokens, cmd| {
             match cmd {
@@ -47,13 +188,68 @@ impl Resource for Mtl {
                     new_mat.set_name(name);
                     mats.push(new_mat);
                 }
+                "map_Ka" => {
+                    // define ambient texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_ambient_map(map);
+                }
                 "map_Kd" => {
-                    // define diffuse texmap path
-                    let map_path =
-                        Path::new(tokens.remainder().ok_or(ImportError::InvalidData)?).to_owned();
+                    // define diffuse texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_diffuse_map(map);
+                }
+                "map_Ks" => {
+                    // define specular color texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_specular_map(map);
+                }
+                "map_Ns" => {
+                    // define specular exponent texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_specular_exponent_map(map);
+                }
+                "map_Ke" => {
+                    // define emissive texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_emissive_map(map);
+                }
+                "map_d" => {
+                    // define alpha texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_alpha_map(map);
+                }
+                "map_bump" | "bump" => {
+                    // define bump map
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_bump_map(map);
+                }
+                "disp" => {
+                    // define displacement map
+                    let map = TextureMap::parse(&mut tokens)?;
                     mats.last_mut()
                         .ok_or(ImportError::InvalidData)?
-                        .set_diffuse_path(map_path);
+                        .set_displacement_map(map);
+                }
+                "decal" => {
+                    // define decal texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_decal_map(map);
                 }
                 "illum" => {
                     // define illumination model
@@ -63,39 +259,33 @@ impl Resource for Mtl {
                 }
                 "Ka" => {
                     // define ambient color
-                    let col = Vector3::new(
-                        Fs::parse_float(&mut tokens)?,
-                        Fs::parse_float(&mut tokens)?,
-                        Fs::parse_float(&mut tokens)?,
-                    );
-
-                    mats.last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .set_ambient_color(col);
+                    let mat = mats.last_mut().ok_or(ImportError::InvalidData)?;
+                    match Self::parse_color(&mut tokens)? {
+                        Some(col) => mat.set_ambient_color(col),
+                        None => {
+                            warnings.push(ImportWarning::SpectralColor(mat.get_name().to_owned()))
+                        }
+                    }
                 }
                 "Kd" => {
                     // define diffuse color
-                    let col = Vector3::new(
-                        Fs::parse_float(&mut tokens)?,
-                        Fs::parse_float(&mut tokens)?,
-                        Fs::parse_float(&mut tokens)?,
-                    );
-
-                    mats.last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .set_diffuse_color(col);
+                    let mat = mats.last_mut().ok_or(ImportError::InvalidData)?;
+                    match Self::parse_color(&mut tokens)? {
+                        Some(col) => mat.set_diffuse_color(col),
+                        None => {
+                            warnings.push(ImportWarning::SpectralColor(mat.get_name().to_owned()))
+                        }
+                    }
                 }
                 "Ks" => {
                     // define specular color
-                    let col = Vector3 {
-                        x: Fs::parse_float(&mut tokens)?,
-                        y: Fs::parse_float(&mut tokens)?,
-                        z: Fs::parse_float(&mut tokens)?,
-                    };
-
-                    mats.last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .set_specular_color(col);
+                    let mat = mats.last_mut().ok_or(ImportError::InvalidData)?;
+                    match Self::parse_color(&mut tokens)? {
+                        Some(col) => mat.set_specular_color(col),
+                        None => {
+                            warnings.push(ImportWarning::SpectralColor(mat.get_name().to_owned()))
+                        }
+                    }
                 }
                 "Ns" => {
                     // define specular exponent
@@ -105,15 +295,13 @@ impl Resource for Mtl {
                 }
                 "Ke" => {
                     // define emissive
-                    let col = Vector3::new(
-                        Fs::parse_float(&mut tokens)?,
-                        Fs::parse_float(&mut tokens)?,
-                        Fs::parse_float(&mut tokens)?,
-                    );
-
-                    mats.last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .set_emissive(col);
+                    let mat = mats.last_mut().ok_or(ImportError::InvalidData)?;
+                    match Self::parse_color(&mut tokens)? {
+                        Some(col) => mat.set_emissive(col),
+                        None => {
+                            warnings.push(ImportWarning::SpectralColor(mat.get_name().to_owned()))
+                        }
+                    }
                 }
                 "Ni" => {
                     // define optical density
@@ -122,13 +310,107 @@ impl Resource for Mtl {
                         .set_optical_density(Fs::parse_float(&mut tokens)?);
                 }
                 "d" => {
-                    // define transparency
+                    // define dissolve (opacity), optionally as a halo
+                    let mat = mats.last_mut().ok_or(ImportError::InvalidData)?;
+                    let halo = tokens.clone().next() == Some("-halo");
+                    if halo {
+                        tokens.next();
+                    }
+
+                    mat.set_dissolve_halo(halo);
+                    mat.set_opacity(Fs::parse_float(&mut tokens)?);
+                }
+                "Tr" => {
+                    // define transparency (inverse of dissolve)
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_transparency(Fs::parse_float(&mut tokens)?);
+                }
+                "Tf" => {
+                    // define transmission filter
+                    let mat = mats.last_mut().ok_or(ImportError::InvalidData)?;
+                    match Self::parse_color(&mut tokens)? {
+                        Some(col) => mat.set_transmission_filter(col),
+                        None => {
+                            warnings.push(ImportWarning::SpectralColor(mat.get_name().to_owned()))
+                        }
+                    }
+                }
+                "sharpness" => {
+                    // define reflection sharpness
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_sharpness(Fs::parse_float(&mut tokens)?);
+                }
+                "Pr" => {
+                    // define roughness
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_roughness(Fs::parse_float(&mut tokens)?);
+                }
+                "Pm" => {
+                    // define metallic
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_metallic(Fs::parse_float(&mut tokens)?);
+                }
+                "Ps" => {
+                    // define sheen
                     mats.last_mut()
                         .ok_or(ImportError::InvalidData)?
-                        .set_opacity(Fs::parse_float(&mut tokens)?);
+                        .set_sheen(Fs::parse_float(&mut tokens)?);
                 }
-                "#" => {
-                    // comment
+                "Pc" => {
+                    // define clearcoat thickness
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_clearcoat_thickness(Fs::parse_float(&mut tokens)?);
+                }
+                "Pcr" => {
+                    // define clearcoat roughness
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_clearcoat_roughness(Fs::parse_float(&mut tokens)?);
+                }
+                "aniso" => {
+                    // define anisotropy
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_anisotropy(Fs::parse_float(&mut tokens)?);
+                }
+                "anisor" => {
+                    // define anisotropy rotation
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_anisotropy_rotation(Fs::parse_float(&mut tokens)?);
+                }
+                "map_Pr" => {
+                    // define roughness texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_roughness_map(map);
+                }
+                "map_Pm" => {
+                    // define metallic texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_metallic_map(map);
+                }
+                "map_Ps" => {
+                    // define sheen texmap
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_sheen_map(map);
+                }
+                "norm" => {
+                    // define normal map
+                    let map = TextureMap::parse(&mut tokens)?;
+                    mats.last_mut()
+                        .ok_or(ImportError::InvalidData)?
+                        .set_normal_map(map);
                 }
                 "" => (),
                 _ => return Err(ImportError::UnrecognisedToken(cmd.to_owned())),
@@ -136,6 +418,69 @@ impl Resource for Mtl {
             Ok(())
         })?;
 
-        Ok(Self { materials: mats })
+        Ok(Self {
+            materials: mats,
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(name: &str, text: &str) -> Mtl {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, text).unwrap();
+        let mtl = Mtl::import(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        mtl
+    }
+
+    #[test]
+    fn converts_xyz_transmission_filter_to_rgb() {
+        let mtl = import(
+            "suoi_simp_tf_xyz.mtl",
+            "newmtl glass\nTf xyz 0.9505 1.0 1.089\n",
+        );
+        let filter = mtl.get_material("glass").unwrap().get_transmission_filter();
+        for channel in [filter.x, filter.y, filter.z] {
+            assert!((channel - 1.0).abs() < 1e-3);
+        }
+        assert!(mtl.warnings().is_empty());
+    }
+
+    #[test]
+    fn reports_spectral_transmission_filter() {
+        let mtl = import(
+            "suoi_simp_tf_spectral.mtl",
+            "newmtl glass\nTf spectral glass.rfl 1.0\n",
+        );
+        assert_eq!(
+            mtl.warnings(),
+            &vec![ImportWarning::SpectralColor("glass".to_owned())]
+        );
+    }
+
+    #[test]
+    fn parses_every_color_statement_alike() {
+        let mtl = import(
+            "suoi_simp_colors.mtl",
+            "newmtl paint\nKa 0.5\nKd 0.1 0.2 0.3\nKs xyz 0.9505 1.0 1.089\nKe spectral glow.rfl\nTf 0.25\n",
+        );
+        let mat = mtl.get_material("paint").unwrap();
+        let rgb = |color: &Vector3| [color.x, color.y, color.z];
+
+        assert_eq!(rgb(mat.get_ambient_color()), [0.5; 3]);
+        assert_eq!(rgb(mat.get_diffuse_color()), [0.1, 0.2, 0.3]);
+        assert!(rgb(mat.get_specular_color())
+            .iter()
+            .all(|c| (c - 1.0).abs() < 1e-3));
+        assert_eq!(rgb(mat.get_emissive()), [0.0; 3]);
+        assert_eq!(rgb(mat.get_transmission_filter()), [0.25; 3]);
+        assert_eq!(
+            mtl.warnings(),
+            &vec![ImportWarning::SpectralColor("paint".to_owned())]
+        );
     }
 }
@@ -1,8 +1,13 @@
 use suoi_types::Vector3 as Color;
 
-use crate::PathBuf;
+use crate::{math, PathBuf, TextureMap};
 
-#[allow(unused)]
+/**
+`mtl::MtlMaterial`
+---
+A single material of an `.mtl` library,
+declared by a `newmtl` statement
+*/
 #[derive(Debug, Clone)]
 pub struct MtlMaterial {
     // token: newmtl
@@ -19,8 +24,16 @@ pub struct MtlMaterial {
     // token: Ke
     emissive: Color,
 
-    // token: d
-    opacity: f32,
+    // tokens: d, Tr
+    dissolve: Option<f32>,
+    dissolve_halo: bool,
+    transparency: Option<f32>,
+
+    // token: Tf
+    transmission_filter: Color,
+
+    // token: sharpness
+    sharpness: f32,
 
     // token: Ni
     optical_density: f32,
@@ -28,13 +41,41 @@ pub struct MtlMaterial {
     // token: illum
     illumination_model: u32,
 
-    // tokens: mapd_Ka, map_Kd, map_d
-    ambient_texmap: Option<PathBuf>,
-    diffuse_texmap: Option<PathBuf>,
-    alpha_texmap: Option<PathBuf>,
+    // tokens: map_Ka, map_Kd, map_Ks, map_Ns, map_Ke, map_d
+    ambient_texmap: Option<TextureMap>,
+    diffuse_texmap: Option<TextureMap>,
+    specular_texmap: Option<TextureMap>,
+    specular_exponent_texmap: Option<TextureMap>,
+    emissive_texmap: Option<TextureMap>,
+    alpha_texmap: Option<TextureMap>,
+
+    // tokens: map_bump, bump, disp, decal
+    bump_texmap: Option<TextureMap>,
+    displacement_texmap: Option<TextureMap>,
+    decal_texmap: Option<TextureMap>,
+
+    // tokens: Pr, Pm, Ps
+    roughness: Option<f32>,
+    metallic: Option<f32>,
+    sheen: Option<f32>,
+
+    // tokens: Pc, Pcr
+    clearcoat_thickness: Option<f32>,
+    clearcoat_roughness: Option<f32>,
+
+    // tokens: aniso, anisor
+    anisotropy: Option<f32>,
+    anisotropy_rotation: Option<f32>,
+
+    // tokens: map_Pr, map_Pm, map_Ps, norm
+    roughness_texmap: Option<TextureMap>,
+    metallic_texmap: Option<TextureMap>,
+    sheen_texmap: Option<TextureMap>,
+    normal_texmap: Option<TextureMap>,
 }
 
 impl MtlMaterial {
+    /// Immutable getter for `name` of `MtlMaterial`
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -43,12 +84,226 @@ impl MtlMaterial {
         self.name = value.to_string();
     }
 
-    pub fn get_diffuse_path(&self) -> &Option<PathBuf> {
-        &self.diffuse_texmap
+    /// Immutable getter for `ambient_color` of `MtlMaterial`
+    pub fn get_ambient_color(&self) -> &Color {
+        &self.ambient_color
+    }
+
+    /// Immutable getter for `diffuse_color` of `MtlMaterial`
+    pub fn get_diffuse_color(&self) -> &Color {
+        &self.diffuse_color
+    }
+
+    /// Immutable getter for `specular_color` of `MtlMaterial`
+    pub fn get_specular_color(&self) -> &Color {
+        &self.specular_color
+    }
+
+    /// Immutable getter for `specular_exponent` of `MtlMaterial`
+    pub fn get_specular_exponent(&self) -> f32 {
+        self.specular_exponent
+    }
+
+    /// Immutable getter for `emissive` of `MtlMaterial`
+    pub fn get_emissive(&self) -> &Color {
+        &self.emissive
+    }
+
+    /// Immutable getter for `dissolve` of `MtlMaterial`
+    ///
+    /// Raw value of the `d` statement, see `get_opacity` for the resolved opacity
+    pub fn get_dissolve(&self) -> Option<f32> {
+        self.dissolve
+    }
+
+    /// Immutable getter for `dissolve_halo` of `MtlMaterial`
+    pub fn get_dissolve_halo(&self) -> bool {
+        self.dissolve_halo
+    }
+
+    /// Immutable getter for `transparency` of `MtlMaterial`
+    ///
+    /// Raw value of the `Tr` statement, see `get_opacity` for the resolved opacity
+    pub fn get_transparency(&self) -> Option<f32> {
+        self.transparency
+    }
+
+    /**
+    `MtlMaterial::get_opacity`
+    ---
+    Returns the final opacity of the material.
+    `d` takes priority over `Tr` when a material defines both,
+    since `d` is the statement the format specifies and `Tr`
+    is its inverse written by some exporters instead.
+    Without either statement the material is fully opaque.
+    */
+    pub fn get_opacity(&self) -> f32 {
+        match (self.dissolve, self.transparency) {
+            (Some(dissolve), _) => dissolve,
+            (None, Some(transparency)) => 1.0 - transparency,
+            (None, None) => 1.0,
+        }
+    }
+
+    /// Immutable getter for `transmission_filter` of `MtlMaterial`
+    pub fn get_transmission_filter(&self) -> &Color {
+        &self.transmission_filter
+    }
+
+    /// Immutable getter for `sharpness` of `MtlMaterial`
+    pub fn get_sharpness(&self) -> f32 {
+        self.sharpness
+    }
+
+    /// Immutable getter for `optical_density` of `MtlMaterial`
+    pub fn get_optical_density(&self) -> f32 {
+        self.optical_density
+    }
+
+    /// Immutable getter for `illumination_model` of `MtlMaterial`
+    pub fn get_illum(&self) -> u32 {
+        self.illumination_model
+    }
+
+    /// Immutable getter for `ambient_texmap` of `MtlMaterial`
+    pub fn get_ambient_map(&self) -> Option<&TextureMap> {
+        self.ambient_texmap.as_ref()
+    }
+
+    /// Immutable getter for `diffuse_texmap` of `MtlMaterial`
+    pub fn get_diffuse_map(&self) -> Option<&TextureMap> {
+        self.diffuse_texmap.as_ref()
+    }
+
+    /// Path of the `diffuse_texmap` of `MtlMaterial`, if it has one
+    pub fn get_diffuse_path(&self) -> Option<&PathBuf> {
+        self.diffuse_texmap.as_ref().map(|map| map.path())
+    }
+
+    /// Immutable getter for `specular_texmap` of `MtlMaterial`
+    pub fn get_specular_map(&self) -> Option<&TextureMap> {
+        self.specular_texmap.as_ref()
+    }
+
+    /// Immutable getter for `specular_exponent_texmap` of `MtlMaterial`
+    pub fn get_specular_exponent_map(&self) -> Option<&TextureMap> {
+        self.specular_exponent_texmap.as_ref()
+    }
+
+    /// Immutable getter for `emissive_texmap` of `MtlMaterial`
+    pub fn get_emissive_map(&self) -> Option<&TextureMap> {
+        self.emissive_texmap.as_ref()
+    }
+
+    /// Immutable getter for `alpha_texmap` of `MtlMaterial`
+    pub fn get_alpha_map(&self) -> Option<&TextureMap> {
+        self.alpha_texmap.as_ref()
+    }
+
+    /// Immutable getter for `bump_texmap` of `MtlMaterial`
+    pub fn get_bump_map(&self) -> Option<&TextureMap> {
+        self.bump_texmap.as_ref()
+    }
+
+    /// Immutable getter for `displacement_texmap` of `MtlMaterial`
+    pub fn get_displacement_map(&self) -> Option<&TextureMap> {
+        self.displacement_texmap.as_ref()
+    }
+
+    /// Immutable getter for `decal_texmap` of `MtlMaterial`
+    pub fn get_decal_map(&self) -> Option<&TextureMap> {
+        self.decal_texmap.as_ref()
+    }
+
+    /// Immutable getter for `roughness` of `MtlMaterial`
+    pub fn get_roughness(&self) -> Option<f32> {
+        self.roughness
+    }
+
+    /// Immutable getter for `metallic` of `MtlMaterial`
+    pub fn get_metallic(&self) -> Option<f32> {
+        self.metallic
+    }
+
+    /// Immutable getter for `sheen` of `MtlMaterial`
+    pub fn get_sheen(&self) -> Option<f32> {
+        self.sheen
+    }
+
+    /// Immutable getter for `clearcoat_thickness` of `MtlMaterial`
+    pub fn get_clearcoat_thickness(&self) -> Option<f32> {
+        self.clearcoat_thickness
+    }
+
+    /// Immutable getter for `clearcoat_roughness` of `MtlMaterial`
+    pub fn get_clearcoat_roughness(&self) -> Option<f32> {
+        self.clearcoat_roughness
+    }
+
+    /// Immutable getter for `anisotropy` of `MtlMaterial`
+    pub fn get_anisotropy(&self) -> Option<f32> {
+        self.anisotropy
     }
 
-    pub fn set_diffuse_path(&mut self, path: PathBuf) {
-        self.diffuse_texmap = Some(path);
+    /// Immutable getter for `anisotropy_rotation` of `MtlMaterial`
+    pub fn get_anisotropy_rotation(&self) -> Option<f32> {
+        self.anisotropy_rotation
+    }
+
+    /// Immutable getter for `roughness_texmap` of `MtlMaterial`
+    pub fn get_roughness_map(&self) -> Option<&TextureMap> {
+        self.roughness_texmap.as_ref()
+    }
+
+    /// Immutable getter for `metallic_texmap` of `MtlMaterial`
+    pub fn get_metallic_map(&self) -> Option<&TextureMap> {
+        self.metallic_texmap.as_ref()
+    }
+
+    /// Immutable getter for `sheen_texmap` of `MtlMaterial`
+    pub fn get_sheen_map(&self) -> Option<&TextureMap> {
+        self.sheen_texmap.as_ref()
+    }
+
+    /// Immutable getter for `normal_texmap` of `MtlMaterial`
+    pub fn get_normal_map(&self) -> Option<&TextureMap> {
+        self.normal_texmap.as_ref()
+    }
+
+    pub fn set_ambient_map(&mut self, map: TextureMap) {
+        self.ambient_texmap = Some(map);
+    }
+
+    pub fn set_diffuse_map(&mut self, map: TextureMap) {
+        self.diffuse_texmap = Some(map);
+    }
+
+    pub fn set_specular_map(&mut self, map: TextureMap) {
+        self.specular_texmap = Some(map);
+    }
+
+    pub fn set_specular_exponent_map(&mut self, map: TextureMap) {
+        self.specular_exponent_texmap = Some(map);
+    }
+
+    pub fn set_emissive_map(&mut self, map: TextureMap) {
+        self.emissive_texmap = Some(map);
+    }
+
+    pub fn set_alpha_map(&mut self, map: TextureMap) {
+        self.alpha_texmap = Some(map);
+    }
+
+    pub fn set_bump_map(&mut self, map: TextureMap) {
+        self.bump_texmap = Some(map);
+    }
+
+    pub fn set_displacement_map(&mut self, map: TextureMap) {
+        self.displacement_texmap = Some(map);
+    }
+
+    pub fn set_decal_map(&mut self, map: TextureMap) {
+        self.decal_texmap = Some(map);
     }
     
     pub fn set_ambient_color(&mut self, ambient_color: Color) {
@@ -64,7 +319,23 @@ impl MtlMaterial {
     }
 
     pub fn set_opacity(&mut self, value: f32) {
-        self.opacity = value;
+        self.dissolve = Some(value);
+    }
+
+    pub fn set_dissolve_halo(&mut self, value: bool) {
+        self.dissolve_halo = value;
+    }
+
+    pub fn set_transparency(&mut self, value: f32) {
+        self.transparency = Some(value);
+    }
+
+    pub fn set_transmission_filter(&mut self, value: Color) {
+        self.transmission_filter = value;
+    }
+
+    pub fn set_sharpness(&mut self, value: f32) {
+        self.sharpness = value;
     }
 
     pub fn set_specular_color(&mut self, value: Color) {
@@ -83,6 +354,65 @@ impl MtlMaterial {
         self.emissive = emissive;
     }
 
+    pub fn set_roughness(&mut self, value: f32) {
+        self.roughness = Some(value);
+    }
+
+    pub fn set_metallic(&mut self, value: f32) {
+        self.metallic = Some(value);
+    }
+
+    pub fn set_sheen(&mut self, value: f32) {
+        self.sheen = Some(value);
+    }
+
+    pub fn set_clearcoat_thickness(&mut self, value: f32) {
+        self.clearcoat_thickness = Some(value);
+    }
+
+    pub fn set_clearcoat_roughness(&mut self, value: f32) {
+        self.clearcoat_roughness = Some(value);
+    }
+
+    pub fn set_anisotropy(&mut self, value: f32) {
+        self.anisotropy = Some(value);
+    }
+
+    pub fn set_anisotropy_rotation(&mut self, value: f32) {
+        self.anisotropy_rotation = Some(value);
+    }
+
+    pub fn set_roughness_map(&mut self, map: TextureMap) {
+        self.roughness_texmap = Some(map);
+    }
+
+    pub fn set_metallic_map(&mut self, map: TextureMap) {
+        self.metallic_texmap = Some(map);
+    }
+
+    pub fn set_sheen_map(&mut self, map: TextureMap) {
+        self.sheen_texmap = Some(map);
+    }
+
+    pub fn set_normal_map(&mut self, map: TextureMap) {
+        self.normal_texmap = Some(map);
+    }
+
+    /**
+    `MtlMaterial::fallback`
+    ---
+    Creates the plain light grey material named `default`,
+    used in place of missing libraries and materials
+    */
+    pub fn fallback() -> Self {
+        let mut mat = Self::empty();
+        mat.set_name("default");
+        mat.set_ambient_color(Color::new(0.2, 0.2, 0.2));
+        mat.set_diffuse_color(Color::new(0.8, 0.8, 0.8));
+        mat.set_illum(1);
+        mat
+    }
+
     pub fn empty() -> Self {
         Self {
             name: String::new(),
@@ -91,12 +421,88 @@ impl MtlMaterial {
             specular_color: Default::default(),
             specular_exponent: 0.0,
             emissive: Default::default(),
-            opacity: 1.0,
+            dissolve: None,
+            dissolve_halo: false,
+            transparency: None,
+            transmission_filter: Color::new(1.0, 1.0, 1.0),
+            sharpness: 60.0,
             optical_density: 1.0,
             illumination_model: 0,
             ambient_texmap: None,
             diffuse_texmap: None,
+            specular_texmap: None,
+            specular_exponent_texmap: None,
+            emissive_texmap: None,
             alpha_texmap: None,
+            bump_texmap: None,
+            displacement_texmap: None,
+            decal_texmap: None,
+            roughness: None,
+            metallic: None,
+            sheen: None,
+            clearcoat_thickness: None,
+            clearcoat_roughness: None,
+            anisotropy: None,
+            anisotropy_rotation: None,
+            roughness_texmap: None,
+            metallic_texmap: None,
+            sheen_texmap: None,
+            normal_texmap: None,
         }
     }
 }
+
+impl PartialEq for MtlMaterial {
+    fn eq(&self, other: &Self) -> bool {
+        let colors = |mat: &Self| {
+            [
+                &mat.ambient_color,
+                &mat.diffuse_color,
+                &mat.specular_color,
+                &mat.emissive,
+                &mat.transmission_filter,
+            ]
+            .map(math::from_vector)
+        };
+        fn texmaps(mat: &MtlMaterial) -> [&Option<TextureMap>; 13] {
+            [
+                &mat.ambient_texmap,
+                &mat.diffuse_texmap,
+                &mat.specular_texmap,
+                &mat.specular_exponent_texmap,
+                &mat.emissive_texmap,
+                &mat.alpha_texmap,
+                &mat.bump_texmap,
+                &mat.displacement_texmap,
+                &mat.decal_texmap,
+                &mat.roughness_texmap,
+                &mat.metallic_texmap,
+                &mat.sheen_texmap,
+                &mat.normal_texmap,
+            ]
+        }
+        let scalars = |mat: &Self| {
+            [
+                mat.dissolve,
+                mat.transparency,
+                mat.roughness,
+                mat.metallic,
+                mat.sheen,
+                mat.clearcoat_thickness,
+                mat.clearcoat_roughness,
+                mat.anisotropy,
+                mat.anisotropy_rotation,
+            ]
+        };
+
+        self.name == other.name
+            && colors(self) == colors(other)
+            && self.specular_exponent == other.specular_exponent
+            && self.dissolve_halo == other.dissolve_halo
+            && self.sharpness == other.sharpness
+            && self.optical_density == other.optical_density
+            && self.illumination_model == other.illumination_model
+            && scalars(self) == scalars(other)
+            && texmaps(self) == texmaps(other)
+    }
+}
@@ -0,0 +1,218 @@
+use std::io::{BufWriter, Write};
+
+use suoi_types::Vector3;
+
+use crate::{Export, FloatFormat, Fs, ImportError, Mtl, MtlMaterial, Path, TextureMap};
+
+impl Mtl {
+    /**
+    `Mtl.write`
+    ---
+    Serializes every material of `self` as `.mtl` text into `out`.
+    The output can be read back with `Mtl::import`.
+    */
+    pub fn write<W: Write>(&self, out: &mut W, format: FloatFormat) -> std::io::Result<()> {
+        for (index, mat) in self.materials().iter().enumerate() {
+            if index > 0 {
+                writeln!(out)?;
+            }
+            mat.write(out, format)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Export for Mtl {
+    /**
+    `Mtl::export`
+    ---
+    Writes `self` to the `.mtl` file located at `path`
+    */
+    fn export(&self, path: &Path, format: FloatFormat) -> Result<(), ImportError> {
+        let mut out = BufWriter::new(Fs::create_file(path)?);
+        self.write(&mut out, format)
+            .and_then(|_| out.flush())
+            .or(Err(ImportError::WriteFailed(path.to_owned())))
+    }
+}
+
+impl MtlMaterial {
+    /**
+    `MtlMaterial.write`
+    ---
+    Serializes `self` as a `newmtl` block into `out`.
+    Statements that hold optional values are only written when set.
+    */
+    pub fn write<W: Write>(&self, out: &mut W, format: FloatFormat) -> std::io::Result<()> {
+        let float = |value: f32| Fs::format_float(value, format);
+        let color =
+            |value: &Vector3| format!("{} {} {}", float(value.x), float(value.y), float(value.z));
+
+        writeln!(out, "newmtl {}", self.get_name())?;
+        writeln!(out, "Ka {}", color(self.get_ambient_color()))?;
+        writeln!(out, "Kd {}", color(self.get_diffuse_color()))?;
+        writeln!(out, "Ks {}", color(self.get_specular_color()))?;
+        writeln!(out, "Ns {}", float(self.get_specular_exponent()))?;
+        writeln!(out, "Ke {}", color(self.get_emissive()))?;
+        writeln!(out, "Ni {}", float(self.get_optical_density()))?;
+
+        if let Some(dissolve) = self.get_dissolve() {
+            match self.get_dissolve_halo() {
+                true => writeln!(out, "d -halo {}", float(dissolve))?,
+                false => writeln!(out, "d {}", float(dissolve))?,
+            }
+        }
+        if let Some(transparency) = self.get_transparency() {
+            writeln!(out, "Tr {}", float(transparency))?;
+        }
+
+        writeln!(out, "Tf {}", color(self.get_transmission_filter()))?;
+        writeln!(out, "sharpness {}", float(self.get_sharpness()))?;
+        writeln!(out, "illum {}", self.get_illum())?;
+
+        let scalars = [
+            ("Pr", self.get_roughness()),
+            ("Pm", self.get_metallic()),
+            ("Ps", self.get_sheen()),
+            ("Pc", self.get_clearcoat_thickness()),
+            ("Pcr", self.get_clearcoat_roughness()),
+            ("aniso", self.get_anisotropy()),
+            ("anisor", self.get_anisotropy_rotation()),
+        ];
+        for (token, value) in scalars {
+            if let Some(value) = value {
+                writeln!(out, "{} {}", token, float(value))?;
+            }
+        }
+
+        let maps = [
+            ("map_Ka", self.get_ambient_map()),
+            ("map_Kd", self.get_diffuse_map()),
+            ("map_Ks", self.get_specular_map()),
+            ("map_Ns", self.get_specular_exponent_map()),
+            ("map_Ke", self.get_emissive_map()),
+            ("map_d", self.get_alpha_map()),
+            ("bump", self.get_bump_map()),
+            ("disp", self.get_displacement_map()),
+            ("decal", self.get_decal_map()),
+            ("map_Pr", self.get_roughness_map()),
+            ("map_Pm", self.get_metallic_map()),
+            ("map_Ps", self.get_sheen_map()),
+            ("norm", self.get_normal_map()),
+        ];
+        for (token, map) in maps {
+            if let Some(map) = map {
+                writeln!(out, "{} {}", token, map.to_statement(format))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TextureMap {
+    /**
+    `TextureMap.to_statement`
+    ---
+    Formats the arguments of a texture map statement:
+    every option that differs from its default, then the file name
+    */
+    pub fn to_statement(&self, format: FloatFormat) -> String {
+        let float = |value: f32| Fs::format_float(value, format);
+        let uvw =
+            |value: &Vector3| format!("{} {} {}", float(value.x), float(value.y), float(value.z));
+        let switch = |value: bool| if value { "on" } else { "off" };
+
+        let default = TextureMap::new(self.path().clone());
+        let mut options: Vec<String> = vec![];
+
+        if !Self::same_uvw(self.offset(), default.offset()) {
+            options.push(format!("-o {}", uvw(self.offset())));
+        }
+        if !Self::same_uvw(self.scale(), default.scale()) {
+            options.push(format!("-s {}", uvw(self.scale())));
+        }
+        if !Self::same_uvw(self.turbulence(), default.turbulence()) {
+            options.push(format!("-t {}", uvw(self.turbulence())));
+        }
+        if self.clamp() != default.clamp() {
+            options.push(format!("-clamp {}", switch(self.clamp())));
+        }
+        if self.blend_u() != default.blend_u() {
+            options.push(format!("-blendu {}", switch(self.blend_u())));
+        }
+        if self.blend_v() != default.blend_v() {
+            options.push(format!("-blendv {}", switch(self.blend_v())));
+        }
+        if self.color_correction() != default.color_correction() {
+            options.push(format!("-cc {}", switch(self.color_correction())));
+        }
+        if self.bump_multiplier() != default.bump_multiplier() {
+            options.push(format!("-bm {}", float(self.bump_multiplier())));
+        }
+        if self.value_base() != default.value_base() || self.value_gain() != default.value_gain() {
+            options.push(format!(
+                "-mm {} {}",
+                float(self.value_base()),
+                float(self.value_gain())
+            ));
+        }
+        if let Some(channel) = self.channel() {
+            options.push(format!("-imfchan {}", channel.token()));
+        }
+        if let Some(boost) = self.boost() {
+            options.push(format!("-boost {}", float(boost)));
+        }
+        if let Some(resolution) = self.resolution() {
+            options.push(format!("-texres {}", resolution));
+        }
+
+        options.push(self.path().to_string_lossy().into_owned());
+        options.join(" ")
+    }
+
+    fn same_uvw(a: &Vector3, b: &Vector3) -> bool {
+        a.x == b.x && a.y == b.y && a.z == b.z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resource;
+
+    const LIBRARY: &str = "\
+newmtl brick
+Ka 0.1 0.1 0.1
+Kd 0.8 0.3 0.2
+Ks 0.5 0.5 0.5
+Ns 96
+d -halo 0.75
+Tf 0.9 0.8 0.7
+Pr 0.4
+map_Kd -o 0.5 0.25 0 -s 2 2 1 -clamp on -imfchan r brick wall.png
+bump -bm 0.3 -mm 0.1 0.9 brick_height.png
+newmtl glass
+Kd 1 1 1
+Ni 1.5
+illum 7
+";
+
+    #[test]
+    fn round_trips_through_the_writer() {
+        let dir = std::env::temp_dir();
+        let source = dir.join("suoi_simp_mtl_round_trip_a.mtl");
+        let copy = dir.join("suoi_simp_mtl_round_trip_b.mtl");
+        std::fs::write(&source, LIBRARY).unwrap();
+
+        let first = Mtl::import(&source).unwrap();
+        first.export(&copy, FloatFormat::Shortest).unwrap();
+        let second = Mtl::import(&copy).unwrap();
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&copy).unwrap();
+
+        assert_eq!(first.materials().len(), 2);
+        assert_eq!(first.materials(), second.materials());
+    }
+}
@@ -0,0 +1,271 @@
+use suoi_types::Vector3 as Color;
+
+use crate::{MtlMaterial, TextureMap};
+
+/// Reflectance of a dielectric at normal incidence (4%),
+/// used as the zero point when estimating `metallic` from `Ks`
+const DIELECTRIC_SPECULAR: f32 = 0.04;
+
+/// Alpha cutoff used for materials with an alpha texmap but no blending
+const DEFAULT_ALPHA_CUTOFF: f32 = 0.5;
+
+/**
+`mtl::AlphaMode`
+---
+How the alpha of a `PbrMaterial` is meant to be rendered
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    /// alpha is ignored
+    Opaque,
+    /// fragments with alpha below the cutoff are discarded
+    Mask(f32),
+    /// alpha is blended with the background
+    Blend,
+}
+
+/**
+`mtl::PbrMaterial`
+---
+Metallic-roughness description of a material,
+derived from an `MtlMaterial` through `PbrMaterial::from`
+*/
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    name: String,
+
+    base_color: Color,
+    base_color_map: Option<TextureMap>,
+
+    metallic: f32,
+    metallic_map: Option<TextureMap>,
+
+    roughness: f32,
+    roughness_map: Option<TextureMap>,
+
+    normal_map: Option<TextureMap>,
+    bump_map: Option<TextureMap>,
+    bump_multiplier: f32,
+
+    emissive: Color,
+    emissive_map: Option<TextureMap>,
+
+    alpha: f32,
+    alpha_mode: AlphaMode,
+    alpha_map: Option<TextureMap>,
+}
+
+impl PbrMaterial {
+    /// Immutable getter for `name` of `PbrMaterial`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Immutable getter for `base_color` of `PbrMaterial`
+    pub fn base_color(&self) -> &Color {
+        &self.base_color
+    }
+
+    /// Immutable getter for `base_color_map` of `PbrMaterial`
+    pub fn base_color_map(&self) -> Option<&TextureMap> {
+        self.base_color_map.as_ref()
+    }
+
+    /// Immutable getter for `metallic` of `PbrMaterial`
+    pub fn metallic(&self) -> f32 {
+        self.metallic
+    }
+
+    /// Immutable getter for `metallic_map` of `PbrMaterial`
+    pub fn metallic_map(&self) -> Option<&TextureMap> {
+        self.metallic_map.as_ref()
+    }
+
+    /// Immutable getter for `roughness` of `PbrMaterial`
+    pub fn roughness(&self) -> f32 {
+        self.roughness
+    }
+
+    /// Immutable getter for `roughness_map` of `PbrMaterial`
+    pub fn roughness_map(&self) -> Option<&TextureMap> {
+        self.roughness_map.as_ref()
+    }
+
+    /// Immutable getter for `normal_map` of `PbrMaterial`
+    pub fn normal_map(&self) -> Option<&TextureMap> {
+        self.normal_map.as_ref()
+    }
+
+    /// Immutable getter for `bump_map` of `PbrMaterial`, a height map rather than a normal map
+    pub fn bump_map(&self) -> Option<&TextureMap> {
+        self.bump_map.as_ref()
+    }
+
+    /// Immutable getter for `bump_multiplier` of `PbrMaterial`, the `-bm` option of `bump_map`
+    pub fn bump_multiplier(&self) -> f32 {
+        self.bump_multiplier
+    }
+
+    /// Immutable getter for `emissive` of `PbrMaterial`
+    pub fn emissive(&self) -> &Color {
+        &self.emissive
+    }
+
+    /// Immutable getter for `emissive_map` of `PbrMaterial`
+    pub fn emissive_map(&self) -> Option<&TextureMap> {
+        self.emissive_map.as_ref()
+    }
+
+    /// Immutable getter for `alpha` of `PbrMaterial`
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Immutable getter for `alpha_mode` of `PbrMaterial`
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    /// Immutable getter for `alpha_map` of `PbrMaterial`
+    pub fn alpha_map(&self) -> Option<&TextureMap> {
+        self.alpha_map.as_ref()
+    }
+
+    /**
+    `PbrMaterial::roughness_from_exponent`
+    ---
+    Maps a Blinn-Phong specular exponent (`Ns`) to a perceptual
+    roughness. `alpha = sqrt(2 / (Ns + 2))` is the Beckmann or GGX
+    roughness giving a highlight of the same width, and the perceptual
+    roughness stored by metallic-roughness materials is `sqrt(alpha)`.
+    */
+    pub fn roughness_from_exponent(exponent: f32) -> f32 {
+        let alpha = (2.0 / (exponent.max(0.0) + 2.0)).sqrt();
+        alpha.sqrt().clamp(0.0, 1.0)
+    }
+
+    /**
+    `PbrMaterial::metallic_from_specular`
+    ---
+    Estimates `metallic` from the brightest channel of a specular
+    color (`Ks`): dielectric reflectance (4%) or less maps to `0`,
+    a fully white specular maps to `1`
+    */
+    pub fn metallic_from_specular(specular: &Color) -> f32 {
+        let peak = specular.x.max(specular.y).max(specular.z);
+        ((peak - DIELECTRIC_SPECULAR) / (1.0 - DIELECTRIC_SPECULAR)).clamp(0.0, 1.0)
+    }
+}
+
+impl From<&MtlMaterial> for PbrMaterial {
+    /**
+    `PbrMaterial::from`
+    ---
+    Converts a Phong `MtlMaterial` to a metallic-roughness description.
+
+    Explicit PBR extension statements always take priority:
+    `Pr`, `Pm`, `map_Pr` and `map_Pm` are used as-is when present.
+    Otherwise the values are derived from the illumination model:
+
+    - `illum 0` and `1` have no highlight: `roughness = 1`, `metallic = 0`
+    - `illum 2`, `4`, `6`, `7` and `9` (highlight and glass models):
+      `roughness` from `Ns`, `metallic = 0`
+    - `illum 3`, `5` and `8` (reflective models): `roughness` from `Ns`,
+      `metallic` estimated from `Ks`
+    - any other model is treated like `illum 2`
+
+    The base color is `Kd` with `map_Kd`, the emissive is `Ke` with `map_Ke`.
+    The normal map is `norm` only: `bump` is a height map, and is kept
+    apart along with its `-bm` multiplier.
+    The alpha mode is `Blend` when the opacity is below `1` or the
+    illumination model enables transparency (`4`, `6`, `7`, `9`),
+    `Mask` when only an alpha texmap is present, and `Opaque` otherwise.
+    */
+    fn from(mat: &MtlMaterial) -> Self {
+        let illum = mat.get_illum();
+
+        let roughness = match mat.get_roughness() {
+            Some(value) => value.clamp(0.0, 1.0),
+            None => match illum {
+                0 | 1 => 1.0,
+                _ => Self::roughness_from_exponent(mat.get_specular_exponent()),
+            },
+        };
+
+        let metallic = match mat.get_metallic() {
+            Some(value) => value.clamp(0.0, 1.0),
+            None => match illum {
+                3 | 5 | 8 => Self::metallic_from_specular(mat.get_specular_color()),
+                _ => 0.0,
+            },
+        };
+
+        let alpha = mat.get_opacity().clamp(0.0, 1.0);
+        let transparent_illum = matches!(illum, 4 | 6 | 7 | 9);
+        let alpha_mode = if alpha < 1.0 || transparent_illum {
+            AlphaMode::Blend
+        } else if mat.get_alpha_map().is_some() {
+            AlphaMode::Mask(DEFAULT_ALPHA_CUTOFF)
+        } else {
+            AlphaMode::Opaque
+        };
+
+        Self {
+            name: mat.get_name().to_owned(),
+            base_color: mat.get_diffuse_color().clone(),
+            base_color_map: mat.get_diffuse_map().cloned(),
+            metallic,
+            metallic_map: mat.get_metallic_map().cloned(),
+            roughness,
+            roughness_map: mat.get_roughness_map().cloned(),
+            normal_map: mat.get_normal_map().cloned(),
+            bump_map: mat.get_bump_map().cloned(),
+            bump_multiplier: mat.get_bump_map().map_or(1.0, |map| map.bump_multiplier()),
+            emissive: mat.get_emissive().clone(),
+            emissive_map: mat.get_emissive_map().cloned(),
+            alpha,
+            alpha_mode,
+            alpha_map: mat.get_alpha_map().cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathBuf;
+
+    #[test]
+    fn roughness_is_the_square_root_of_alpha() {
+        assert_eq!(PbrMaterial::roughness_from_exponent(0.0), 1.0);
+        for exponent in [2.0f32, 30.0, 250.0] {
+            let alpha: f32 = (2.0 / (exponent + 2.0)).sqrt();
+            let roughness = PbrMaterial::roughness_from_exponent(exponent);
+            assert!((roughness * roughness - alpha).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn bump_is_not_a_normal_map() {
+        let mut bump =
+            TextureMap::parse(&mut "-bm 0.4 height.png".split_ascii_whitespace()).unwrap();
+        let mut mat = MtlMaterial::empty();
+        mat.set_bump_map(bump.clone());
+
+        let pbr = PbrMaterial::from(&mat);
+        assert!(pbr.normal_map().is_none());
+        assert_eq!(
+            pbr.bump_map().map(|map| map.path()),
+            Some(&PathBuf::from("height.png"))
+        );
+        assert_eq!(pbr.bump_multiplier(), 0.4);
+
+        bump = TextureMap::new(PathBuf::from("normals.png"));
+        mat.set_normal_map(bump);
+        let pbr = PbrMaterial::from(&mat);
+        assert_eq!(
+            pbr.normal_map().map(|map| map.path()),
+            Some(&PathBuf::from("normals.png"))
+        );
+    }
+}
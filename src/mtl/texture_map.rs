@@ -0,0 +1,353 @@
+use suoi_types::Vector3;
+
+use crate::{math, Fs, ImportError, PathBuf, Tokens};
+
+/**
+`mtl::ImageChannel`
+---
+Channel of a texture image selected by the
+`-imfchan` option of a texture map statement
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageChannel {
+    Red,
+    Green,
+    Blue,
+    Matte,
+    Luminance,
+    Depth,
+}
+
+impl ImageChannel {
+    /**
+    `ImageChannel::parse`
+    ---
+    Converts an `-imfchan` argument (`r`, `g`, `b`, `m`, `l` or `z`)
+    into an `ImageChannel`
+    */
+    pub fn parse(token: &str) -> Result<Self, ImportError> {
+        match token {
+            "r" => Ok(Self::Red),
+            "g" => Ok(Self::Green),
+            "b" => Ok(Self::Blue),
+            "m" => Ok(Self::Matte),
+            "l" => Ok(Self::Luminance),
+            "z" => Ok(Self::Depth),
+            _ => Err(ImportError::InvalidData),
+        }
+    }
+
+    /// Returns the `-imfchan` argument that selects this channel
+    pub fn token(&self) -> &'static str {
+        match self {
+            Self::Red => "r",
+            Self::Green => "g",
+            Self::Blue => "b",
+            Self::Matte => "m",
+            Self::Luminance => "l",
+            Self::Depth => "z",
+        }
+    }
+}
+
+/**
+`mtl::TextureMap`
+---
+Describes a texture map statement (`map_Kd`, `bump`, `disp`, ...)
+of an `.mtl` file: the image file itself and the
+options that control how it is sampled
+*/
+#[derive(Debug, Clone)]
+pub struct TextureMap {
+    // image file, may contain spaces
+    path: PathBuf,
+
+    // options: -o, -s, -t
+    offset: Vector3,
+    scale: Vector3,
+    turbulence: Vector3,
+
+    // option: -clamp
+    clamp: bool,
+
+    // options: -blendu, -blendv
+    blend_u: bool,
+    blend_v: bool,
+
+    // option: -bm
+    bump_multiplier: f32,
+
+    // option: -mm
+    value_base: f32,
+    value_gain: f32,
+
+    // option: -imfchan
+    channel: Option<ImageChannel>,
+
+    // options: -boost, -texres, -cc
+    boost: Option<f32>,
+    resolution: Option<u32>,
+    color_correction: bool,
+}
+
+impl TextureMap {
+    /**
+    `TextureMap::new`
+    ---
+    Creates a `TextureMap` for the image at `path`
+    with every option set to its default value
+    */
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            offset: Vector3::new(0.0, 0.0, 0.0),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            turbulence: Vector3::new(0.0, 0.0, 0.0),
+            clamp: false,
+            blend_u: true,
+            blend_v: true,
+            bump_multiplier: 1.0,
+            value_base: 0.0,
+            value_gain: 1.0,
+            channel: None,
+            boost: None,
+            resolution: None,
+            color_correction: false,
+        }
+    }
+
+    /// Immutable getter for `path` of `TextureMap`
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Immutable getter for the `-o` option of `TextureMap`
+    pub fn offset(&self) -> &Vector3 {
+        &self.offset
+    }
+
+    /// Immutable getter for the `-s` option of `TextureMap`
+    pub fn scale(&self) -> &Vector3 {
+        &self.scale
+    }
+
+    /// Immutable getter for the `-t` option of `TextureMap`
+    pub fn turbulence(&self) -> &Vector3 {
+        &self.turbulence
+    }
+
+    /// Immutable getter for the `-clamp` option of `TextureMap`
+    pub fn clamp(&self) -> bool {
+        self.clamp
+    }
+
+    /// Immutable getter for the `-blendu` option of `TextureMap`
+    pub fn blend_u(&self) -> bool {
+        self.blend_u
+    }
+
+    /// Immutable getter for the `-blendv` option of `TextureMap`
+    pub fn blend_v(&self) -> bool {
+        self.blend_v
+    }
+
+    /// Immutable getter for the `-bm` option of `TextureMap`
+    pub fn bump_multiplier(&self) -> f32 {
+        self.bump_multiplier
+    }
+
+    /// Immutable getter for the `base` argument of the `-mm` option of `TextureMap`
+    pub fn value_base(&self) -> f32 {
+        self.value_base
+    }
+
+    /// Immutable getter for the `gain` argument of the `-mm` option of `TextureMap`
+    pub fn value_gain(&self) -> f32 {
+        self.value_gain
+    }
+
+    /// Immutable getter for the `-imfchan` option of `TextureMap`
+    pub fn channel(&self) -> Option<ImageChannel> {
+        self.channel
+    }
+
+    /// Immutable getter for the `-boost` option of `TextureMap`
+    pub fn boost(&self) -> Option<f32> {
+        self.boost
+    }
+
+    /// Immutable getter for the `-texres` option of `TextureMap`
+    pub fn resolution(&self) -> Option<u32> {
+        self.resolution
+    }
+
+    /// Immutable getter for the `-cc` option of `TextureMap`
+    pub fn color_correction(&self) -> bool {
+        self.color_correction
+    }
+
+    /**
+    `TextureMap::parse`
+    ---
+    Consumes the arguments of a texture map statement.
+    Every leading `-option` is parsed along with its values,
+    and whatever remains of the line is taken as the file name,
+    so that names containing spaces are kept intact.
+    */
+    pub fn parse(tokens: &mut Tokens) -> Result<Self, ImportError> {
+        let mut map = Self::new(PathBuf::new());
+
+        loop {
+            let rest = tokens.remainder().ok_or(ImportError::InvalidData)?.trim();
+            if !rest.starts_with('-') {
+                if rest.is_empty() {
+                    return Err(ImportError::InvalidData);
+                }
+                map.path = PathBuf::from(rest);
+                return Ok(map);
+            }
+
+            let option = tokens.next().ok_or(ImportError::InvalidData)?;
+            match option {
+                "-o" => map.offset = Self::parse_uvw(tokens, 0.0)?,
+                "-s" => map.scale = Self::parse_uvw(tokens, 1.0)?,
+                "-t" => map.turbulence = Self::parse_uvw(tokens, 0.0)?,
+                "-clamp" => map.clamp = Self::parse_switch(tokens)?,
+                "-blendu" => map.blend_u = Self::parse_switch(tokens)?,
+                "-blendv" => map.blend_v = Self::parse_switch(tokens)?,
+                "-cc" => map.color_correction = Self::parse_switch(tokens)?,
+                "-bm" => map.bump_multiplier = Fs::parse_float(tokens)?,
+                "-mm" => {
+                    map.value_base = Fs::parse_float(tokens)?;
+                    map.value_gain = Fs::parse_float(tokens)?;
+                }
+                "-imfchan" => {
+                    map.channel = Some(ImageChannel::parse(
+                        tokens.next().ok_or(ImportError::InvalidData)?,
+                    )?)
+                }
+                "-boost" => map.boost = Some(Fs::parse_float(tokens)?),
+                "-texres" => map.resolution = Some(Fs::parse_uint(tokens)?),
+                _ => return Err(ImportError::UnrecognisedToken(option.to_owned())),
+            }
+        }
+    }
+
+    /**
+    `TextureMap::parse_uvw`
+    ---
+    Parses the `u [v [w]]` arguments of the `-o`, `-s` and `-t` options.
+    `u` is required, `v` and `w` fall back to `default` when omitted.
+    */
+    fn parse_uvw(tokens: &mut Tokens, default: f32) -> Result<Vector3, ImportError> {
+        let u = Fs::parse_float(tokens)?;
+        let v = Self::parse_optional_float(tokens).unwrap_or(default);
+        let w = Self::parse_optional_float(tokens).unwrap_or(default);
+
+        Ok(Vector3::new(u, v, w))
+    }
+
+    /// Parses the `on` / `off` argument of a switch option
+    fn parse_switch(tokens: &mut Tokens) -> Result<bool, ImportError> {
+        match tokens.next() {
+            Some("on") => Ok(true),
+            Some("off") => Ok(false),
+            _ => Err(ImportError::InvalidData),
+        }
+    }
+
+    /// Consumes the next token only if it is a float
+    fn parse_optional_float(tokens: &mut Tokens) -> Option<f32> {
+        let value = tokens.clone().next()?.parse::<f32>().ok()?;
+        tokens.next();
+        Some(value)
+    }
+}
+
+impl PartialEq for TextureMap {
+    fn eq(&self, other: &Self) -> bool {
+        let vectors =
+            |map: &Self| [&map.offset, &map.scale, &map.turbulence].map(math::from_vector);
+
+        self.path == other.path
+            && vectors(self) == vectors(other)
+            && self.clamp == other.clamp
+            && self.blend_u == other.blend_u
+            && self.blend_v == other.blend_v
+            && self.bump_multiplier == other.bump_multiplier
+            && self.value_base == other.value_base
+            && self.value_gain == other.value_gain
+            && self.channel == other.channel
+            && self.boost == other.boost
+            && self.resolution == other.resolution
+            && self.color_correction == other.color_correction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(arguments: &str) -> Result<TextureMap, ImportError> {
+        TextureMap::parse(&mut arguments.split_ascii_whitespace())
+    }
+
+    #[test]
+    fn parses_options_before_the_file_name() {
+        let map = parse(
+            "-o 0.5 0.25 -s 2 -t 1 2 3 -clamp on -blendu off -bm 0.3 -mm 0.1 0.9 \
+             -imfchan l -boost 1.5 -texres 512 -cc on bricks.png",
+        )
+        .unwrap();
+
+        assert_eq!(map.path(), &PathBuf::from("bricks.png"));
+        assert_eq!(
+            (map.offset().x, map.offset().y, map.offset().z),
+            (0.5, 0.25, 0.0)
+        );
+        assert_eq!(
+            (map.scale().x, map.scale().y, map.scale().z),
+            (2.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            (map.turbulence().x, map.turbulence().y, map.turbulence().z),
+            (1.0, 2.0, 3.0)
+        );
+        assert!(map.clamp());
+        assert!(!map.blend_u());
+        assert!(map.blend_v());
+        assert!(map.color_correction());
+        assert_eq!(map.bump_multiplier(), 0.3);
+        assert_eq!((map.value_base(), map.value_gain()), (0.1, 0.9));
+        assert_eq!(map.channel(), Some(ImageChannel::Luminance));
+        assert_eq!(map.boost(), Some(1.5));
+        assert_eq!(map.resolution(), Some(512));
+    }
+
+    #[test]
+    fn keeps_spaces_in_file_names() {
+        let map = parse("-bm 2 my textures/old brick.png").unwrap();
+        assert_eq!(map.path(), &PathBuf::from("my textures/old brick.png"));
+
+        let map = parse("plain name.tga").unwrap();
+        assert_eq!(map.path(), &PathBuf::from("plain name.tga"));
+        assert_eq!(map.bump_multiplier(), 1.0);
+    }
+
+    #[test]
+    fn requires_option_arguments() {
+        assert!(parse("-mm 0.2 bricks.png").is_err());
+        assert!(parse("-o bricks.png").is_err());
+        assert!(parse("-clamp bricks.png").is_err());
+        assert!(parse("-imfchan q bricks.png").is_err());
+        assert!(parse("-o 1 2 3").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert!(matches!(
+            parse("-foo 1 bricks.png"),
+            Err(ImportError::UnrecognisedToken(option)) if option == "-foo"
+        ));
+    }
+}
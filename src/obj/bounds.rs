@@ -0,0 +1,511 @@
+use std::sync::OnceLock;
+
+use suoi_types::Vector3;
+
+use crate::math::{self, Vec3};
+use crate::obj_mesh::ObjMesh;
+use crate::Obj;
+
+/**
+`obj::BoundingBox`
+---
+An axis-aligned box enclosing a set of points
+*/
+#[derive(Debug, Clone)]
+pub struct BoundingBox {
+    min: Vector3,
+    max: Vector3,
+}
+
+impl BoundingBox {
+    /// Immutable getter for `min` of `BoundingBox`
+    pub fn min(&self) -> &Vector3 {
+        &self.min
+    }
+
+    /// Immutable getter for `max` of `BoundingBox`
+    pub fn max(&self) -> &Vector3 {
+        &self.max
+    }
+
+    /// Returns the middle of the box
+    pub fn center(&self) -> Vector3 {
+        let [min, max] = [&self.min, &self.max].map(math::from_vector);
+        math::to_vector(math::scale(math::add(min, max), 0.5))
+    }
+
+    /// Returns the extent of the box along each axis
+    pub fn size(&self) -> Vector3 {
+        let [min, max] = [&self.min, &self.max].map(math::from_vector);
+        math::to_vector(math::sub(max, min))
+    }
+
+    /// Encloses `points`, or returns `None` if there are none
+    pub(crate) fn from_points(points: &[Vec3]) -> Option<Self> {
+        let first = *points.first()?;
+        let (min, max) = points.iter().fold((first, first), |(min, max), p| {
+            (
+                std::array::from_fn(|i| min[i].min(p[i])),
+                std::array::from_fn(|i| max[i].max(p[i])),
+            )
+        });
+
+        Some(Self {
+            min: math::to_vector(min),
+            max: math::to_vector(max),
+        })
+    }
+
+    /// Encloses both `self` and `other`
+    pub(crate) fn union(&self, other: &Self) -> Self {
+        let [a, b] = [&self.min, &other.min].map(math::from_vector);
+        let [c, d] = [&self.max, &other.max].map(math::from_vector);
+
+        Self {
+            min: math::to_vector(std::array::from_fn(|i| a[i].min(b[i]))),
+            max: math::to_vector(std::array::from_fn(|i| c[i].max(d[i]))),
+        }
+    }
+}
+
+/**
+`obj::BoundingSphere`
+---
+A sphere enclosing a set of points
+*/
+#[derive(Debug, Clone)]
+pub struct BoundingSphere {
+    center: Vector3,
+    radius: f32,
+}
+
+impl BoundingSphere {
+    /// Immutable getter for `center` of `BoundingSphere`
+    pub fn center(&self) -> &Vector3 {
+        &self.center
+    }
+
+    /// Immutable getter for `radius` of `BoundingSphere`
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /**
+    `BoundingSphere::from_points`
+    ---
+    Encloses `points` with Ritter's algorithm: a sphere through the two
+    points furthest apart along a first guess, grown to take in the rest.
+    Within a few percent of the smallest sphere, and deterministic.
+    */
+    pub(crate) fn from_points(points: &[Vec3]) -> Self {
+        let Some(&first) = points.first() else {
+            return Self {
+                center: Vector3::new(0.0, 0.0, 0.0),
+                radius: 0.0,
+            };
+        };
+        let furthest = |from: Vec3| {
+            points.iter().copied().fold(from, |best, p| {
+                let distance = |q: Vec3| math::length(math::sub(q, from));
+                if distance(p) > distance(best) {
+                    p
+                } else {
+                    best
+                }
+            })
+        };
+
+        let a = furthest(first);
+        let b = furthest(a);
+        let mut center = math::scale(math::add(a, b), 0.5);
+        let mut radius = math::length(math::sub(b, a)) / 2.0;
+
+        for &p in points {
+            let distance = math::length(math::sub(p, center));
+            if distance > radius {
+                // move the center towards `p`, just far enough to take it in
+                let grown = (radius + distance) / 2.0;
+                center = math::add(
+                    center,
+                    math::scale(math::sub(p, center), (grown - radius) / distance),
+                );
+                radius = grown;
+            }
+        }
+
+        Self {
+            center: math::to_vector(center),
+            radius,
+        }
+    }
+}
+
+/**
+`obj::OrientedBox`
+---
+A box enclosing a set of points, turned to follow their principal axes.
+`axes` are unit length, at right angles to each other and ordered from
+the direction the points spread the most in to the least; the box spans
+`half_extents` along each of them on both sides of `center`.
+*/
+#[derive(Debug, Clone)]
+pub struct OrientedBox {
+    center: Vector3,
+    axes: [Vector3; 3],
+    half_extents: Vector3,
+}
+
+impl OrientedBox {
+    /// Immutable getter for `center` of `OrientedBox`
+    pub fn center(&self) -> &Vector3 {
+        &self.center
+    }
+
+    /// Immutable getter for `axes` of `OrientedBox`
+    pub fn axes(&self) -> &[Vector3; 3] {
+        &self.axes
+    }
+
+    /// Immutable getter for `half_extents` of `OrientedBox`
+    pub fn half_extents(&self) -> &Vector3 {
+        &self.half_extents
+    }
+
+    /**
+    `OrientedBox::from_points`
+    ---
+    Fits a box to the principal components of `points`: the eigenvectors
+    of their covariance matrix. When the axis-aligned box is smaller, as
+    it can be for boxy shapes, it is returned instead, with the world axes.
+    */
+    pub(crate) fn from_points(points: &[Vec3]) -> Option<Self> {
+        let first = *points.first()?;
+        let count = points.len() as f64;
+        let mean = points
+            .iter()
+            .fold([0.0f64; 3], |sum, p| {
+                std::array::from_fn(|i| sum[i] + p[i] as f64)
+            })
+            .map(|sum| sum / count);
+
+        let mut covariance = [[0.0f64; 3]; 3];
+        for p in points {
+            let d: [f64; 3] = std::array::from_fn(|i| p[i] as f64 - mean[i]);
+            for (i, row) in covariance.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value += d[i] * d[j] / count;
+                }
+            }
+        }
+
+        let (values, vectors) = Self::eigen(covariance);
+        let mut order = [0, 1, 2];
+        order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+        let axis = |k: usize| -> Vec3 { std::array::from_fn(|i| vectors[i][order[k]] as f32) };
+        let principal = [axis(0), axis(1), math::cross(axis(0), axis(1))];
+        let world = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+        [principal, world]
+            .into_iter()
+            .map(|axes| Self::fit(points, first, axes))
+            .min_by(|a, b| a.volume().total_cmp(&b.volume()))
+    }
+
+    /// The smallest box with the given `axes` around `points`
+    fn fit(points: &[Vec3], first: Vec3, axes: [Vec3; 3]) -> Self {
+        let start = axes.map(|axis| math::dot(axis, first));
+        let (min, max) = points.iter().fold((start, start), |(min, max), &p| {
+            let projected = axes.map(|axis| math::dot(axis, p));
+            (
+                std::array::from_fn(|i| min[i].min(projected[i])),
+                std::array::from_fn(|i| max[i].max(projected[i])),
+            )
+        });
+
+        let center = (0..3).fold([0.0; 3], |center, i| {
+            math::add(center, math::scale(axes[i], (min[i] + max[i]) / 2.0))
+        });
+        Self {
+            center: math::to_vector(center),
+            axes: axes.map(math::to_vector),
+            half_extents: math::to_vector(std::array::from_fn(|i| (max[i] - min[i]) / 2.0)),
+        }
+    }
+
+    fn volume(&self) -> f32 {
+        let e = &self.half_extents;
+        e.x * e.y * e.z
+    }
+
+    /**
+    `OrientedBox::eigen`
+    ---
+    Eigenvalues and eigenvectors, as the columns of the second matrix, of
+    the symmetric matrix `a`, by cyclic Jacobi rotations that zero one
+    off-diagonal element at a time
+    */
+    fn eigen(mut a: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+        let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+        for _ in 0..32 {
+            let off = a[0][1] * a[0][1] + a[0][2] * a[0][2] + a[1][2] * a[1][2];
+            let diagonal = a[0][0] * a[0][0] + a[1][1] * a[1][1] + a[2][2] * a[2][2];
+            if off <= diagonal * f64::EPSILON * f64::EPSILON {
+                break;
+            }
+
+            for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for row in &mut a {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+                let (row_p, row_q) = (a[p], a[q]);
+                a[p] = std::array::from_fn(|k| c * row_p[k] - s * row_q[k]);
+                a[q] = std::array::from_fn(|k| s * row_p[k] + c * row_q[k]);
+                for row in &mut v {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+            }
+        }
+
+        ([a[0][0], a[1][1], a[2][2]], v)
+    }
+}
+
+/**
+`obj::BoundsCache`
+---
+Bounds of a set of positions, each computed on first use and kept
+until `BoundsCache.invalidate` is called when the positions change
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BoundsCache {
+    bounding_box: OnceLock<Option<BoundingBox>>,
+    bounding_sphere: OnceLock<Option<BoundingSphere>>,
+    oriented_box: OnceLock<Option<OrientedBox>>,
+}
+
+impl BoundsCache {
+    /// Forgets every computed bound
+    pub(crate) fn invalidate(&mut self) {
+        // cheap to check, as this runs for every position loaded
+        if self.bounding_box.get().is_some()
+            || self.bounding_sphere.get().is_some()
+            || self.oriented_box.get().is_some()
+        {
+            *self = Self::default();
+        }
+    }
+}
+
+impl ObjMesh {
+    /// Positions of `self` as `Vec3`s, leaving out those that aren't finite
+    fn bounded_points(&self) -> Vec<Vec3> {
+        self.positions()
+            .iter()
+            .map(math::from_vector)
+            .filter(|&p| math::is_finite(p))
+            .collect()
+    }
+
+    /**
+    `ObjMesh.bounding_box`
+    ---
+    Returns the axis-aligned box around the finite positions of `self`,
+    or `None` if there are none. Like the other bounds, it is computed
+    once and kept until the positions change.
+    */
+    pub fn bounding_box(&self) -> Option<&BoundingBox> {
+        self.bounds_cache()
+            .bounding_box
+            .get_or_init(|| BoundingBox::from_points(&self.bounded_points()))
+            .as_ref()
+    }
+
+    /// Returns the bounding sphere of the finite positions of `self`, see `BoundingSphere::from_points`
+    pub fn bounding_sphere(&self) -> Option<&BoundingSphere> {
+        self.bounds_cache()
+            .bounding_sphere
+            .get_or_init(|| {
+                let points = self.bounded_points();
+                (!points.is_empty()).then(|| BoundingSphere::from_points(&points))
+            })
+            .as_ref()
+    }
+
+    /// Returns the oriented box of the finite positions of `self`, see `OrientedBox::from_points`
+    pub fn oriented_box(&self) -> Option<&OrientedBox> {
+        self.bounds_cache()
+            .oriented_box
+            .get_or_init(|| OrientedBox::from_points(&self.bounded_points()))
+            .as_ref()
+    }
+}
+
+impl Obj {
+    /// Finite positions of every mesh of `self`
+    fn bounded_points(&self) -> Vec<Vec3> {
+        self.meshes()
+            .iter()
+            .flat_map(|mesh| mesh.bounded_points())
+            .collect()
+    }
+
+    /**
+    `Obj.bounding_box`
+    ---
+    Returns the axis-aligned box around every mesh of `self`. The bounds of
+    `Obj` are computed once and kept until `Obj.meshes_mut` is called.
+    */
+    pub fn bounding_box(&self) -> Option<&BoundingBox> {
+        self.bounds_cache()
+            .bounding_box
+            .get_or_init(|| {
+                self.meshes().iter().filter_map(ObjMesh::bounding_box).fold(
+                    None,
+                    |bounds: Option<BoundingBox>, next| {
+                        Some(bounds.map_or_else(|| next.clone(), |bounds| bounds.union(next)))
+                    },
+                )
+            })
+            .as_ref()
+    }
+
+    /// Returns the bounding sphere of every mesh of `self`, see `BoundingSphere::from_points`
+    pub fn bounding_sphere(&self) -> Option<&BoundingSphere> {
+        self.bounds_cache()
+            .bounding_sphere
+            .get_or_init(|| {
+                let points = self.bounded_points();
+                (!points.is_empty()).then(|| BoundingSphere::from_points(&points))
+            })
+            .as_ref()
+    }
+
+    /// Returns the oriented box of every mesh of `self`, see `OrientedBox::from_points`
+    pub fn oriented_box(&self) -> Option<&OrientedBox> {
+        self.bounds_cache()
+            .oriented_box
+            .get_or_init(|| OrientedBox::from_points(&self.bounded_points()))
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Resource, VertexAttribute};
+
+    const EPSILON: f32 = 1e-4;
+
+    /// Corners of a box with half extents `2, 1, 0.5`, rotated about `z`
+    /// then `x` and moved off the origin
+    fn rotated_box() -> Vec<Vec3> {
+        let (a, b) = (0.5f32, 0.35f32);
+        (0..8)
+            .map(|k| {
+                let sign = |bit: usize| if k & bit == 0 { -1.0 } else { 1.0 };
+                let [x, y, z] = [2.0 * sign(1), sign(2), 0.5 * sign(4)];
+                let (x, y) = (x * a.cos() - y * a.sin(), x * a.sin() + y * a.cos());
+                let (y, z) = (y * b.cos() - z * b.sin(), y * b.sin() + z * b.cos());
+                [x + 1.0, y + 2.0, z + 3.0]
+            })
+            .collect()
+    }
+
+    fn mesh(points: &[Vec3]) -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for &p in points {
+            mesh.load_position(math::to_vector(p));
+        }
+        mesh
+    }
+
+    fn max_x(mesh: &ObjMesh) -> f32 {
+        mesh.bounding_box().unwrap().max().x
+    }
+
+    #[test]
+    fn bounds_of_a_rotated_box_contain_every_point() {
+        let points = rotated_box();
+        let mesh = mesh(&points);
+
+        let aabb = mesh.bounding_box().unwrap();
+        for i in 0..3 {
+            let min = points.iter().map(|p| p[i]).fold(f32::MAX, f32::min);
+            let max = points.iter().map(|p| p[i]).fold(f32::MIN, f32::max);
+            assert!((math::from_vector(aabb.min())[i] - min).abs() < EPSILON);
+            assert!((math::from_vector(aabb.max())[i] - max).abs() < EPSILON);
+        }
+
+        let sphere = mesh.bounding_sphere().unwrap();
+        let center = math::from_vector(sphere.center());
+        for &p in &points {
+            assert!(math::length(math::sub(p, center)) <= sphere.radius() + EPSILON);
+        }
+        // the corners lie on a sphere of the half diagonal, which a good fit is close to
+        let half_diagonal = (4.0f32 + 1.0 + 0.25).sqrt();
+        assert!(sphere.radius() < half_diagonal * 1.1);
+
+        let obb = mesh.oriented_box().unwrap();
+        let center = math::from_vector(obb.center());
+        let axes = obb.axes().clone().map(|axis| math::from_vector(&axis));
+        let extents = math::from_vector(obb.half_extents());
+        for &p in &points {
+            let offset = math::sub(p, center);
+            for i in 0..3 {
+                assert!(math::dot(axes[i], offset).abs() <= extents[i] + EPSILON);
+            }
+        }
+        // the principal axes find the box itself, far tighter than its axis-aligned box
+        assert!((extents[0] * extents[1] * extents[2] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn mesh_bounds_follow_position_changes() {
+        let mut mesh = mesh(&[[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]]);
+        assert_eq!(max_x(&mesh), 1.0);
+
+        mesh.load_position(Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(max_x(&mesh), 2.0);
+
+        mesh.positions_mut()[2].x = 3.0;
+        assert_eq!(max_x(&mesh), 3.0);
+        let sphere = mesh.bounding_sphere().unwrap();
+        let moved = math::sub([3.0, 0.0, 0.0], math::from_vector(sphere.center()));
+        assert!(math::length(moved) <= sphere.radius() + EPSILON);
+
+        mesh.remap(VertexAttribute::Position, &[Some(0), Some(1), None]);
+        assert_eq!(max_x(&mesh), 1.0);
+        assert!(mesh.oriented_box().unwrap().half_extents().x <= 1.0);
+    }
+
+    #[test]
+    fn obj_bounds_follow_meshes_mut() {
+        let dir = std::env::temp_dir().join("suoi_simp_bounds");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("triangle.obj");
+        std::fs::write(&path, "o tri\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let mut obj = Obj::import(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(obj.bounding_box().unwrap().max().x, 1.0);
+        let radius = obj.bounding_sphere().unwrap().radius();
+
+        obj.meshes_mut()[0].load_position(Vector3::new(5.0, 0.0, 0.0));
+        assert_eq!(obj.bounding_box().unwrap().max().x, 5.0);
+        assert!(obj.bounding_sphere().unwrap().radius() > radius);
+        assert!(obj.oriented_box().is_some());
+    }
+}
@@ -0,0 +1,788 @@
+use suoi_types::{Vector2, Vector3};
+
+use crate::bounds::BoundingBox;
+use crate::math::{self, Vec3};
+use crate::obj_mesh::ObjMesh;
+use crate::VertexAttribute;
+
+/// Number of bins the centroids are sorted into when looking for the best split
+const SAH_BINS: usize = 12;
+
+/// Largest number of triangles in a leaf, unless they can't be told apart
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// Cost of visiting a node, relative to testing a triangle
+const TRAVERSAL_COST: f32 = 1.0;
+
+/**
+`obj::RayHit`
+---
+The point of a face found by a `Bvh` query. `corners` are the elements
+of the face making up the triangle that was hit, as faces with more than
+three corners are split into fans, and `barycentric` weighs those three
+corners. `uv` is `None` unless all three corners have one, and `normal`
+falls back to the normal of the triangle when they lack normals.
+*/
+#[derive(Debug, Clone)]
+pub struct RayHit {
+    face: usize,
+    corners: [usize; 3],
+    distance: f32,
+    point: Vector3,
+    barycentric: Vector3,
+    uv: Option<Vector2>,
+    normal: Vector3,
+}
+
+impl RayHit {
+    /// Immutable getter for `face` of `RayHit`, an index into `ObjMesh.faces`
+    pub fn face(&self) -> usize {
+        self.face
+    }
+
+    /// Immutable getter for `corners` of `RayHit`, indices into `Face.elements`
+    pub fn corners(&self) -> [usize; 3] {
+        self.corners
+    }
+
+    /// Immutable getter for `distance` of `RayHit`, from the ray origin or the query point
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Immutable getter for `point` of `RayHit`
+    pub fn point(&self) -> &Vector3 {
+        &self.point
+    }
+
+    /// Immutable getter for `barycentric` of `RayHit`
+    pub fn barycentric(&self) -> &Vector3 {
+        &self.barycentric
+    }
+
+    /// Immutable getter for `uv` of `RayHit`
+    pub fn uv(&self) -> Option<&Vector2> {
+        self.uv.as_ref()
+    }
+
+    /// Immutable getter for `normal` of `RayHit`
+    pub fn normal(&self) -> &Vector3 {
+        &self.normal
+    }
+}
+
+/// A triangle of a face, with the attributes of its corners
+#[derive(Debug, Clone)]
+struct Triangle {
+    face: usize,
+    corners: [usize; 3],
+    positions: [Vec3; 3],
+    uvs: Option<[[f32; 2]; 3]>,
+    normals: Option<[Vec3; 3]>,
+}
+
+impl Triangle {
+    fn bounds(&self) -> [Vec3; 2] {
+        let [a, b, c] = self.positions;
+        [
+            std::array::from_fn(|i| a[i].min(b[i]).min(c[i])),
+            std::array::from_fn(|i| a[i].max(b[i]).max(c[i])),
+        ]
+    }
+
+    fn centroid(&self) -> Vec3 {
+        let [a, b, c] = self.positions;
+        math::scale(math::add(math::add(a, b), c), 1.0 / 3.0)
+    }
+
+    /// Interpolates the attributes of `self` at the barycentric `weights`
+    fn hit(&self, weights: Vec3, distance: f32) -> RayHit {
+        let mix = |values: [Vec3; 3]| {
+            (0..3).fold([0.0; 3], |sum, k| {
+                math::add(sum, math::scale(values[k], weights[k]))
+            })
+        };
+        let [a, b, c] = self.positions;
+        let geometric = math::normalize(math::cross(math::sub(b, a), math::sub(c, a)));
+        let normal = self
+            .normals
+            .and_then(|normals| math::normalize(mix(normals)))
+            .or(geometric)
+            .unwrap_or([0.0, 0.0, 0.0]);
+
+        RayHit {
+            face: self.face,
+            corners: self.corners,
+            distance,
+            point: math::to_vector(mix(self.positions)),
+            barycentric: math::to_vector(weights),
+            uv: self.uvs.map(|uvs| {
+                let [x, y, _] = mix(uvs.map(|[u, v]| [u, v, 0.0]));
+                Vector2 { x, y }
+            }),
+            normal: math::to_vector(normal),
+        }
+    }
+
+    /// Möller-Trumbore intersection, from either side, returning the distance and weights
+    fn intersect(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+        let [a, b, c] = self.positions;
+        let ab = math::sub(b, a);
+        let ac = math::sub(c, a);
+        let p = math::cross(direction, ac);
+        let determinant = math::dot(ab, p);
+        if determinant.abs() <= f32::EPSILON * math::dot(ab, ab).max(math::dot(ac, ac)) {
+            return None;
+        }
+
+        let inverse = 1.0 / determinant;
+        let offset = math::sub(origin, a);
+        let u = math::dot(offset, p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = math::cross(offset, ab);
+        let v = math::dot(direction, q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = math::dot(ac, q) * inverse;
+
+        (0.0..=max_distance)
+            .contains(&t)
+            .then_some((t, [1.0 - u - v, u, v]))
+    }
+
+    /// Weights of the point of `self` closest to `point`, after Ericson's region tests
+    fn closest_point(&self, point: Vec3) -> Vec3 {
+        let [a, b, c] = self.positions;
+        let ab = math::sub(b, a);
+        let ac = math::sub(c, a);
+
+        let ap = math::sub(point, a);
+        let (d1, d2) = (math::dot(ab, ap), math::dot(ac, ap));
+        if d1 <= 0.0 && d2 <= 0.0 {
+            return [1.0, 0.0, 0.0];
+        }
+        let bp = math::sub(point, b);
+        let (d3, d4) = (math::dot(ab, bp), math::dot(ac, bp));
+        if d3 >= 0.0 && d4 <= d3 {
+            return [0.0, 1.0, 0.0];
+        }
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+            let v = d1 / (d1 - d3);
+            return [1.0 - v, v, 0.0];
+        }
+        let cp = math::sub(point, c);
+        let (d5, d6) = (math::dot(ab, cp), math::dot(ac, cp));
+        if d6 >= 0.0 && d5 <= d6 {
+            return [0.0, 0.0, 1.0];
+        }
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+            let w = d2 / (d2 - d6);
+            return [1.0 - w, 0.0, w];
+        }
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+            let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+            return [0.0, 1.0 - w, w];
+        }
+
+        let denominator = 1.0 / (va + vb + vc);
+        let (v, w) = (vb * denominator, vc * denominator);
+        [1.0 - v - w, v, w]
+    }
+}
+
+/// A node of the hierarchy: a leaf with `count` triangles from `start`,
+/// or, when `count` is `0`, a branch whose children are the next node
+/// and the node at `start`
+#[derive(Debug, Clone)]
+struct BvhNode {
+    bounds: [Vec3; 2],
+    start: usize,
+    count: usize,
+}
+
+/**
+`obj::Bvh`
+---
+A bounding volume hierarchy over the triangles of an `ObjMesh`, built
+with the surface area heuristic, for ray casts and closest point queries.
+It keeps a copy of the geometry, so it outlives changes to the mesh,
+but has to be rebuilt to see them.
+*/
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    triangles: Vec<Triangle>,
+}
+
+impl ObjMesh {
+    /**
+    `ObjMesh.build_bvh`
+    ---
+    Builds a `Bvh` over the faces of `self`, splitting faces with more than
+    three corners into triangle fans. Triangles with out-of-range positions
+    or no area are left out.
+    */
+    pub fn build_bvh(&self) -> Bvh {
+        let local = |attribute, index| self.local_index(attribute, index);
+        let mut triangles = vec![];
+
+        for (face, data) in self.faces().iter().enumerate() {
+            let elements = data.elements();
+            for second in 1..elements.len().saturating_sub(1) {
+                let corners = [0, second, second + 1];
+                let element = |k: usize| &elements[corners[k]];
+
+                let positions: Option<Vec<Vec3>> = (0..3)
+                    .map(|k| {
+                        local(VertexAttribute::Position, element(k).position_index())
+                            .map(|i| math::from_vector(&self.positions()[i]))
+                    })
+                    .collect();
+                let Some([a, b, c]) = positions.and_then(|p| <[Vec3; 3]>::try_from(p).ok()) else {
+                    continue;
+                };
+                let cross = math::cross(math::sub(b, a), math::sub(c, a));
+                if math::dot(cross, cross) <= 0.0 {
+                    continue;
+                }
+
+                let uvs: Option<Vec<[f32; 2]>> = (0..3)
+                    .map(|k| {
+                        local(VertexAttribute::Uv, element(k).uv_index()).map(|i| {
+                            let uv = &self.uvs()[i];
+                            [uv.x, uv.y]
+                        })
+                    })
+                    .collect();
+                let normals: Option<Vec<Vec3>> = (0..3)
+                    .map(|k| {
+                        local(VertexAttribute::Normal, element(k).normal_index())
+                            .map(|i| math::from_vector(&self.normals()[i]))
+                    })
+                    .collect();
+
+                triangles.push(Triangle {
+                    face,
+                    corners,
+                    positions: [a, b, c],
+                    uvs: uvs.and_then(|uvs| uvs.try_into().ok()),
+                    normals: normals.and_then(|normals| normals.try_into().ok()),
+                });
+            }
+        }
+
+        Bvh::build(triangles)
+    }
+}
+
+impl Bvh {
+    /// Sorts `triangles` into a hierarchy, splitting nodes where the surface area heuristic is lowest
+    fn build(triangles: Vec<Triangle>) -> Self {
+        let mut bvh = Self {
+            nodes: vec![],
+            triangles,
+        };
+        let bounds: Vec<[Vec3; 2]> = bvh.triangles.iter().map(Triangle::bounds).collect();
+        let centroids: Vec<Vec3> = bvh.triangles.iter().map(Triangle::centroid).collect();
+        let mut order: Vec<usize> = (0..bvh.triangles.len()).collect();
+
+        if !order.is_empty() {
+            bvh.split(&mut order, 0, &bounds, &centroids);
+        }
+        bvh.triangles = order.iter().map(|&t| bvh.triangles[t].clone()).collect();
+
+        bvh
+    }
+
+    /// Adds the node of `order[start..]`, then its children, returning its index
+    fn split(
+        &mut self,
+        order: &mut [usize],
+        start: usize,
+        bounds: &[[Vec3; 2]],
+        centroids: &[Vec3],
+    ) -> usize {
+        let node = self.nodes.len();
+        let node_bounds = enclose(order.iter().map(|&t| bounds[t]));
+        self.nodes.push(BvhNode {
+            bounds: node_bounds,
+            start,
+            count: order.len(),
+        });
+        if order.len() <= 1 {
+            return node;
+        }
+
+        let [low, high] = enclose(order.iter().map(|&t| [centroids[t], centroids[t]]));
+        let bin_of = |axis: usize, t: usize| {
+            let extent = high[axis] - low[axis];
+            let bin = ((centroids[t][axis] - low[axis]) / extent * SAH_BINS as f32) as usize;
+            bin.min(SAH_BINS - 1)
+        };
+
+        // the split between bins with the lowest cost, over every axis
+        let mut best: Option<(f32, usize, usize)> = None;
+        for axis in 0..3 {
+            if high[axis] - low[axis] <= 0.0 {
+                continue;
+            }
+            let mut bins: [Option<[Vec3; 2]>; SAH_BINS] = [None; SAH_BINS];
+            let mut counts = [0usize; SAH_BINS];
+            for &t in order.iter() {
+                let bin = bin_of(axis, t);
+                counts[bin] += 1;
+                bins[bin] = Some(match bins[bin] {
+                    Some(b) => enclose([b, bounds[t]]),
+                    None => bounds[t],
+                });
+            }
+
+            let mut right_areas = [0.0; SAH_BINS];
+            let mut right: Option<[Vec3; 2]> = None;
+            for bin in (1..SAH_BINS).rev() {
+                right = merge(right, bins[bin]);
+                right_areas[bin] = right.map_or(0.0, area);
+            }
+            let mut left: Option<[Vec3; 2]> = None;
+            let mut left_count = 0;
+            for bin in 1..SAH_BINS {
+                left = merge(left, bins[bin - 1]);
+                left_count += counts[bin - 1];
+                let right_count = order.len() - left_count;
+                if left_count == 0 || right_count == 0 {
+                    continue;
+                }
+                let cost = left.map_or(0.0, area) * left_count as f32
+                    + right_areas[bin] * right_count as f32;
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, bin));
+                }
+            }
+        }
+
+        let Some((cost, axis, bin)) = best else {
+            return node;
+        };
+        let node_area = area(node_bounds);
+        let split_cost = match node_area > 0.0 {
+            true => TRAVERSAL_COST + cost / node_area,
+            false => TRAVERSAL_COST,
+        };
+        if order.len() <= MAX_LEAF_TRIANGLES && split_cost >= order.len() as f32 {
+            return node;
+        }
+
+        // stable, so that the same mesh always gives the same hierarchy
+        order.sort_by_key(|&t| bin_of(axis, t) >= bin);
+        let middle = order.iter().take_while(|&&t| bin_of(axis, t) < bin).count();
+        let (left, right) = order.split_at_mut(middle);
+
+        self.nodes[node].count = 0;
+        self.split(left, start, bounds, centroids);
+        let right_node = self.split(right, start + middle, bounds, centroids);
+        self.nodes[node].start = right_node;
+
+        node
+    }
+
+    /// Number of triangles in `self`
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Number of nodes in `self`
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the box enclosing every triangle of `self`, or `None` if there are none
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        let root = self.nodes.first()?;
+        BoundingBox::from_points(&root.bounds)
+    }
+
+    /**
+    `Bvh.raycast`
+    ---
+    Finds the first triangle hit by the ray from `origin` along `direction`
+    within `max_distance`, from either side. The direction doesn't need to
+    be normalized, and the distance of the hit is measured along it once
+    normalized. Returns `None` when nothing is hit or `direction` is zero.
+    */
+    pub fn raycast(
+        &self,
+        origin: &Vector3,
+        direction: &Vector3,
+        max_distance: f32,
+    ) -> Option<RayHit> {
+        let origin = math::from_vector(origin);
+        let direction = math::normalize(math::from_vector(direction))?;
+        let inverse = direction.map(|d| 1.0 / d);
+
+        let mut best: Option<(f32, Vec3, usize)> = None;
+        let mut stack = match self.nodes.is_empty() {
+            true => vec![],
+            false => vec![0],
+        };
+        while let Some(index) = stack.pop() {
+            let limit = best.map_or(max_distance, |(t, _, _)| t);
+            let node = &self.nodes[index];
+            if slab(node.bounds, origin, inverse, limit).is_none() {
+                continue;
+            }
+
+            if node.count > 0 {
+                for t in node.start..node.start + node.count {
+                    let limit = best.map_or(max_distance, |(t, _, _)| t);
+                    if let Some((distance, weights)) =
+                        self.triangles[t].intersect(origin, direction, limit)
+                    {
+                        best = Some((distance, weights, t));
+                    }
+                }
+                continue;
+            }
+
+            // visit the nearer child first, so that it can cut the other one short
+            let (near, far) = (index + 1, node.start);
+            let entry = |child: usize| slab(self.nodes[child].bounds, origin, inverse, limit);
+            match (entry(near), entry(far)) {
+                (Some(a), Some(b)) if b < a => stack.extend([near, far]),
+                (Some(_), Some(_)) => stack.extend([far, near]),
+                (Some(_), None) => stack.push(near),
+                (None, Some(_)) => stack.push(far),
+                (None, None) => {}
+            }
+        }
+
+        best.map(|(distance, weights, t)| self.triangles[t].hit(weights, distance))
+    }
+
+    /**
+    `Bvh.segment_cast`
+    ---
+    Finds the triangle hit first on the way from `start` to `end`,
+    see `Bvh.raycast`
+    */
+    pub fn segment_cast(&self, start: &Vector3, end: &Vector3) -> Option<RayHit> {
+        let direction = math::sub(math::from_vector(end), math::from_vector(start));
+        self.raycast(start, &math::to_vector(direction), math::length(direction))
+    }
+
+    /**
+    `Bvh.closest_point`
+    ---
+    Finds the point of the triangles of `self` nearest to `point`, within
+    `max_distance`; pass `f32::INFINITY` to search the whole mesh
+    */
+    pub fn closest_point(&self, point: &Vector3, max_distance: f32) -> Option<RayHit> {
+        let point = math::from_vector(point);
+        let mut best: Option<(f32, Vec3, usize)> = None;
+        let mut stack = match self.nodes.is_empty() {
+            true => vec![],
+            false => vec![0],
+        };
+
+        while let Some(index) = stack.pop() {
+            let limit = best.map_or(max_distance * max_distance, |(d, _, _)| d);
+            let node = &self.nodes[index];
+            if box_distance_squared(node.bounds, point) > limit {
+                continue;
+            }
+
+            if node.count > 0 {
+                for t in node.start..node.start + node.count {
+                    let triangle = &self.triangles[t];
+                    let weights = triangle.closest_point(point);
+                    let closest = (0..3).fold([0.0; 3], |sum, k| {
+                        math::add(sum, math::scale(triangle.positions[k], weights[k]))
+                    });
+                    let offset = math::sub(closest, point);
+                    let distance = math::dot(offset, offset);
+                    if distance <= best.map_or(max_distance * max_distance, |(d, _, _)| d) {
+                        best = Some((distance, weights, t));
+                    }
+                }
+                continue;
+            }
+
+            let (near, far) = (index + 1, node.start);
+            let [a, b] =
+                [near, far].map(|child| box_distance_squared(self.nodes[child].bounds, point));
+            match b < a {
+                true => stack.extend([near, far]),
+                false => stack.extend([far, near]),
+            }
+        }
+
+        best.map(|(distance, weights, t)| self.triangles[t].hit(weights, distance.sqrt()))
+    }
+}
+
+/// Encloses every box of `boxes`, which must not be empty
+fn enclose(boxes: impl IntoIterator<Item = [Vec3; 2]>) -> [Vec3; 2] {
+    boxes
+        .into_iter()
+        .reduce(|[min, max], [low, high]| {
+            [
+                std::array::from_fn(|i| min[i].min(low[i])),
+                std::array::from_fn(|i| max[i].max(high[i])),
+            ]
+        })
+        .unwrap_or([[0.0; 3]; 2])
+}
+
+fn merge(a: Option<[Vec3; 2]>, b: Option<[Vec3; 2]>) -> Option<[Vec3; 2]> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(enclose([a, b])),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Half the surface area of a box, enough to compare costs
+fn area([min, max]: [Vec3; 2]) -> f32 {
+    let [x, y, z] = math::sub(max, min);
+    x * y + y * z + z * x
+}
+
+/// Distance along the ray at which it enters `bounds`, if it does before `limit`
+fn slab(bounds: [Vec3; 2], origin: Vec3, inverse: Vec3, limit: f32) -> Option<f32> {
+    let mut near = 0.0f32;
+    let mut far = limit;
+    for axis in 0..3 {
+        let a = (bounds[0][axis] - origin[axis]) * inverse[axis];
+        let b = (bounds[1][axis] - origin[axis]) * inverse[axis];
+        // a ray running within the plane of a face of the box gives NaN, and
+        // stays between the planes of that axis all along
+        if a.is_nan() || b.is_nan() {
+            continue;
+        }
+        near = near.max(a.min(b));
+        far = far.min(a.max(b));
+    }
+
+    (near <= far).then_some(near)
+}
+
+fn box_distance_squared([min, max]: [Vec3; 2], point: Vec3) -> f32 {
+    (0..3)
+        .map(|i| {
+            let d = (min[i] - point[i]).max(point[i] - max[i]).max(0.0);
+            d * d
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::{Face, FaceElement};
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(actual: &Vector3, expected: Vec3) {
+        let actual = math::from_vector(actual);
+        assert!(
+            (0..3).all(|i| (actual[i] - expected[i]).abs() < EPSILON),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    /// A quad in the `z = 0` plane with uvs, and a normal leaning towards `+x` at its third corner
+    fn quad() -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for [x, y] in [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]] {
+            mesh.load_position(Vector3::new(x, y, 0.0));
+            mesh.load_uv(Vector2 {
+                x: x / 2.0,
+                y: y / 2.0,
+            });
+        }
+        mesh.load_normal(Vector3::new(0.0, 0.0, 1.0));
+        mesh.load_normal(Vector3::new(1.0, 0.0, 0.0));
+        let normals = [1, 1, 2, 1];
+        let elements = (0..4)
+            .map(|k| FaceElement::new(k + 1, normals[k as usize], k + 1))
+            .collect();
+        mesh.load_face(Face::new(elements));
+        mesh
+    }
+
+    /// A bumpy grid of `n` by `n` quads, to give the hierarchy some depth
+    fn terrain(n: u32) -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for j in 0..=n {
+            for i in 0..=n {
+                let (x, y) = (i as f32, j as f32);
+                mesh.load_position(Vector3::new(x, y, (x * 0.7).sin() + (y * 0.4).cos()));
+            }
+        }
+        for j in 0..n {
+            for i in 0..n {
+                let corner = |i: u32, j: u32| FaceElement::new(j * (n + 1) + i + 1, 0, 0);
+                mesh.load_face(Face::new(vec![
+                    corner(i, j),
+                    corner(i + 1, j),
+                    corner(i + 1, j + 1),
+                    corner(i, j + 1),
+                ]));
+            }
+        }
+        mesh
+    }
+
+    /// A small linear congruential generator, for repeatable rays
+    fn random(state: &mut u32) -> f32 {
+        *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (*state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    #[test]
+    fn raycast_interpolates_the_fan_triangle_hit() {
+        let bvh = quad().build_bvh();
+        assert_eq!(bvh.triangle_count(), 2);
+
+        let hit = bvh
+            .raycast(
+                &Vector3::new(0.5, 1.5, 5.0),
+                &Vector3::new(0.0, 0.0, -2.0),
+                f32::INFINITY,
+            )
+            .unwrap();
+        assert_eq!(hit.face(), 0);
+        assert_eq!(hit.corners(), [0, 2, 3]);
+        assert!((hit.distance() - 5.0).abs() < EPSILON);
+        assert_close(hit.point(), [0.5, 1.5, 0.0]);
+        assert_close(hit.barycentric(), [0.25, 0.25, 0.5]);
+
+        let uv = hit.uv().unwrap();
+        assert!((uv.x - 0.25).abs() < EPSILON && (uv.y - 0.75).abs() < EPSILON);
+        let normal = math::normalize([0.25, 0.0, 0.75]).unwrap();
+        assert_close(hit.normal(), normal);
+
+        let first = bvh
+            .raycast(
+                &Vector3::new(1.5, 0.5, -1.0),
+                &Vector3::new(0.0, 0.0, 1.0),
+                f32::INFINITY,
+            )
+            .unwrap();
+        assert_eq!(first.corners(), [0, 1, 2]);
+        assert_close(first.barycentric(), [0.25, 0.5, 0.25]);
+        assert_close(first.normal(), normal);
+    }
+
+    #[test]
+    fn raycast_agrees_with_brute_force() {
+        let bvh = terrain(16).build_bvh();
+        assert!(bvh.node_count() > 1);
+
+        let mut state = 7;
+        let mut hits = 0;
+        for _ in 0..500 {
+            let mut next = || random(&mut state);
+            let origin = [next() * 20.0 - 2.0, next() * 20.0 - 2.0, 4.0];
+            let direction = [next() - 0.5, next() - 0.5, -next() - 0.1];
+
+            let expected = bvh
+                .triangles
+                .iter()
+                .filter_map(|triangle| {
+                    let direction = math::normalize(direction)?;
+                    triangle.intersect(origin, direction, f32::INFINITY)
+                })
+                .map(|(distance, _)| distance)
+                .reduce(f32::min);
+            let hit = bvh.raycast(
+                &math::to_vector(origin),
+                &math::to_vector(direction),
+                f32::INFINITY,
+            );
+
+            match (hit, expected) {
+                (Some(hit), Some(expected)) => {
+                    assert!((hit.distance() - expected).abs() < EPSILON);
+                    hits += 1;
+                }
+                (None, None) => {}
+                (hit, expected) => panic!("{hit:?} against {expected:?}"),
+            }
+        }
+        assert!(hits > 250, "{hits} hits");
+    }
+
+    #[test]
+    fn segment_cast_stops_at_the_segment_end() {
+        let bvh = quad().build_bvh();
+        let start = Vector3::new(1.0, 0.5, 5.0);
+
+        assert!(bvh
+            .segment_cast(&start, &Vector3::new(1.0, 0.5, 1.0))
+            .is_none());
+        assert!(bvh
+            .segment_cast(&start, &Vector3::new(1.0, 0.5, 0.0))
+            .is_some());
+        let hit = bvh
+            .segment_cast(&start, &Vector3::new(1.0, 0.5, -1.0))
+            .unwrap();
+        assert!((hit.distance() - 5.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn closest_point_in_each_region() {
+        let mut mesh = ObjMesh::empty();
+        for [x, y] in [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]] {
+            mesh.load_position(Vector3::new(x, y, 0.0));
+        }
+        mesh.load_face(Face::new(
+            (1..=3).map(|p| FaceElement::new(p, 0, 0)).collect(),
+        ));
+        let bvh = mesh.build_bvh();
+
+        let regions = [
+            ([-1.0, -1.0, 0.5], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            ([2.0, -0.5, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([-0.5, 3.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+            ([0.5, -1.0, 0.0], [0.5, 0.0, 0.0], [0.5, 0.5, 0.0]),
+            ([-1.0, 0.25, 0.0], [0.0, 0.25, 0.0], [0.75, 0.0, 0.25]),
+            ([1.0, 1.0, 0.0], [0.5, 0.5, 0.0], [0.0, 0.5, 0.5]),
+            ([0.25, 0.25, 2.0], [0.25, 0.25, 0.0], [0.5, 0.25, 0.25]),
+        ];
+        for (query, point, weights) in regions {
+            let hit = bvh
+                .closest_point(&math::to_vector(query), f32::INFINITY)
+                .unwrap();
+            assert_close(hit.point(), point);
+            assert_close(hit.barycentric(), weights);
+            assert!((hit.distance() - math::length(math::sub(query, point))).abs() < EPSILON);
+        }
+
+        let above = Vector3::new(0.25, 0.25, 2.0);
+        assert!(bvh.closest_point(&above, 1.5).is_none());
+        assert!((bvh.closest_point(&above, 2.5).unwrap().distance() - 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn building_twice_gives_identical_nodes() {
+        let mesh = terrain(12);
+        let summary = |bvh: &Bvh| {
+            let nodes: Vec<_> = bvh
+                .nodes
+                .iter()
+                .map(|node| (node.bounds, node.start, node.count))
+                .collect();
+            let triangles: Vec<_> = bvh.triangles.iter().map(|t| (t.face, t.corners)).collect();
+            (nodes, triangles)
+        };
+
+        let first = mesh.build_bvh();
+        assert_eq!(summary(&first), summary(&mesh.build_bvh()));
+        assert_eq!(summary(&first), summary(&terrain(12).build_bvh()));
+    }
+}
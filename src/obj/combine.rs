@@ -0,0 +1,294 @@
+use crate::face::{Face, FaceElement};
+use crate::obj_mesh::ObjMesh;
+use crate::{MaterialHandle, VertexAttribute};
+
+impl ObjMesh {
+    /**
+    `ObjMesh::merge`
+    ---
+    Creates a mesh named `name` holding the data and faces of every mesh
+    in `meshes`, in order, see `ObjMesh.append`. The material handles of
+    all the meshes must refer to the same material table.
+    */
+    pub fn merge(name: &str, meshes: &[&ObjMesh]) -> Self {
+        let mut merged = Self::empty();
+        merged.set_name(name);
+        for mesh in meshes {
+            merged.append(mesh);
+        }
+
+        merged
+    }
+
+    /**
+    `ObjMesh.append`
+    ---
+    Appends the positions, uvs, normals and faces of `other` to `self`,
+    rebasing the face indices onto the buffers of `self`. Groups and
+    material spans follow the faces; faces of `other` outside any group
+    start an unnamed one, and faces without a material clear the active
+    material of `self`. Out-of-range
+    indices become `0`, and free-form geometry is left out.
+    */
+    pub fn append(&mut self, other: &ObjMesh) {
+        let faces: Vec<usize> = (0..other.faces().len()).collect();
+        self.append_faces(other, &faces);
+    }
+
+    /**
+    `ObjMesh.split_by_material`
+    ---
+    Splits `self` into one mesh per material, in the order the materials
+    are first used, each with the faces of that material, their groups,
+    and only the data they refer to. The meshes keep the name of `self`.
+    */
+    pub fn split_by_material(&self) -> Vec<ObjMesh> {
+        let mut materials: Vec<(Option<MaterialHandle>, Vec<usize>)> = vec![];
+        for face in 0..self.faces().len() {
+            let material = self.face_material(face);
+            match materials.iter_mut().find(|(m, _)| *m == material) {
+                Some((_, faces)) => faces.push(face),
+                None => materials.push((material, vec![face])),
+            }
+        }
+
+        materials
+            .into_iter()
+            .map(|(_, faces)| self.extract(&faces))
+            .collect()
+    }
+
+    /**
+    `ObjMesh.split_by_component`
+    ---
+    Splits `self` into its connected components, the sets of faces linked
+    by shared positions, in the order of their first face. Each mesh keeps
+    the groups and materials of its faces, only the data they refer to,
+    and the name of `self`. Faces without a valid position stand alone.
+    */
+    pub fn split_by_component(&self) -> Vec<ObjMesh> {
+        fn root(parents: &mut [usize], mut face: usize) -> usize {
+            while parents[face] != face {
+                parents[face] = parents[parents[face]];
+                face = parents[face];
+            }
+            face
+        }
+
+        let mut parents: Vec<usize> = (0..self.faces().len()).collect();
+        let mut owners: Vec<Option<usize>> = vec![None; self.positions().len()];
+        for (face, data) in self.faces().iter().enumerate() {
+            for element in data.elements() {
+                let Some(position) =
+                    self.local_index(VertexAttribute::Position, element.position_index())
+                else {
+                    continue;
+                };
+                match owners[position] {
+                    Some(owner) => {
+                        let (a, b) = (root(&mut parents, owner), root(&mut parents, face));
+                        // the lower face becomes the root, so components sort by their first face
+                        parents[a.max(b)] = a.min(b);
+                    }
+                    None => owners[position] = Some(face),
+                }
+            }
+        }
+
+        let mut components: Vec<Vec<usize>> = vec![];
+        let mut slots: Vec<Option<usize>> = vec![None; self.faces().len()];
+        for face in 0..self.faces().len() {
+            let component = root(&mut parents, face);
+            let slot = *slots[component].get_or_insert_with(|| {
+                components.push(vec![]);
+                components.len() - 1
+            });
+            components[slot].push(face);
+        }
+
+        components.iter().map(|faces| self.extract(faces)).collect()
+    }
+
+    /// Copies the `faces` of `self` into a new mesh with only the data they refer to
+    fn extract(&self, faces: &[usize]) -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        mesh.set_name(self.get_name());
+        mesh.append_faces(self, faces);
+        mesh.compact();
+
+        mesh
+    }
+
+    /**
+    `ObjMesh.append_faces`
+    ---
+    Appends every position, uv and normal of `other` to `self`, along with
+    the faces of `other` listed in `faces`, starting a group or material
+    span wherever the one of `other` changes
+    */
+    fn append_faces(&mut self, other: &ObjMesh, faces: &[usize]) {
+        let offsets = [
+            self.positions().len() as u32 + self.position_base(),
+            self.uvs().len() as u32 + self.uv_base(),
+            self.normals().len() as u32 + self.normal_base(),
+        ];
+        for (index, position) in other.positions().iter().enumerate() {
+            self.load_weighted_position(position.clone(), other.weight(index));
+        }
+        for uv in other.uvs() {
+            self.load_uv(uv.clone());
+        }
+        for normal in other.normals() {
+            self.load_normal(normal.clone());
+        }
+
+        let rebase = |attribute, index, offset: u32| {
+            other
+                .local_index(attribute, index)
+                .map_or(0, |local| offset + local as u32 + 1)
+        };
+        let mut last_group = None;
+        for &face in faces {
+            let group = other
+                .groups()
+                .iter()
+                .rposition(|group| group.first_face() <= face);
+            if last_group != Some(group) {
+                match group {
+                    Some(group) => self.load_group(other.groups()[group].names().clone()),
+                    None if !self.groups().is_empty() => self.load_group(vec![]),
+                    None => {}
+                }
+                last_group = Some(group);
+            }
+
+            let material = other.face_material(face);
+            if material != self.get_material() {
+                self.use_material(material);
+            }
+
+            let elements = other.faces()[face]
+                .elements()
+                .iter()
+                .map(|element| {
+                    FaceElement::new(
+                        rebase(
+                            VertexAttribute::Position,
+                            element.position_index(),
+                            offsets[0],
+                        ),
+                        rebase(VertexAttribute::Normal, element.normal_index(), offsets[2]),
+                        rebase(VertexAttribute::Uv, element.uv_index(), offsets[1]),
+                    )
+                })
+                .collect();
+            self.load_face(Face::new(elements));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use suoi_types::Vector3;
+
+    use super::*;
+    use crate::{FloatFormat, Mtl, MtlMaterial, Obj, Resource};
+
+    /// A strip of triangles whose materials are `materials`, in order
+    fn strip(materials: &[Option<MaterialHandle>]) -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for i in 0..materials.len() + 2 {
+            mesh.load_position(Vector3::new(i as f32, (i % 2) as f32, 0.0));
+        }
+        for (i, material) in materials.iter().enumerate() {
+            mesh.use_material(*material);
+            let corners = [1, 2, 3].map(|k| FaceElement::new(i as u32 + k, 0, 0));
+            mesh.load_face(Face::new(corners.to_vec()));
+        }
+        mesh
+    }
+
+    fn face_materials(mesh: &ObjMesh) -> Vec<Option<MaterialHandle>> {
+        (0..mesh.faces().len())
+            .map(|face| mesh.face_material(face))
+            .collect()
+    }
+
+    #[test]
+    fn split_by_material_and_merge_keep_faces_without_material() {
+        let mut mtl = Mtl::empty();
+        let a = Some(mtl.add_material(MtlMaterial::empty()));
+        let b = Some(mtl.add_material(MtlMaterial::empty()));
+        let mesh = strip(&[None, a, None, b, a]);
+
+        let parts = mesh.split_by_material();
+        assert_eq!(parts.len(), 3);
+        for (part, material) in parts.iter().zip([None, a, b]) {
+            assert!(face_materials(part).iter().all(|&m| m == material));
+        }
+
+        let merged = ObjMesh::merge("merged", &parts.iter().collect::<Vec<_>>());
+        assert_eq!(face_materials(&merged), vec![None, None, a, a, b]);
+
+        let appended = ObjMesh::merge("appended", &[&parts[1], &parts[0]]);
+        assert_eq!(face_materials(&appended), vec![a, a, None, None]);
+    }
+
+    #[test]
+    fn split_by_component_separates_disjoint_triangles() {
+        let mut mesh = ObjMesh::empty();
+        mesh.set_name("pair");
+        for [x, y] in [
+            [0.0, 0.0],
+            [5.0, 0.0],
+            [1.0, 0.0],
+            [6.0, 0.0],
+            [0.0, 1.0],
+            [5.0, 1.0],
+        ] {
+            mesh.load_position(Vector3::new(x, y, 0.0));
+        }
+        for face in [[1, 3, 5], [2, 4, 6]] {
+            let corners = face.map(|p| FaceElement::new(p, 0, 0));
+            mesh.load_face(Face::new(corners.to_vec()));
+        }
+
+        let parts = mesh.split_by_component();
+        assert_eq!(parts.len(), 2);
+        for (part, x) in parts.iter().zip([0.0, 5.0]) {
+            assert_eq!(part.get_name(), "pair");
+            assert_eq!(part.faces().len(), 1);
+            assert_eq!(part.positions().len(), 3);
+            let corners: Vec<[f32; 2]> = part.faces()[0]
+                .elements()
+                .iter()
+                .map(|e| {
+                    let p = &part.positions()[e.position_index() as usize - 1];
+                    [p.x, p.y]
+                })
+                .collect();
+            assert_eq!(corners, vec![[x, 0.0], [x + 1.0, 0.0], [x, 1.0]]);
+        }
+    }
+
+    #[test]
+    fn clearing_the_material_survives_a_write() {
+        let dir = std::env::temp_dir().join("suoi_simp_clear_material");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("clear.mtl"), "newmtl red\nKd 1 0 0\n").unwrap();
+        let text = "mtllib clear.mtl\no strip\nv 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\nusemtl red\nf 1 2 3\nusemtl\nf 1 2 3\n";
+        std::fs::write(dir.join("clear.obj"), text).unwrap();
+
+        let obj = Obj::import(&dir.join("clear.obj")).unwrap();
+        let red = obj.materials().get_handle("red");
+        assert_eq!(face_materials(&obj.meshes()[0]), vec![None, red, None]);
+
+        let mut out = vec![];
+        obj.write(&mut out, FloatFormat::Shortest).unwrap();
+        std::fs::write(dir.join("copy.obj"), out).unwrap();
+        let copy = Obj::import(&dir.join("copy.obj")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(obj.meshes(), copy.meshes());
+    }
+}
@@ -0,0 +1,364 @@
+use suoi_types::Vector3;
+
+use crate::math::{self, Vec3};
+use crate::obj_mesh::ObjMesh;
+
+/// The axis pointing up in a `CoordinateSystem`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+    Y,
+    Z,
+}
+
+/// Whether `x` cross `y` gives `z` (right-handed) or `-z` (left-handed)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handedness {
+    Right,
+    Left,
+}
+
+/**
+`obj::CoordinateSystem`
+---
+The conventions of a tool or engine: which axis is up, the handedness,
+and the size of a unit in meters. The default, right-handed and Y-up in
+meters, is the convention of `.obj` files and of `Obj::import`.
+
+The common combinations relate to the default as follows:
+right-handed Z-up (Blender, 3ds Max) has `(x, y, z)` at `(x, z, -y)`,
+left-handed Y-up (Unity, Direct3D) at `(x, y, -z)`, and
+left-handed Z-up (Unreal) at `(x, z, y)`.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordinateSystem {
+    up: UpAxis,
+    handedness: Handedness,
+    meters_per_unit: f32,
+}
+
+impl Default for CoordinateSystem {
+    fn default() -> Self {
+        Self {
+            up: UpAxis::Y,
+            handedness: Handedness::Right,
+            meters_per_unit: 1.0,
+        }
+    }
+}
+
+impl CoordinateSystem {
+    pub fn new(up: UpAxis, handedness: Handedness, meters_per_unit: f32) -> Self {
+        Self {
+            up,
+            handedness,
+            meters_per_unit,
+        }
+    }
+
+    /// Immutable getter for `up` of `CoordinateSystem`
+    pub fn up(&self) -> UpAxis {
+        self.up
+    }
+
+    /// Setter for `up` of `CoordinateSystem`
+    pub fn set_up(&mut self, value: UpAxis) {
+        self.up = value;
+    }
+
+    /// Immutable getter for `handedness` of `CoordinateSystem`
+    pub fn handedness(&self) -> Handedness {
+        self.handedness
+    }
+
+    /// Setter for `handedness` of `CoordinateSystem`
+    pub fn set_handedness(&mut self, value: Handedness) {
+        self.handedness = value;
+    }
+
+    /// Immutable getter for `meters_per_unit` of `CoordinateSystem`
+    pub fn meters_per_unit(&self) -> f32 {
+        self.meters_per_unit
+    }
+
+    /// Setter for `meters_per_unit` of `CoordinateSystem`, `0.01` for centimeters
+    pub fn set_meters_per_unit(&mut self, value: f32) {
+        self.meters_per_unit = value;
+    }
+
+    /// Rows of the matrix taking coordinates of `self` to the default system
+    fn to_default(&self) -> [Vec3; 3] {
+        match (self.up, self.handedness) {
+            (UpAxis::Y, Handedness::Right) => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            (UpAxis::Y, Handedness::Left) => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]],
+            (UpAxis::Z, Handedness::Right) => [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+            (UpAxis::Z, Handedness::Left) => [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+        }
+    }
+}
+
+/**
+`obj::AxisConversion`
+---
+Converts coordinates from one `CoordinateSystem` to another: a rotation,
+a mirror when the handedness changes, and a uniform scale for the units
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisConversion {
+    // rows of the axis change, without the scale
+    matrix: [Vec3; 3],
+    scale: f32,
+}
+
+impl AxisConversion {
+    /// Creates the `AxisConversion` from coordinates of `from` to coordinates of `to`
+    pub fn between(from: &CoordinateSystem, to: &CoordinateSystem) -> Self {
+        let source = from.to_default();
+        let target = to.to_default();
+
+        // the matrices are orthonormal, so the inverse of `target` is its transpose
+        let matrix = std::array::from_fn(|row| {
+            std::array::from_fn(|column| {
+                (0..3)
+                    .map(|k| target[k][row] * source[k][column])
+                    .sum::<f32>()
+            })
+        });
+
+        Self {
+            matrix,
+            scale: from.meters_per_unit() / to.meters_per_unit(),
+        }
+    }
+
+    /// Returns `true` when the conversion leaves coordinates unchanged
+    pub fn is_identity(&self) -> bool {
+        self.scale == 1.0 && self.matrix == [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+    }
+
+    /// Returns `true` when the handedness changes, which reverses the winding of faces
+    pub fn flips_winding(&self) -> bool {
+        let [a, b, c] = self.matrix;
+        math::dot(a, math::cross(b, c)) < 0.0
+    }
+
+    fn rotate(&self, v: Vec3) -> Vec3 {
+        self.matrix.map(|row| math::dot(row, v))
+    }
+
+    /// Converts a position, axes and units
+    pub fn convert_position(&self, position: &Vector3) -> Vector3 {
+        math::to_vector(math::scale(
+            self.rotate(math::from_vector(position)),
+            self.scale,
+        ))
+    }
+
+    /// Converts a direction, such as a normal, which only changes axes
+    pub fn convert_direction(&self, direction: &Vector3) -> Vector3 {
+        math::to_vector(self.rotate(math::from_vector(direction)))
+    }
+
+    /**
+    `AxisConversion.convert_tangent`
+    ---
+    Converts a tangent and the sign `w` of its bitangent, `cross(normal,
+    tangent) * w`, which changes along with the handedness
+    */
+    pub fn convert_tangent(&self, tangent: &Vector3, w: f32) -> (Vector3, f32) {
+        let w = if self.flips_winding() { -w } else { w };
+        (self.convert_direction(tangent), w)
+    }
+}
+
+impl ObjMesh {
+    /**
+    `ObjMesh.convert_coordinates`
+    ---
+    Converts the positions and normals of `self` with `conversion`, and
+    reverses the winding of the faces when the handedness changes so that
+    they keep facing the same way. Free-form geometry keeps its parameter
+    space, only its control points move.
+    */
+    pub fn convert_coordinates(&mut self, conversion: &AxisConversion) {
+        if conversion.is_identity() {
+            return;
+        }
+
+        for position in self.positions_mut() {
+            *position = conversion.convert_position(position);
+        }
+        for normal in self.normals_mut() {
+            *normal = conversion.convert_direction(normal);
+        }
+        if conversion.flips_winding() {
+            for face in self.faces_mut() {
+                face.elements_mut().reverse();
+            }
+        }
+    }
+
+    /**
+    `ObjMesh.flip_v`
+    ---
+    Replaces the `v` of every texture coordinate with `1 - v`, for APIs
+    whose textures start at the top left rather than the bottom left
+    */
+    pub fn flip_v(&mut self) {
+        for uv in self.uvs_mut() {
+            uv.y = 1.0 - uv.y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::{Face, FaceElement};
+    use crate::{Obj, ObjImportOptions};
+    use suoi_types::Vector2;
+
+    fn assert_close(actual: &Vector3, expected: Vec3) {
+        let actual = math::from_vector(actual);
+        assert!(
+            (0..3).all(|i| (actual[i] - expected[i]).abs() < 1e-6),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    fn triangle() -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for [x, y, z] in [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            mesh.load_position(Vector3::new(x, y, z));
+        }
+        mesh.load_normal(Vector3::new(0.0, 0.0, 1.0));
+        let elements = (1..=3).map(|i| FaceElement::new(i, 1, 0)).collect();
+        mesh.load_face(Face::new(elements));
+        mesh
+    }
+
+    fn winding(mesh: &ObjMesh) -> Vec<u32> {
+        mesh.faces()[0]
+            .elements()
+            .iter()
+            .map(FaceElement::position_index)
+            .collect()
+    }
+
+    #[test]
+    fn converts_z_up_to_y_up() {
+        let blender = CoordinateSystem::new(UpAxis::Z, Handedness::Right, 0.01);
+        let conversion = AxisConversion::between(&blender, &CoordinateSystem::default());
+        assert!(!conversion.flips_winding());
+
+        // up stays up, forward becomes -z, and centimeters become meters
+        let up = conversion.convert_direction(&Vector3::new(0.0, 0.0, 1.0));
+        assert_close(&up, [0.0, 1.0, 0.0]);
+        let forward = conversion.convert_direction(&Vector3::new(0.0, 1.0, 0.0));
+        assert_close(&forward, [0.0, 0.0, -1.0]);
+        let position = conversion.convert_position(&Vector3::new(100.0, 200.0, 300.0));
+        assert_close(&position, [1.0, 3.0, -2.0]);
+
+        let back = AxisConversion::between(&CoordinateSystem::default(), &blender);
+        assert_close(&back.convert_position(&position), [100.0, 200.0, 300.0]);
+    }
+
+    #[test]
+    fn converts_left_to_right_handed() {
+        let unity = CoordinateSystem::new(UpAxis::Y, Handedness::Left, 1.0);
+        let conversion = AxisConversion::between(&unity, &CoordinateSystem::default());
+        assert!(conversion.flips_winding());
+        assert_close(
+            &conversion.convert_position(&Vector3::new(1.0, 2.0, 3.0)),
+            [1.0, 2.0, -3.0],
+        );
+
+        let unreal = CoordinateSystem::new(UpAxis::Z, Handedness::Left, 1.0);
+        let conversion = AxisConversion::between(&unreal, &CoordinateSystem::default());
+        assert!(conversion.flips_winding());
+        assert_close(
+            &conversion.convert_position(&Vector3::new(1.0, 2.0, 3.0)),
+            [1.0, 3.0, 2.0],
+        );
+
+        let (tangent, w) = conversion.convert_tangent(&Vector3::new(0.0, 1.0, 0.0), 1.0);
+        assert_close(&tangent, [0.0, 0.0, 1.0]);
+        assert_eq!(w, -1.0);
+    }
+
+    #[test]
+    fn identity_only_between_matching_systems() {
+        for up in [UpAxis::Y, UpAxis::Z] {
+            for handedness in [Handedness::Right, Handedness::Left] {
+                let system = CoordinateSystem::new(up, handedness, 0.01);
+                assert!(AxisConversion::between(&system, &system.clone()).is_identity());
+
+                let mut meters = system.clone();
+                meters.set_meters_per_unit(1.0);
+                assert!(!AxisConversion::between(&system, &meters).is_identity());
+            }
+        }
+
+        let mut mesh = triangle();
+        let identity =
+            AxisConversion::between(&CoordinateSystem::default(), &CoordinateSystem::default());
+        mesh.convert_coordinates(&identity);
+        assert_eq!(winding(&mesh), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn flipped_handedness_reverses_faces() {
+        let unity = CoordinateSystem::new(UpAxis::Y, Handedness::Left, 1.0);
+        let mut mesh = triangle();
+        mesh.convert_coordinates(&AxisConversion::between(
+            &unity,
+            &CoordinateSystem::default(),
+        ));
+        assert_eq!(winding(&mesh), vec![3, 2, 1]);
+        assert_close(&mesh.normals()[0], [0.0, 0.0, -1.0]);
+
+        // a rotation alone keeps the winding
+        let blender = CoordinateSystem::new(UpAxis::Z, Handedness::Right, 1.0);
+        let mut mesh = triangle();
+        mesh.convert_coordinates(&AxisConversion::between(
+            &blender,
+            &CoordinateSystem::default(),
+        ));
+        assert_eq!(winding(&mesh), vec![1, 2, 3]);
+        assert_close(&mesh.normals()[0], [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn flip_v_mirrors_texture_coordinates() {
+        let mut mesh = ObjMesh::empty();
+        mesh.load_uv(Vector2 { x: 0.25, y: 0.0 });
+        mesh.load_uv(Vector2 { x: 0.5, y: 0.75 });
+        mesh.flip_v();
+
+        let uvs: Vec<[f32; 2]> = mesh.uvs().iter().map(|uv| [uv.x, uv.y]).collect();
+        assert_eq!(uvs, vec![[0.25, 1.0], [0.5, 0.25]]);
+    }
+
+    #[test]
+    fn import_with_options_converts_coordinates() {
+        let dir = std::env::temp_dir().join("suoi_simp_coordinates");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("z_up.obj");
+        let text =
+            "o tri\nv 0 0 0\nv 100 0 0\nv 0 0 100\nvt 0 0.25\nvn 0 -1 0\nf 1/1/1 2/1/1 3/1/1\n";
+        std::fs::write(&path, text).unwrap();
+
+        let mut options = ObjImportOptions::default();
+        options.set_source_system(CoordinateSystem::new(UpAxis::Z, Handedness::Left, 0.01));
+        options.set_flip_v(true);
+        let obj = Obj::import_with_options(&path, &options);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let obj = obj.unwrap();
+        let mesh = &obj.meshes()[0];
+        assert_close(&mesh.positions()[1], [1.0, 0.0, 0.0]);
+        assert_close(&mesh.positions()[2], [0.0, 1.0, 0.0]);
+        assert_close(&mesh.normals()[0], [0.0, 0.0, -1.0]);
+        assert_eq!(mesh.uvs()[0].y, 0.75);
+        assert_eq!(winding(mesh), vec![3, 2, 1]);
+    }
+}
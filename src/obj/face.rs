@@ -1,3 +1,8 @@
+use crate::freeform::{ElementCounts, FreeformState};
+use crate::Fs;
+use crate::ImportError;
+use crate::MaterialHandle;
+
 /**
 `obj::FaceELement`
 ---
@@ -5,7 +10,7 @@ Substructure for `obj::Face` that stores the
 index into the `position`, `normal`, and `uv` buffers
 of a single polygonal `vertex`
 */
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct FaceElement {
     position_index: u32,
     normal_index: u32,
@@ -57,9 +62,9 @@ impl FaceElement {
             })
             .collect();
 
-        let pos_index: u32 = *tokens.get(0).unwrap();
-        let uvs_index: u32 = *tokens.get(1).unwrap();
-        let nrm_index: u32 = *tokens.get(2).unwrap();
+        let pos_index: u32 = tokens.first().copied().unwrap_or(0);
+        let uvs_index: u32 = tokens.get(1).copied().unwrap_or(0);
+        let nrm_index: u32 = tokens.get(2).copied().unwrap_or(0);
 
         Self {
             position_index: pos_index,
@@ -67,6 +72,89 @@ impl FaceElement {
             uv_index: uvs_index,
         }
     }
+
+    /**
+    `FaceElement::resolve`
+    ---
+    Parses a `v/vt/vn` element whose indices may be negative,
+    turning them into the absolute indices they refer to,
+    given the number of elements of each kind defined so far
+    */
+    pub(crate) fn resolve(token: &str, counts: ElementCounts) -> Result<Self, ImportError> {
+        let mut parts = token.split('/');
+        let mut next = |count: u32| match parts.next() {
+            Some("") | None => Ok(0),
+            Some(part) => FreeformState::resolve_index(part, count),
+        };
+
+        let position = next(counts.positions)?;
+        let uv = next(counts.uvs)?;
+        let normal = next(counts.normals)?;
+        if position == 0 {
+            return Err(ImportError::InvalidData);
+        }
+
+        Ok(Self::new(position, normal, uv))
+    }
+
+    /**
+    `FaceElement::parse_bytes`
+    ---
+    Byte counterpart of `FaceElement::parse`, with the
+    same replacement of syntax errors by `0`. Negative
+    indices need the element counts and are left to
+    `FaceElement::resolve`, see `ObjEvent::RelativeFace`.
+    */
+    pub fn parse_bytes(element: &[u8]) -> Self {
+        match Self::read_bytes(element) {
+            Some((parsed, end)) if end == element.len() => parsed,
+            _ => {
+                let mut indices = [0u32; 3];
+                for (index, token) in indices.iter_mut().zip(element.split(|&b| b == b'/')) {
+                    *index = Fs::parse_uint_bytes(token).unwrap_or(0);
+                }
+                let [pos_index, uvs_index, nrm_index] = indices;
+
+                Self::new(pos_index, nrm_index, uvs_index)
+            }
+        }
+    }
+
+    /**
+    `FaceElement::read_bytes`
+    ---
+    Reads the `v/vt/vn` element at the start of `bytes`, up to the first
+    whitespace, and returns it with the number of bytes it spans. Plain
+    digits and slashes are read in a single pass, as this runs for every
+    corner of every face; anything else, negative indices included,
+    returns `None`.
+    */
+    pub(crate) fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        let mut indices = [0u32; 3];
+        let mut slot = 0;
+        let mut value: u32 = 0;
+        let mut end = bytes.len();
+
+        for (at, &b) in bytes.iter().enumerate() {
+            match b {
+                b'0'..=b'9' => value = value.checked_mul(10)?.checked_add((b - b'0') as u32)?,
+                b'/' if slot < 2 => {
+                    indices[slot] = value;
+                    slot += 1;
+                    value = 0;
+                }
+                b if b.is_ascii_whitespace() => {
+                    end = at;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+        indices[slot] = value;
+        let [pos_index, uvs_index, nrm_index] = indices;
+
+        Some((Self::new(pos_index, nrm_index, uvs_index), end))
+    }
 }
 
 /**
@@ -75,7 +163,7 @@ impl FaceElement {
 Represents a polygonal face by storing indices
 for the relevant `position`, `normal` and `uv` buffers
 */
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Face {
     elements: Vec<FaceElement>,
 }
@@ -93,4 +181,79 @@ impl Face {
     pub fn elements(&self) -> &Vec<FaceElement> {
         &self.elements
     }
+
+    /// Mutable getter for `Face.elements`
+    pub(crate) fn elements_mut(&mut self) -> &mut Vec<FaceElement> {
+        &mut self.elements
+    }
+}
+
+/**
+`obj::FaceGroup`
+---
+Names set by a `g` statement, applying to every
+face from `first_face` up to the next `FaceGroup`
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaceGroup {
+    names: Vec<String>,
+    first_face: usize,
+}
+
+impl FaceGroup {
+    /**
+    `FaceGroup::new`
+    ---
+    Creates a new `FaceGroup` starting at the face with index `first_face`
+    */
+    pub fn new(names: Vec<String>, first_face: usize) -> Self {
+        Self { names, first_face }
+    }
+
+    /// Immutable getter for `names` of `FaceGroup`
+    pub fn names(&self) -> &Vec<String> {
+        &self.names
+    }
+
+    /// Immutable getter for `first_face` of `FaceGroup`
+    pub fn first_face(&self) -> usize {
+        self.first_face
+    }
+}
+
+/**
+`obj::MaterialSpan`
+---
+Material set by a `usemtl` statement, applying to every
+face from `first_face` up to the next `MaterialSpan`.
+`None` when the faces have no material.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialSpan {
+    material: Option<MaterialHandle>,
+    first_face: usize,
+}
+
+impl MaterialSpan {
+    /**
+    `MaterialSpan::new`
+    ---
+    Creates a new `MaterialSpan` starting at the face with index `first_face`
+    */
+    pub fn new(material: Option<MaterialHandle>, first_face: usize) -> Self {
+        Self {
+            material,
+            first_face,
+        }
+    }
+
+    /// Immutable getter for `material` of `MaterialSpan`
+    pub fn material(&self) -> Option<MaterialHandle> {
+        self.material
+    }
+
+    /// Immutable getter for `first_face` of `MaterialSpan`
+    pub fn first_face(&self) -> usize {
+        self.first_face
+    }
 }
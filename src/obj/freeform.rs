@@ -0,0 +1,753 @@
+use crate::face::FaceElement;
+use crate::{Fs, ImportError, MaterialHandle, Tokens};
+
+/**
+`obj::CurveType`
+---
+Basis of a free-form curve or surface,
+set by the `cstype` statement
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveType {
+    BasisMatrix,
+    Bezier,
+    BSpline,
+    Cardinal,
+    Taylor,
+}
+
+impl CurveType {
+    /**
+    `CurveType::parse`
+    ---
+    Converts a `cstype` argument into a `CurveType`
+    */
+    pub fn parse(token: &str) -> Result<Self, ImportError> {
+        match token {
+            "bmatrix" => Ok(Self::BasisMatrix),
+            "bezier" => Ok(Self::Bezier),
+            "bspline" => Ok(Self::BSpline),
+            "cardinal" => Ok(Self::Cardinal),
+            "taylor" => Ok(Self::Taylor),
+            _ => Err(ImportError::UnrecognisedToken(token.to_owned())),
+        }
+    }
+
+    /// Returns the `cstype` argument that selects this basis
+    pub fn token(&self) -> &'static str {
+        match self {
+            Self::BasisMatrix => "bmatrix",
+            Self::Bezier => "bezier",
+            Self::BSpline => "bspline",
+            Self::Cardinal => "cardinal",
+            Self::Taylor => "taylor",
+        }
+    }
+}
+
+/**
+`obj::FreeformAttributes`
+---
+State set by the `cstype`, `deg`, `bmat` and `step` statements,
+shared by every curve and surface defined after them
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreeformAttributes {
+    curve_type: CurveType,
+    rational: bool,
+    degree_u: u32,
+    degree_v: u32,
+    basis_u: Vec<f32>,
+    basis_v: Vec<f32>,
+    step_u: Option<f32>,
+    step_v: Option<f32>,
+}
+
+impl FreeformAttributes {
+    /// Immutable getter for `curve_type` of `FreeformAttributes`
+    pub fn curve_type(&self) -> CurveType {
+        self.curve_type
+    }
+
+    /// Immutable getter for `rational` of `FreeformAttributes`
+    pub fn rational(&self) -> bool {
+        self.rational
+    }
+
+    /// Immutable getter for `degree_u` of `FreeformAttributes`
+    pub fn degree_u(&self) -> u32 {
+        self.degree_u
+    }
+
+    /// Immutable getter for `degree_v` of `FreeformAttributes`
+    pub fn degree_v(&self) -> u32 {
+        self.degree_v
+    }
+
+    /// Immutable getter for the `bmat u` matrix of `FreeformAttributes`
+    pub fn basis_u(&self) -> &Vec<f32> {
+        &self.basis_u
+    }
+
+    /// Immutable getter for the `bmat v` matrix of `FreeformAttributes`
+    pub fn basis_v(&self) -> &Vec<f32> {
+        &self.basis_v
+    }
+
+    /// Immutable getter for the `u` argument of `step`
+    pub fn step_u(&self) -> Option<f32> {
+        self.step_u
+    }
+
+    /// Immutable getter for the `v` argument of `step`
+    pub fn step_v(&self) -> Option<f32> {
+        self.step_v
+    }
+}
+
+impl Default for FreeformAttributes {
+    fn default() -> Self {
+        Self {
+            curve_type: CurveType::BSpline,
+            rational: false,
+            degree_u: 3,
+            degree_v: 3,
+            basis_u: vec![],
+            basis_v: vec![],
+            step_u: None,
+            step_v: None,
+        }
+    }
+}
+
+/**
+`obj::TrimCurve`
+---
+Reference to the `[start, end]` interval of a
+`curv2` curve, as used by `trim`, `hole` and `scrv`
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimCurve {
+    start: f32,
+    end: f32,
+    curve2_index: u32,
+}
+
+impl TrimCurve {
+    /// Immutable getter for `start` of `TrimCurve`
+    pub fn start(&self) -> f32 {
+        self.start
+    }
+
+    /// Immutable getter for `end` of `TrimCurve`
+    pub fn end(&self) -> f32 {
+        self.end
+    }
+
+    /// 1-based index of the `curv2` curve in the file
+    pub fn curve2_index(&self) -> u32 {
+        self.curve2_index
+    }
+}
+
+/**
+`obj::Curve`
+---
+Free-form space curve defined by a `curv` statement.
+Control points are position indices counting from
+the start of the file, like face indices.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    attributes: FreeformAttributes,
+    start: f32,
+    end: f32,
+    control_points: Vec<u32>,
+    knots: Vec<f32>,
+    special_points: Vec<u32>,
+    material: Option<MaterialHandle>,
+}
+
+impl Curve {
+    /// Immutable getter for `attributes` of `Curve`
+    pub fn attributes(&self) -> &FreeformAttributes {
+        &self.attributes
+    }
+
+    /// Immutable getter for `start` of `Curve`
+    pub fn start(&self) -> f32 {
+        self.start
+    }
+
+    /// Immutable getter for `end` of `Curve`
+    pub fn end(&self) -> f32 {
+        self.end
+    }
+
+    /// Immutable getter for `control_points` of `Curve`
+    pub fn control_points(&self) -> &Vec<u32> {
+        &self.control_points
+    }
+
+    /// Mutable getter for `control_points` of `Curve`
+    pub(crate) fn control_points_mut(&mut self) -> &mut Vec<u32> {
+        &mut self.control_points
+    }
+
+    /// Immutable getter for the `parm u` values of `Curve`
+    pub fn knots(&self) -> &Vec<f32> {
+        &self.knots
+    }
+
+    /// Immutable getter for the `sp` parameter vertices of `Curve`
+    pub fn special_points(&self) -> &Vec<u32> {
+        &self.special_points
+    }
+
+    /// Material active when the curve was defined
+    pub fn material(&self) -> Option<MaterialHandle> {
+        self.material
+    }
+}
+
+/**
+`obj::Curve2`
+---
+Free-form curve in the parameter space of a surface,
+defined by a `curv2` statement. Control points are
+`vp` indices counting from the start of the file.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve2 {
+    attributes: FreeformAttributes,
+    control_points: Vec<u32>,
+    knots: Vec<f32>,
+    special_points: Vec<u32>,
+}
+
+impl Curve2 {
+    /// Immutable getter for `attributes` of `Curve2`
+    pub fn attributes(&self) -> &FreeformAttributes {
+        &self.attributes
+    }
+
+    /// Immutable getter for `control_points` of `Curve2`
+    pub fn control_points(&self) -> &Vec<u32> {
+        &self.control_points
+    }
+
+    /// Immutable getter for the `parm u` values of `Curve2`
+    pub fn knots(&self) -> &Vec<f32> {
+        &self.knots
+    }
+
+    /// Immutable getter for the `sp` parameter vertices of `Curve2`
+    pub fn special_points(&self) -> &Vec<u32> {
+        &self.special_points
+    }
+}
+
+/**
+`obj::Surface`
+---
+Free-form surface defined by a `surf` statement.
+Control points are `v/vt/vn` elements ordered with `u`
+varying fastest, counting from the start of the file.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Surface {
+    attributes: FreeformAttributes,
+    start_u: f32,
+    end_u: f32,
+    start_v: f32,
+    end_v: f32,
+    control_points: Vec<FaceElement>,
+    knots_u: Vec<f32>,
+    knots_v: Vec<f32>,
+    trims: Vec<Vec<TrimCurve>>,
+    holes: Vec<Vec<TrimCurve>>,
+    special_curves: Vec<Vec<TrimCurve>>,
+    special_points: Vec<u32>,
+    material: Option<MaterialHandle>,
+}
+
+impl Surface {
+    /// Immutable getter for `attributes` of `Surface`
+    pub fn attributes(&self) -> &FreeformAttributes {
+        &self.attributes
+    }
+
+    /// Immutable getter for the `s0 s1` range of `Surface`
+    pub fn range_u(&self) -> (f32, f32) {
+        (self.start_u, self.end_u)
+    }
+
+    /// Immutable getter for the `t0 t1` range of `Surface`
+    pub fn range_v(&self) -> (f32, f32) {
+        (self.start_v, self.end_v)
+    }
+
+    /// Immutable getter for `control_points` of `Surface`
+    pub fn control_points(&self) -> &Vec<FaceElement> {
+        &self.control_points
+    }
+
+    /// Mutable getter for `control_points` of `Surface`
+    pub(crate) fn control_points_mut(&mut self) -> &mut Vec<FaceElement> {
+        &mut self.control_points
+    }
+
+    /// Immutable getter for the `parm u` values of `Surface`
+    pub fn knots_u(&self) -> &Vec<f32> {
+        &self.knots_u
+    }
+
+    /// Immutable getter for the `parm v` values of `Surface`
+    pub fn knots_v(&self) -> &Vec<f32> {
+        &self.knots_v
+    }
+
+    /// Outer trimming loops, one per `trim` statement
+    pub fn trims(&self) -> &Vec<Vec<TrimCurve>> {
+        &self.trims
+    }
+
+    /// Inner trimming loops, one per `hole` statement
+    pub fn holes(&self) -> &Vec<Vec<TrimCurve>> {
+        &self.holes
+    }
+
+    /// Special curves, one per `scrv` statement
+    pub fn special_curves(&self) -> &Vec<Vec<TrimCurve>> {
+        &self.special_curves
+    }
+
+    /// Immutable getter for the `sp` parameter vertices of `Surface`
+    pub fn special_points(&self) -> &Vec<u32> {
+        &self.special_points
+    }
+
+    /// Material active when the surface was defined
+    pub fn material(&self) -> Option<MaterialHandle> {
+        self.material
+    }
+}
+
+/**
+`obj::SurfaceConnection`
+---
+Connectivity between the edges of two surfaces,
+defined by a `con` statement
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceConnection {
+    surfaces: [u32; 2],
+    curves: [TrimCurve; 2],
+}
+
+impl SurfaceConnection {
+    /// 1-based indices of the two connected surfaces in the file
+    pub fn surfaces(&self) -> [u32; 2] {
+        self.surfaces
+    }
+
+    /// Edge of each surface, as a `curv2` interval
+    pub fn curves(&self) -> [TrimCurve; 2] {
+        self.curves
+    }
+}
+
+/**
+`obj::FreeformElement`
+---
+A curve or surface whose body (`parm`, `trim`, ...)
+is still being read, up to its `end` statement
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum FreeformElement {
+    Curve(Curve),
+    Curve2(Curve2),
+    Surface(Surface),
+}
+
+/**
+`obj::ElementCounts`
+---
+Number of positions, uvs, normals and parameter vertices
+read so far, used to resolve negative (relative) indices
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ElementCounts {
+    pub positions: u32,
+    pub uvs: u32,
+    pub normals: u32,
+    pub parameters: u32,
+}
+
+impl std::ops::Add for ElementCounts {
+    type Output = Self;
+
+    /// The counts of elements read in a chunk, `other`, after the elements `self`
+    fn add(self, other: Self) -> Self {
+        Self {
+            positions: self.positions + other.positions,
+            uvs: self.uvs + other.uvs,
+            normals: self.normals + other.normals,
+            parameters: self.parameters + other.parameters,
+        }
+    }
+}
+
+/**
+`obj::FreeformState`
+---
+Parser state for the free-form statements of an `.obj` file
+*/
+#[derive(Debug, Default)]
+pub(crate) struct FreeformState {
+    attributes: FreeformAttributes,
+    current: Option<FreeformElement>,
+}
+
+impl FreeformState {
+    /**
+    `FreeformState.parse_statement`
+    ---
+    Handles the free-form statement `cmd`.
+    Returns `Ok(Some(element))` when an `end` statement completes an element,
+    `Ok(None)` for every other statement, and `UnrecognisedToken`
+    if `cmd` isn't a free-form statement.
+    */
+    pub fn parse_statement(
+        &mut self,
+        cmd: &str,
+        tokens: &mut Tokens,
+        counts: ElementCounts,
+        material: Option<MaterialHandle>,
+    ) -> Result<Option<FreeformElement>, ImportError> {
+        match cmd {
+            "cstype" => {
+                // curve or surface type
+                let mut token = tokens.next().ok_or(ImportError::InvalidData)?;
+                self.attributes.rational = token == "rat";
+                if self.attributes.rational {
+                    token = tokens.next().ok_or(ImportError::InvalidData)?;
+                }
+                self.attributes.curve_type = CurveType::parse(token)?;
+            }
+            "deg" => {
+                // degree
+                self.attributes.degree_u = Fs::parse_uint(tokens)?;
+                self.attributes.degree_v = match tokens.next() {
+                    Some(token) => token.parse().or(Err(ImportError::InvalidData))?,
+                    None => self.attributes.degree_u,
+                };
+            }
+            "bmat" => {
+                // basis matrix
+                let direction = tokens.next().ok_or(ImportError::InvalidData)?;
+                let matrix = Self::parse_floats(tokens)?;
+                match direction {
+                    "u" => self.attributes.basis_u = matrix,
+                    "v" => self.attributes.basis_v = matrix,
+                    _ => return Err(ImportError::InvalidData),
+                }
+            }
+            "step" => {
+                // step size
+                self.attributes.step_u = Some(Fs::parse_float(tokens)?);
+                self.attributes.step_v = match tokens.next() {
+                    Some(token) => Some(token.parse().or(Err(ImportError::InvalidData))?),
+                    None => None,
+                };
+            }
+            "curv" => {
+                // space curve
+                let start = Fs::parse_float(tokens)?;
+                let end = Fs::parse_float(tokens)?;
+                let control_points = tokens
+                    .map(|token| Self::resolve_index(token, counts.positions))
+                    .collect::<Result<Vec<u32>, ImportError>>()?;
+
+                self.begin(FreeformElement::Curve(Curve {
+                    attributes: self.attributes.clone(),
+                    start,
+                    end,
+                    control_points,
+                    knots: vec![],
+                    special_points: vec![],
+                    material,
+                }))?;
+            }
+            "curv2" => {
+                // curve in parameter space
+                let control_points = tokens
+                    .map(|token| Self::resolve_index(token, counts.parameters))
+                    .collect::<Result<Vec<u32>, ImportError>>()?;
+
+                self.begin(FreeformElement::Curve2(Curve2 {
+                    attributes: self.attributes.clone(),
+                    control_points,
+                    knots: vec![],
+                    special_points: vec![],
+                }))?;
+            }
+            "surf" => {
+                // surface
+                let start_u = Fs::parse_float(tokens)?;
+                let end_u = Fs::parse_float(tokens)?;
+                let start_v = Fs::parse_float(tokens)?;
+                let end_v = Fs::parse_float(tokens)?;
+                let control_points = tokens
+                    .map(|token| FaceElement::resolve(token, counts))
+                    .collect::<Result<Vec<FaceElement>, ImportError>>()?;
+
+                self.begin(FreeformElement::Surface(Surface {
+                    attributes: self.attributes.clone(),
+                    start_u,
+                    end_u,
+                    start_v,
+                    end_v,
+                    control_points,
+                    knots_u: vec![],
+                    knots_v: vec![],
+                    trims: vec![],
+                    holes: vec![],
+                    special_curves: vec![],
+                    special_points: vec![],
+                    material,
+                }))?;
+            }
+            "parm" => {
+                // knot vector or global parameters
+                let direction = tokens.next().ok_or(ImportError::InvalidData)?;
+                let values = Self::parse_floats(tokens)?;
+                match (self.current.as_mut(), direction) {
+                    (Some(FreeformElement::Curve(curve)), "u") => curve.knots = values,
+                    (Some(FreeformElement::Curve2(curve)), "u") => curve.knots = values,
+                    (Some(FreeformElement::Surface(surf)), "u") => surf.knots_u = values,
+                    (Some(FreeformElement::Surface(surf)), "v") => surf.knots_v = values,
+                    _ => return Err(ImportError::InvalidData),
+                }
+            }
+            "trim" | "hole" | "scrv" => {
+                // trimming loop, hole or special curve
+                let curves = Self::parse_trim_curves(tokens)?;
+                let surf = match self.current.as_mut() {
+                    Some(FreeformElement::Surface(surf)) => surf,
+                    _ => return Err(ImportError::InvalidData),
+                };
+                match cmd {
+                    "trim" => surf.trims.push(curves),
+                    "hole" => surf.holes.push(curves),
+                    _ => surf.special_curves.push(curves),
+                }
+            }
+            "sp" => {
+                // special points
+                let points = tokens
+                    .map(|token| Self::resolve_index(token, counts.parameters))
+                    .collect::<Result<Vec<u32>, ImportError>>()?;
+                match self.current.as_mut() {
+                    Some(FreeformElement::Curve(curve)) => curve.special_points.extend(points),
+                    Some(FreeformElement::Curve2(curve)) => curve.special_points.extend(points),
+                    Some(FreeformElement::Surface(surf)) => surf.special_points.extend(points),
+                    None => return Err(ImportError::InvalidData),
+                }
+            }
+            "end" => {
+                // end of the element body
+                return self
+                    .current
+                    .take()
+                    .ok_or(ImportError::InvalidData)
+                    .map(Some);
+            }
+            _ => return Err(ImportError::UnrecognisedToken(cmd.to_owned())),
+        }
+
+        Ok(None)
+    }
+
+    /**
+    `FreeformState::parse_connection`
+    ---
+    Parses the arguments of a `con` statement:
+    `surf_1 q0_1 q1_1 curv2d_1 surf_2 q0_2 q1_2 curv2d_2`
+    */
+    pub fn parse_connection(tokens: &mut Tokens) -> Result<SurfaceConnection, ImportError> {
+        let mut surfaces = [0; 2];
+        let mut curves = [TrimCurve {
+            start: 0.0,
+            end: 0.0,
+            curve2_index: 0,
+        }; 2];
+
+        for side in 0..2 {
+            surfaces[side] = Fs::parse_uint(tokens)?;
+            curves[side] = TrimCurve {
+                start: Fs::parse_float(tokens)?,
+                end: Fs::parse_float(tokens)?,
+                curve2_index: Fs::parse_uint(tokens)?,
+            };
+        }
+
+        Ok(SurfaceConnection { surfaces, curves })
+    }
+
+    fn begin(&mut self, element: FreeformElement) -> Result<(), ImportError> {
+        // a new element can't start inside the body of another
+        if self.current.is_some() {
+            return Err(ImportError::InvalidData);
+        }
+
+        self.current = Some(element);
+        Ok(())
+    }
+
+    fn parse_floats(tokens: &mut Tokens) -> Result<Vec<f32>, ImportError> {
+        tokens
+            .map(|token| token.parse::<f32>().or(Err(ImportError::InvalidData)))
+            .collect()
+    }
+
+    fn parse_trim_curves(tokens: &mut Tokens) -> Result<Vec<TrimCurve>, ImportError> {
+        let mut curves = vec![];
+        while tokens.clone().next().is_some() {
+            curves.push(TrimCurve {
+                start: Fs::parse_float(tokens)?,
+                end: Fs::parse_float(tokens)?,
+                curve2_index: Fs::parse_uint(tokens)?,
+            });
+        }
+
+        Ok(curves)
+    }
+
+    /**
+    `FreeformState::resolve_index`
+    ---
+    Parses a 1-based index, turning a negative index
+    into the absolute index it refers to, given that
+    `count` elements of its kind were defined so far
+    */
+    pub fn resolve_index(token: &str, count: u32) -> Result<u32, ImportError> {
+        let index = token.parse::<i64>().or(Err(ImportError::InvalidData))?;
+        let absolute = if index < 0 {
+            count as i64 + 1 + index
+        } else {
+            index
+        };
+
+        match absolute {
+            1.. => u32::try_from(absolute).or(Err(ImportError::InvalidData)),
+            _ => Err(ImportError::InvalidData),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Export, FloatFormat, Obj, Resource};
+
+    fn parse(state: &mut FreeformState, line: &str) -> Option<FreeformElement> {
+        let counts = ElementCounts {
+            positions: 3,
+            ..Default::default()
+        };
+        let mut tokens = line.split_ascii_whitespace();
+        let cmd = tokens.next().unwrap();
+        state
+            .parse_statement(cmd, &mut tokens, counts, None)
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_a_curve_block() {
+        let mut state = FreeformState::default();
+        for line in [
+            "cstype bezier",
+            "deg 2",
+            "curv 0.0 1.0 1 -2 3",
+            "parm u 0 1",
+        ] {
+            assert_eq!(parse(&mut state, line), None);
+        }
+
+        let curve = match parse(&mut state, "end") {
+            Some(FreeformElement::Curve(curve)) => curve,
+            other => panic!("expected a curve, got {:?}", other),
+        };
+        assert_eq!(curve.attributes().curve_type(), CurveType::Bezier);
+        assert!(!curve.attributes().rational());
+        assert_eq!(curve.attributes().degree_u(), 2);
+        assert_eq!((curve.start(), curve.end()), (0.0, 1.0));
+        assert_eq!(curve.control_points(), &vec![1, 2, 3]);
+        assert_eq!(curve.knots(), &vec![0.0, 1.0]);
+
+        // the attributes outlive the block, the body does not
+        assert_eq!(parse(&mut state, "curv 0 1 1 2 3"), None);
+        let curve = match parse(&mut state, "end") {
+            Some(FreeformElement::Curve(curve)) => curve,
+            other => panic!("expected a curve, got {:?}", other),
+        };
+        assert_eq!(curve.attributes().degree_u(), 2);
+        assert!(curve.knots().is_empty());
+
+        let mut tokens = "".split_ascii_whitespace();
+        let counts = ElementCounts::default();
+        assert!(state
+            .parse_statement("end", &mut tokens, counts, None)
+            .is_err());
+    }
+
+    #[test]
+    fn round_trips_free_form_data_through_the_writer() {
+        let dir = std::env::temp_dir().join("suoi_simp_freeform_round_trip");
+        std::fs::create_dir_all(&dir).unwrap();
+        let text = "\
+o patch
+v 0 0 0
+v 1 0 0 2
+v 0 1 0
+v 1 1 1
+vp 0 0
+vp 1 1
+cstype rat bspline
+deg 1
+curv 0 1 1 2
+parm u 0 0 1 1
+end
+curv2 -2 -1
+parm u 0 0 1 1
+end
+cstype bezier
+deg 1 1
+surf 0 1 0 1 1 2 3 4
+parm u 0 1
+parm v 0 1
+trim 0 1 1
+sp 1
+end
+con 1 0 1 1 1 0 1 1
+";
+        std::fs::write(dir.join("source.obj"), text).unwrap();
+
+        let first = Obj::import(&dir.join("source.obj")).unwrap();
+        first
+            .export(&dir.join("copy.obj"), FloatFormat::Shortest)
+            .unwrap();
+        let second = Obj::import(&dir.join("copy.obj")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mesh = &first.meshes()[0];
+        assert_eq!(mesh.curves().len(), 1);
+        assert!(mesh.curves()[0].attributes().rational());
+        assert_eq!(mesh.curves2()[0].control_points(), &vec![1, 2]);
+        assert_eq!(mesh.surfaces()[0].trims().len(), 1);
+        assert_eq!(mesh.connections().len(), 1);
+        assert_eq!(mesh.weight(1), 2.0);
+        assert_eq!(first.meshes(), second.meshes());
+    }
+}
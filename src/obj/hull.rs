@@ -0,0 +1,781 @@
+use std::collections::HashMap;
+
+use suoi_types::Vector3;
+
+use crate::face::{Face, FaceElement};
+use crate::math::double::{add, cross, dot, length, scale, sub, Vec3d as Point};
+use crate::obj_mesh::ObjMesh;
+use crate::VertexAttribute;
+
+/// Number of cutting planes tried along each axis when splitting a part
+const SPLIT_PLANES: usize = 7;
+
+/**
+`obj::HullOptions`
+---
+Limits of the hulls built by `ObjMesh.convex_hull`. With `max_vertices`
+set, the hull only takes in the points furthest out until it reaches that
+many vertices, at least `4`, so it lies slightly inside the full hull.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct HullOptions {
+    max_vertices: Option<usize>,
+}
+
+impl HullOptions {
+    /// Immutable getter for `max_vertices` of `HullOptions`
+    pub fn max_vertices(&self) -> Option<usize> {
+        self.max_vertices
+    }
+
+    /// Setter for `max_vertices` of `HullOptions`
+    pub fn set_max_vertices(&mut self, value: Option<usize>) {
+        self.max_vertices = value.map(|value| value.max(4));
+    }
+}
+
+/**
+`obj::DecompositionOptions`
+---
+When `ObjMesh.convex_decomposition` stops splitting: once there are
+`max_hulls` parts, or when no part sinks further below its hull than
+`max_concavity`, relative to the diagonal of the mesh's bounding box,
+`0.02` by default. `hull` applies to every hull of the result.
+*/
+#[derive(Debug, Clone)]
+pub struct DecompositionOptions {
+    max_hulls: usize,
+    max_concavity: f32,
+    hull: HullOptions,
+}
+
+impl Default for DecompositionOptions {
+    fn default() -> Self {
+        Self {
+            max_hulls: 8,
+            max_concavity: 0.02,
+            hull: HullOptions::default(),
+        }
+    }
+}
+
+impl DecompositionOptions {
+    /// Immutable getter for `max_hulls` of `DecompositionOptions`
+    pub fn max_hulls(&self) -> usize {
+        self.max_hulls
+    }
+
+    /// Setter for `max_hulls` of `DecompositionOptions`
+    pub fn set_max_hulls(&mut self, value: usize) {
+        self.max_hulls = value.max(1);
+    }
+
+    /// Immutable getter for `max_concavity` of `DecompositionOptions`
+    pub fn max_concavity(&self) -> f32 {
+        self.max_concavity
+    }
+
+    /// Setter for `max_concavity` of `DecompositionOptions`
+    pub fn set_max_concavity(&mut self, value: f32) {
+        self.max_concavity = value;
+    }
+
+    /// Immutable getter for `hull` of `DecompositionOptions`
+    pub fn hull(&self) -> &HullOptions {
+        &self.hull
+    }
+
+    /// Setter for `hull` of `DecompositionOptions`
+    pub fn set_hull(&mut self, value: HullOptions) {
+        self.hull = value;
+    }
+}
+
+impl ObjMesh {
+    /**
+    `ObjMesh.convex_hull`
+    ---
+    Builds the convex hull of the positions of `self` with quickhull, as
+    a mesh of outward facing triangles named after `self` with a `_hull`
+    suffix. Returns `None` when the positions are all on a plane.
+    */
+    pub fn convex_hull(&self, options: &HullOptions) -> Option<ObjMesh> {
+        let points: Vec<Point> = self
+            .positions()
+            .iter()
+            .map(|p| [p.x as f64, p.y as f64, p.z as f64])
+            .filter(|p| p.iter().all(|c| c.is_finite()))
+            .collect();
+        let faces = quickhull(&points, options.max_vertices())?;
+
+        Some(hull_mesh(
+            &format!("{}_hull", self.get_name()),
+            &points,
+            &faces,
+        ))
+    }
+
+    /**
+    `ObjMesh.convex_decomposition`
+    ---
+    Approximates `self` with several convex hulls, for collision shapes of
+    concave meshes. The surface is cut in two by the axis-aligned plane that
+    gives the smallest hulls, starting with the part that sinks furthest
+    below its hull, until the options are met. The hulls are named after
+    `self` with a `_hull_` suffix and their index; parts too flat for a
+    hull are left out.
+    */
+    pub fn convex_decomposition(&self, options: &DecompositionOptions) -> Vec<ObjMesh> {
+        let polygons: Vec<Vec<Point>> = self
+            .faces()
+            .iter()
+            .filter_map(|face| {
+                face.elements()
+                    .iter()
+                    .map(|element| {
+                        let index =
+                            self.local_index(VertexAttribute::Position, element.position_index())?;
+                        let p = &self.positions()[index];
+                        Some([p.x as f64, p.y as f64, p.z as f64])
+                    })
+                    .collect::<Option<Vec<Point>>>()
+                    .filter(|polygon| polygon.len() >= 3)
+            })
+            .collect();
+
+        let diagonal = self.bounding_box().map_or(0.0, |bounds| {
+            let size = bounds.size();
+            ((size.x * size.x + size.y * size.y + size.z * size.z) as f64).sqrt()
+        });
+        let max_concavity = options.max_concavity() as f64 * diagonal;
+
+        let mut parts = vec![Part::new(polygons).measured()];
+        while parts.len() < options.max_hulls() {
+            let Some((index, part)) = parts
+                .iter()
+                .enumerate()
+                .filter(|(_, part)| part.concavity > max_concavity)
+                .max_by(|a, b| a.1.concavity.total_cmp(&b.1.concavity))
+            else {
+                break;
+            };
+
+            match part.split() {
+                Some((below, above)) => {
+                    parts[index] = below;
+                    parts.insert(index + 1, above);
+                }
+                None => parts[index].concavity = 0.0,
+            }
+        }
+
+        parts
+            .iter()
+            .filter_map(|part| {
+                let points = part.points();
+                let faces = match options.hull().max_vertices() {
+                    None => Some(part.hull.clone()).filter(|hull| !hull.is_empty()),
+                    max_vertices => quickhull(&points, max_vertices),
+                }?;
+                Some((points, faces))
+            })
+            .enumerate()
+            .map(|(i, (points, faces))| {
+                hull_mesh(&format!("{}_hull_{i}", self.get_name()), &points, &faces)
+            })
+            .collect()
+    }
+}
+
+/// A piece of the surface being decomposed, with its hull, the volume of
+/// the hull, and how far the surface sinks below it once measured
+struct Part {
+    polygons: Vec<Vec<Point>>,
+    hull: Vec<[usize; 3]>,
+    volume: f64,
+    concavity: f64,
+}
+
+impl Part {
+    fn new(polygons: Vec<Vec<Point>>) -> Self {
+        let mut part = Self {
+            polygons,
+            hull: vec![],
+            volume: 0.0,
+            concavity: 0.0,
+        };
+        let points = part.points();
+        if let Some(hull) = quickhull(&points, None) {
+            part.volume = volume(&points, &hull);
+            part.hull = hull;
+        }
+
+        part
+    }
+
+    /// Measures the depth of the corners and centres of the polygons below the nearest hull plane
+    fn measured(mut self) -> Self {
+        let points = self.points();
+        let planes: Vec<(Point, f64)> = self.hull.iter().map(|&f| plane(&points, f)).collect();
+        let centres = self.polygons.iter().map(|polygon| {
+            let sum = polygon.iter().fold([0.0; 3], |sum, &p| add(sum, p));
+            scale(sum, 1.0 / polygon.len() as f64)
+        });
+        self.concavity = match planes.is_empty() {
+            true => 0.0,
+            false => points
+                .iter()
+                .copied()
+                .chain(centres)
+                .map(|p| {
+                    planes
+                        .iter()
+                        .map(|&(normal, offset)| offset - dot(normal, p))
+                        .fold(f64::INFINITY, f64::min)
+                        .max(0.0)
+                })
+                .fold(0.0, f64::max),
+        };
+
+        self
+    }
+
+    fn points(&self) -> Vec<Point> {
+        self.polygons.iter().flatten().copied().collect()
+    }
+
+    /// Cuts `self` by the plane that leaves the least hull volume, if any cut leaves
+    /// two parts with a hull: a flat part would be dropped from the result with its faces
+    fn split(&self) -> Option<(Part, Part)> {
+        let points = self.points();
+        let first = *points.first()?;
+        let (min, max) = points.iter().fold((first, first), |(min, max), p| {
+            (
+                std::array::from_fn(|i| min[i].min(p[i])),
+                std::array::from_fn(|i| max[i].max(p[i])),
+            )
+        });
+
+        let mut best: Option<(f64, Part, Part)> = None;
+        for axis in 0..3 {
+            let extent = max[axis] - min[axis];
+            if extent <= 0.0 {
+                continue;
+            }
+            for k in 1..=SPLIT_PLANES {
+                let value = min[axis] + extent * k as f64 / (SPLIT_PLANES + 1) as f64;
+                let (below, above): (Vec<_>, Vec<_>) = self
+                    .polygons
+                    .iter()
+                    .map(|polygon| clip(polygon, axis, value))
+                    .unzip();
+                let keep = |polygons: Vec<Vec<Point>>| -> Vec<Vec<Point>> {
+                    polygons.into_iter().filter(|p| p.len() >= 3).collect()
+                };
+                let (below, above) = (keep(below), keep(above));
+                if below.is_empty() || above.is_empty() {
+                    continue;
+                }
+
+                let (below, above) = (Part::new(below), Part::new(above));
+                if below.hull.is_empty() || above.hull.is_empty() {
+                    continue;
+                }
+                let cost = below.volume + above.volume;
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_cost, _, _)| cost < *best_cost)
+                {
+                    best = Some((cost, below, above));
+                }
+            }
+        }
+
+        best.map(|(_, below, above)| (below.measured(), above.measured()))
+    }
+}
+
+/// Splits `polygon` by the plane where coordinate `axis` equals `value`, into the parts below and above
+fn clip(polygon: &[Point], axis: usize, value: f64) -> (Vec<Point>, Vec<Point>) {
+    // a polygon lying in the plane belongs to the side behind it, where the solid is
+    if polygon.iter().all(|p| p[axis] == value) {
+        let normal = (0..polygon.len()).fold([0.0; 3], |sum, i| {
+            add(sum, cross(polygon[i], polygon[(i + 1) % polygon.len()]))
+        });
+        return match normal[axis] > 0.0 {
+            true => (polygon.to_vec(), vec![]),
+            false => (vec![], polygon.to_vec()),
+        };
+    }
+
+    let mut below = vec![];
+    let mut above = vec![];
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let (da, db) = (a[axis] - value, b[axis] - value);
+        if da <= 0.0 {
+            below.push(a);
+        }
+        if da >= 0.0 {
+            above.push(a);
+        }
+        if (da < 0.0 && db > 0.0) || (da > 0.0 && db < 0.0) {
+            let t = da / (da - db);
+            let mut crossing = add(a, scale(sub(b, a), t));
+            crossing[axis] = value;
+            below.push(crossing);
+            above.push(crossing);
+        }
+    }
+
+    (below, above)
+}
+
+/// A face of a hull being built, with the points still outside it and their distances
+struct HullFace {
+    corners: [usize; 3],
+    normal: Point,
+    offset: f64,
+    outside: Vec<(usize, f64)>,
+    alive: bool,
+}
+
+/**
+`hull::quickhull`
+---
+Returns the outward facing triangles of the convex hull of `points`, as
+indices into `points`, or `None` when they don't span a volume. Points
+within a tolerance of a face, scaled to the coordinates, count as inside.
+With `max_vertices`, the furthest point of all is added each time, and
+the hull stops growing once it has that many vertices.
+*/
+fn quickhull(points: &[Point], max_vertices: Option<usize>) -> Option<Vec<[usize; 3]>> {
+    let scale_of = points
+        .iter()
+        .map(|p| p.iter().map(|c| c.abs()).sum::<f64>())
+        .fold(0.0, f64::max);
+    let epsilon = scale_of * 1e-6;
+
+    let [a, b, c, d] = initial_simplex(points, epsilon)?;
+    let mut faces: Vec<HullFace> = vec![];
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+
+    // each face of the tetrahedron faces away from its fourth corner
+    for (corners, other) in [
+        ([a, b, c], d),
+        ([a, b, d], c),
+        ([a, c, d], b),
+        ([b, c, d], a),
+    ] {
+        let (normal, offset) = plane(points, corners);
+        let corners = match dot(normal, points[other]) - offset > 0.0 {
+            true => [corners[0], corners[2], corners[1]],
+            false => corners,
+        };
+        add_face(points, &mut faces, &mut edges, corners);
+    }
+    let assign = |faces: &mut Vec<HullFace>, candidates: &[usize], point: usize| {
+        let best = candidates
+            .iter()
+            .map(|&f| (f, dot(faces[f].normal, points[point]) - faces[f].offset))
+            .filter(|&(_, distance)| distance > epsilon)
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+        if let Some((f, distance)) = best {
+            faces[f].outside.push((point, distance));
+        }
+    };
+    for point in 0..points.len() {
+        if ![a, b, c, d].contains(&point) {
+            assign(&mut faces, &[0, 1, 2, 3], point);
+        }
+    }
+
+    let mut vertex_count = 4;
+    let mut pending: Vec<usize> = (0..4).collect();
+    loop {
+        if max_vertices.is_some_and(|max| vertex_count >= max) {
+            break;
+        }
+
+        // the face to grow from, and its furthest point
+        let next = match max_vertices {
+            Some(_) => faces
+                .iter()
+                .enumerate()
+                .filter(|(_, face)| face.alive)
+                .flat_map(|(f, face)| face.outside.iter().map(move |&(p, d)| (f, p, d)))
+                .reduce(|best, next| match next.2 > best.2 {
+                    true => next,
+                    false => best,
+                }),
+            None => {
+                let mut next = None;
+                while let Some(f) = pending.pop() {
+                    let face = &faces[f];
+                    if face.alive && !face.outside.is_empty() {
+                        let furthest = face.outside.iter().copied().reduce(|best, next| {
+                            match next.1 > best.1 {
+                                true => next,
+                                false => best,
+                            }
+                        });
+                        next = furthest.map(|(p, d)| (f, p, d));
+                        pending.push(f);
+                        break;
+                    }
+                }
+                next
+            }
+        };
+        let Some((start, eye, _)) = next else {
+            break;
+        };
+        let eye_point = points[eye];
+
+        // the faces the eye sees, around the one it is outside of
+        let mut visible = vec![start];
+        let mut seen = vec![false; faces.len()];
+        seen[start] = true;
+        let mut cursor = 0;
+        while cursor < visible.len() {
+            let corners = faces[visible[cursor]].corners;
+            cursor += 1;
+            for k in 0..3 {
+                let Some(&neighbour) = edges.get(&(corners[(k + 1) % 3], corners[k])) else {
+                    continue;
+                };
+                let face = &faces[neighbour];
+                if !seen[neighbour] && dot(face.normal, eye_point) - face.offset > epsilon {
+                    seen[neighbour] = true;
+                    visible.push(neighbour);
+                }
+            }
+        }
+
+        let mut horizon = vec![];
+        let mut orphans = vec![];
+        let mut removed: Vec<usize> = vec![];
+        for &f in &visible {
+            let corners = faces[f].corners;
+            for k in 0..3 {
+                let edge = (corners[k], corners[(k + 1) % 3]);
+                let neighbour = edges.get(&(edge.1, edge.0)).copied();
+                if neighbour.is_none_or(|n| !seen[n]) {
+                    horizon.push(edge);
+                }
+            }
+            removed.extend(corners);
+            faces[f].alive = false;
+            orphans.extend(
+                std::mem::take(&mut faces[f].outside)
+                    .into_iter()
+                    .map(|(p, _)| p),
+            );
+        }
+        for &f in &visible {
+            let corners = faces[f].corners;
+            for k in 0..3 {
+                edges.remove(&(corners[k], corners[(k + 1) % 3]));
+            }
+        }
+
+        // corners of the visible faces not on the horizon are now inside
+        removed.sort_unstable();
+        removed.dedup();
+        vertex_count -= removed
+            .iter()
+            .filter(|&&v| !horizon.iter().any(|&(a, _)| a == v))
+            .count();
+        vertex_count += 1;
+
+        let first = faces.len();
+        for (a, b) in horizon {
+            add_face(points, &mut faces, &mut edges, [a, b, eye]);
+        }
+        let created: Vec<usize> = (first..faces.len()).collect();
+        for point in orphans {
+            if point != eye {
+                assign(&mut faces, &created, point);
+            }
+        }
+        pending.extend(created);
+    }
+
+    Some(
+        faces
+            .iter()
+            .filter(|face| face.alive)
+            .map(|face| face.corners)
+            .collect(),
+    )
+}
+
+/// Adds the face with `corners` to `faces`, and its edges to `edges`
+fn add_face(
+    points: &[Point],
+    faces: &mut Vec<HullFace>,
+    edges: &mut HashMap<(usize, usize), usize>,
+    corners: [usize; 3],
+) {
+    let (normal, offset) = plane(points, corners);
+    for k in 0..3 {
+        edges.insert((corners[k], corners[(k + 1) % 3]), faces.len());
+    }
+    faces.push(HullFace {
+        corners,
+        normal,
+        offset,
+        outside: vec![],
+        alive: true,
+    });
+}
+
+/// Picks four points spanning a volume: the two furthest apart of the extremes along each
+/// axis, the furthest from the line through them, and the furthest from their plane
+fn initial_simplex(points: &[Point], epsilon: f64) -> Option<[usize; 4]> {
+    let extremes: Vec<usize> = (0..3)
+        .flat_map(|axis| {
+            let by_axis = |&i: &usize, &j: &usize| points[i][axis].total_cmp(&points[j][axis]);
+            let indices = 0..points.len();
+            [indices.clone().min_by(by_axis), indices.max_by(by_axis)]
+        })
+        .flatten()
+        .collect();
+
+    let mut best = None;
+    let mut best_distance = 0.0;
+    for (k, &i) in extremes.iter().enumerate() {
+        for &j in &extremes[k + 1..] {
+            let distance = length(sub(points[j], points[i]));
+            if distance > best_distance {
+                best = Some((i, j));
+                best_distance = distance;
+            }
+        }
+    }
+    let (a, b) = best.filter(|_| best_distance > epsilon)?;
+
+    let line = sub(points[b], points[a]);
+    let furthest = |distance: &dyn Fn(Point) -> f64| {
+        (0..points.len())
+            .map(|i| (i, distance(points[i])))
+            .max_by(|x, y| x.1.total_cmp(&y.1))
+            .filter(|&(_, d)| d > epsilon)
+            .map(|(i, _)| i)
+    };
+    let c = furthest(&|p| length(cross(line, sub(p, points[a]))) / length(line))?;
+    let (normal, offset) = plane(points, [a, b, c]);
+    let d = furthest(&|p| (dot(normal, p) - offset).abs())?;
+
+    Some([a, b, c, d])
+}
+
+/// Unit normal and offset of the plane through the `corners` of a triangle
+fn plane(points: &[Point], [a, b, c]: [usize; 3]) -> (Point, f64) {
+    let normal = cross(sub(points[b], points[a]), sub(points[c], points[a]));
+    let len = length(normal);
+    let normal = match len > 0.0 {
+        true => scale(normal, 1.0 / len),
+        false => normal,
+    };
+
+    (normal, dot(normal, points[a]))
+}
+
+/// Volume enclosed by the outward facing triangles `faces`
+fn volume(points: &[Point], faces: &[[usize; 3]]) -> f64 {
+    faces
+        .iter()
+        .map(|&[a, b, c]| dot(points[a], cross(points[b], points[c])) / 6.0)
+        .sum()
+}
+
+/// Builds a mesh named `name` from the triangles `faces`, with only the points they use
+fn hull_mesh(name: &str, points: &[Point], faces: &[[usize; 3]]) -> ObjMesh {
+    let mut mesh = ObjMesh::empty();
+    mesh.set_name(name);
+
+    let mut map: Vec<Option<u32>> = vec![None; points.len()];
+    for face in faces {
+        let elements = face
+            .iter()
+            .map(|&i| {
+                let index = *map[i].get_or_insert_with(|| {
+                    let [x, y, z] = points[i].map(|c| c as f32);
+                    mesh.load_position(Vector3::new(x, y, z));
+                    mesh.positions().len() as u32
+                });
+                FaceElement::new(index, 0, 0)
+            })
+            .collect();
+        mesh.load_face(Face::new(elements));
+    }
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh(positions: &[[f32; 3]], faces: &[[u32; 4]]) -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        mesh.set_name("fin");
+        for &[x, y, z] in positions {
+            mesh.load_position(Vector3::new(x, y, z));
+        }
+        for face in faces {
+            mesh.load_face(Face::new(
+                face.iter().map(|&i| FaceElement::new(i, 0, 0)).collect(),
+            ));
+        }
+        mesh
+    }
+
+    #[test]
+    fn keeps_flat_parts_attached_to_a_solid_one() {
+        // a unit cube with a flat fin sticking out of its side up to x = 3
+        let mut positions = vec![];
+        for z in [0.0, 1.0] {
+            for y in [0.0, 1.0] {
+                for x in [0.0, 1.0] {
+                    positions.push([x, y, z]);
+                }
+            }
+        }
+        positions.extend([
+            [1.0, 0.0, 0.5],
+            [3.0, 0.0, 0.5],
+            [3.0, 1.0, 0.5],
+            [1.0, 1.0, 0.5],
+        ]);
+        let faces = [
+            [1, 3, 4, 2],
+            [5, 6, 8, 7],
+            [1, 2, 6, 5],
+            [3, 7, 8, 4],
+            [1, 5, 7, 3],
+            [2, 4, 8, 6],
+            [9, 10, 11, 12],
+        ];
+        let mesh = mesh(&positions, &faces);
+
+        let hulls = mesh.convex_decomposition(&DecompositionOptions::default());
+        assert!(!hulls.is_empty());
+        let reach = hulls
+            .iter()
+            .flat_map(|hull| hull.positions().iter().map(|p| p.x))
+            .fold(f32::MIN, f32::max);
+        assert_eq!(reach, 3.0);
+        for hull in &hulls {
+            let points: Vec<Point> = hull
+                .positions()
+                .iter()
+                .map(|p| [p.x, p.y, p.z].map(f64::from))
+                .collect();
+            let faces: Vec<[usize; 3]> = hull
+                .faces()
+                .iter()
+                .map(|face| [0, 1, 2].map(|k| face.elements()[k].position_index() as usize - 1))
+                .collect();
+            assert!(volume(&points, &faces) > 0.0);
+        }
+    }
+
+    fn hull_points(hull: &ObjMesh) -> Vec<Point> {
+        hull.positions()
+            .iter()
+            .map(|p| [p.x, p.y, p.z].map(f64::from))
+            .collect()
+    }
+
+    /// Checks that every triangle of `hull` faces away from `inside`, and
+    /// has every point of `points` on or behind its plane
+    fn assert_encloses(hull: &ObjMesh, points: &[[f32; 3]], inside: Point) {
+        let corners = hull_points(hull);
+        for face in hull.faces() {
+            assert_eq!(face.elements().len(), 3);
+            let [a, b, c] =
+                [0, 1, 2].map(|k| corners[face.elements()[k].position_index() as usize - 1]);
+            let normal = cross(sub(b, a), sub(c, a));
+            assert!(dot(normal, sub(a, inside)) > 0.0);
+            for &p in points {
+                let p = p.map(f64::from);
+                assert!(dot(normal, sub(p, a)) <= 1e-6 * length(normal));
+            }
+        }
+    }
+
+    #[test]
+    fn cube_hull_ignores_interior_points() {
+        let mut positions = vec![];
+        for k in 0..27 {
+            let [x, y, z] = [k % 3, k / 3 % 3, k / 9].map(|i| 0.25 + 0.25 * i as f32);
+            positions.push([x, y, z]);
+        }
+        for k in 0..8 {
+            positions.push([1, 2, 4].map(|bit| (k & bit != 0) as u32 as f32));
+        }
+        let faces: [[u32; 4]; 0] = [];
+        let hull = mesh(&positions, &faces)
+            .convex_hull(&HullOptions::default())
+            .unwrap();
+
+        assert_eq!(hull.get_name(), "fin_hull");
+        assert_eq!(hull.positions().len(), 8);
+        assert_eq!(hull.faces().len(), 12);
+        for p in hull.positions() {
+            assert!([p.x, p.y, p.z].iter().all(|&c| c == 0.0 || c == 1.0));
+        }
+        assert_encloses(&hull, &positions, [0.5; 3]);
+    }
+
+    #[test]
+    fn max_vertices_caps_the_hull() {
+        // points spread evenly over a sphere, all of them on the full hull
+        let count = 200;
+        let positions: Vec<[f32; 3]> = (0..count)
+            .map(|k| {
+                let y = 1.0 - 2.0 * (k as f32 + 0.5) / count as f32;
+                let radius = (1.0 - y * y).sqrt();
+                let angle = k as f32 * 2.399_963;
+                [radius * angle.cos(), y, radius * angle.sin()]
+            })
+            .collect();
+        let faces: [[u32; 4]; 0] = [];
+        let sphere = mesh(&positions, &faces);
+
+        let full = sphere.convex_hull(&HullOptions::default()).unwrap();
+        assert_eq!(full.positions().len(), count);
+
+        for limit in [3, 12, 40] {
+            let mut options = HullOptions::default();
+            options.set_max_vertices(Some(limit));
+            let hull = sphere.convex_hull(&options).unwrap();
+            let vertices = hull.positions().len();
+            assert!(
+                (4..=limit.max(4)).contains(&vertices),
+                "{vertices} vertices"
+            );
+            assert_eq!(hull.faces().len(), 2 * vertices - 4);
+            assert_encloses(&hull, &[], [0.0; 3]);
+        }
+    }
+
+    #[test]
+    fn coplanar_points_have_no_hull() {
+        let positions: Vec<[f32; 3]> = (0..16)
+            .map(|k| {
+                let (x, y) = ((k % 4) as f32, (k / 4) as f32);
+                [x, y, 0.5 * x - y]
+            })
+            .collect();
+        let faces: [[u32; 4]; 0] = [];
+        assert!(mesh(&positions, &faces)
+            .convex_hull(&HullOptions::default())
+            .is_none());
+        assert!(ObjMesh::empty()
+            .convex_hull(&HullOptions::default())
+            .is_none());
+    }
+}
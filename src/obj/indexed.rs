@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use suoi_types::{Vector2, Vector3};
+
+use crate::obj_mesh::ObjMesh;
+use crate::{MaterialHandle, VertexAttribute};
+
+/**
+`obj::IndexedPart`
+---
+A range of `IndexedMesh.indices` drawn with a single material
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedPart {
+    material: Option<MaterialHandle>,
+    first_index: usize,
+    index_count: usize,
+}
+
+impl IndexedPart {
+    /// Immutable getter for `material` of `IndexedPart`
+    pub fn material(&self) -> Option<MaterialHandle> {
+        self.material
+    }
+
+    /// Immutable getter for `first_index` of `IndexedPart`
+    pub fn first_index(&self) -> usize {
+        self.first_index
+    }
+
+    /// Immutable getter for `index_count` of `IndexedPart`
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+}
+
+/**
+`obj::IndexedMesh`
+---
+A triangle list ready for the GPU: one vertex per distinct combination of
+position, uv and normal, and three indices per triangle, split into parts
+by material. `uvs` and `normals` are empty when the mesh has none, and
+filled with zeroes for the corners that lack them otherwise.
+*/
+#[derive(Debug, Clone)]
+pub struct IndexedMesh {
+    positions: Vec<Vector3>,
+    uvs: Vec<Vector2>,
+    normals: Vec<Vector3>,
+    indices: Vec<u32>,
+    parts: Vec<IndexedPart>,
+}
+
+impl IndexedMesh {
+    /// Immutable getter for `positions` of `IndexedMesh`
+    pub fn positions(&self) -> &Vec<Vector3> {
+        &self.positions
+    }
+
+    /// Immutable getter for `uvs` of `IndexedMesh`
+    pub fn uvs(&self) -> &Vec<Vector2> {
+        &self.uvs
+    }
+
+    /// Immutable getter for `normals` of `IndexedMesh`
+    pub fn normals(&self) -> &Vec<Vector3> {
+        &self.normals
+    }
+
+    /// Immutable getter for `indices` of `IndexedMesh`
+    pub fn indices(&self) -> &Vec<u32> {
+        &self.indices
+    }
+
+    /// Immutable getter for `parts` of `IndexedMesh`
+    pub fn parts(&self) -> &Vec<IndexedPart> {
+        &self.parts
+    }
+
+    /// Number of vertices in `self`
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Mutable getter for the indices of each part, in order
+    pub(crate) fn part_indices_mut(&mut self) -> Vec<&mut [u32]> {
+        let mut parts = vec![];
+        let mut rest = &mut self.indices[..];
+        for part in &self.parts {
+            let (indices, tail) = rest.split_at_mut(part.index_count);
+            parts.push(indices);
+            rest = tail;
+        }
+
+        parts
+    }
+
+    /**
+    `IndexedMesh::vertex_triangles`
+    ---
+    Lists the triangles using each of `vertex_count` vertices in a triangle
+    list: those of vertex `v` are `vertex_triangles[offsets[v]..offsets[v + 1]]`,
+    returned as `(offsets, vertex_triangles)`
+    */
+    pub(crate) fn vertex_triangles(
+        indices: &[u32],
+        vertex_count: usize,
+    ) -> (Vec<usize>, Vec<usize>) {
+        let mut offsets = vec![0usize; vertex_count + 1];
+        for &index in indices {
+            offsets[index as usize + 1] += 1;
+        }
+        for i in 0..vertex_count {
+            offsets[i + 1] += offsets[i];
+        }
+
+        let mut vertex_triangles = vec![0usize; indices.len()];
+        let mut fill = offsets.clone();
+        for (corner, &index) in indices.iter().enumerate() {
+            vertex_triangles[fill[index as usize]] = corner / 3;
+            fill[index as usize] += 1;
+        }
+
+        (offsets, vertex_triangles)
+    }
+
+    /**
+    `IndexedMesh.reorder_vertices`
+    ---
+    Moves vertex `i` to index `map[i]`, dropping the vertices mapped to
+    `None`, and rewrites the indices. Every vertex in use must be mapped,
+    and the new indices must cover `0..n` without gaps.
+    */
+    pub(crate) fn reorder_vertices(&mut self, map: &[Option<u32>]) {
+        fn reorder<T: Clone>(data: &[T], map: &[Option<u32>], count: usize) -> Vec<T> {
+            let mut reordered: Vec<Option<T>> = vec![None; count];
+            for (value, target) in data.iter().zip(map) {
+                if let Some(target) = target {
+                    reordered[*target as usize] = Some(value.clone());
+                }
+            }
+            reordered.into_iter().flatten().collect()
+        }
+
+        let count = map.iter().flatten().count();
+        self.positions = reorder(&self.positions, map, count);
+        self.uvs = reorder(&self.uvs, map, count);
+        self.normals = reorder(&self.normals, map, count);
+        for index in &mut self.indices {
+            *index = map[*index as usize].unwrap_or(0);
+        }
+    }
+}
+
+impl ObjMesh {
+    /**
+    `ObjMesh.to_indexed`
+    ---
+    Builds an `IndexedMesh` from the faces of `self`, splitting faces with
+    more than three corners into triangle fans. Faces with out-of-range
+    positions are skipped, and out-of-range uvs and normals are left out
+    of their corners. Parts follow the material spans of the faces.
+    */
+    pub fn to_indexed(&self) -> IndexedMesh {
+        let local = |attribute, index| self.local_index(attribute, index);
+        let mut vertex_ids: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+        let mut corners = vec![];
+        let mut indices = vec![];
+        let mut parts: Vec<IndexedPart> = vec![];
+        let mut has_uvs = false;
+        let mut has_normals = false;
+
+        for (index, face) in self.faces().iter().enumerate() {
+            let elements: Option<Vec<u32>> = face
+                .elements()
+                .iter()
+                .map(|element| {
+                    let key = (
+                        local(VertexAttribute::Position, element.position_index())?,
+                        local(VertexAttribute::Uv, element.uv_index()),
+                        local(VertexAttribute::Normal, element.normal_index()),
+                    );
+                    has_uvs |= key.1.is_some();
+                    has_normals |= key.2.is_some();
+
+                    Some(*vertex_ids.entry(key).or_insert_with(|| {
+                        corners.push(key);
+                        corners.len() as u32 - 1
+                    }))
+                })
+                .collect();
+            let Some(elements) = elements.filter(|elements| elements.len() >= 3) else {
+                continue;
+            };
+
+            let material = self.face_material(index);
+            if parts.last().is_none_or(|part| part.material != material) {
+                parts.push(IndexedPart {
+                    material,
+                    first_index: indices.len(),
+                    index_count: 0,
+                });
+            }
+            for pair in elements[1..].windows(2) {
+                indices.extend([elements[0], pair[0], pair[1]]);
+            }
+            if let Some(part) = parts.last_mut() {
+                part.index_count = indices.len() - part.first_index;
+            }
+        }
+
+        let mut mesh = IndexedMesh {
+            positions: vec![],
+            uvs: vec![],
+            normals: vec![],
+            indices,
+            parts,
+        };
+        for (position, uv, normal) in corners {
+            mesh.positions.push(self.positions()[position].clone());
+            if has_uvs {
+                mesh.uvs
+                    .push(uv.map_or(Vector2 { x: 0.0, y: 0.0 }, |i| self.uvs()[i].clone()));
+            }
+            if has_normals {
+                mesh.normals.push(
+                    normal.map_or(Vector3::new(0.0, 0.0, 0.0), |i| self.normals()[i].clone()),
+                );
+            }
+        }
+
+        mesh
+    }
+}
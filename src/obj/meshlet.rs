@@ -0,0 +1,486 @@
+use suoi_types::Vector3;
+
+use crate::bounds::BoundingSphere;
+use crate::indexed::IndexedMesh;
+use crate::math::{self, Vec3};
+
+/**
+`obj::MeshletOptions`
+---
+Size limits of the meshlets built by `IndexedMesh.build_meshlets`.
+`max_vertices` is kept within `3..=256`, so that local indices fit
+in a byte, and `max_triangles` is at least `1`. The defaults of 64
+vertices and 124 triangles suit most mesh shader pipelines.
+*/
+#[derive(Debug, Clone)]
+pub struct MeshletOptions {
+    max_vertices: usize,
+    max_triangles: usize,
+}
+
+impl Default for MeshletOptions {
+    fn default() -> Self {
+        Self {
+            max_vertices: 64,
+            max_triangles: 124,
+        }
+    }
+}
+
+impl MeshletOptions {
+    /// Immutable getter for `max_vertices` of `MeshletOptions`
+    pub fn max_vertices(&self) -> usize {
+        self.max_vertices
+    }
+
+    /// Setter for `max_vertices` of `MeshletOptions`
+    pub fn set_max_vertices(&mut self, value: usize) {
+        self.max_vertices = value.clamp(3, 256);
+    }
+
+    /// Immutable getter for `max_triangles` of `MeshletOptions`
+    pub fn max_triangles(&self) -> usize {
+        self.max_triangles
+    }
+
+    /// Setter for `max_triangles` of `MeshletOptions`
+    pub fn set_max_triangles(&mut self, value: usize) {
+        self.max_triangles = value.max(1);
+    }
+}
+
+/**
+`obj::NormalCone`
+---
+A cone bounding the normals of a meshlet's triangles. The meshlet faces
+away from a camera, and can be culled, when
+`dot(normalize(apex - camera_position), axis) >= cutoff`; a `cutoff` of
+`1` means the normals spread too far for the test to ever pass.
+*/
+#[derive(Debug, Clone)]
+pub struct NormalCone {
+    apex: Vector3,
+    axis: Vector3,
+    cutoff: f32,
+}
+
+impl NormalCone {
+    /// Immutable getter for `apex` of `NormalCone`
+    pub fn apex(&self) -> &Vector3 {
+        &self.apex
+    }
+
+    /// Immutable getter for `axis` of `NormalCone`
+    pub fn axis(&self) -> &Vector3 {
+        &self.axis
+    }
+
+    /// Immutable getter for `cutoff` of `NormalCone`
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+}
+
+/**
+`obj::Meshlet`
+---
+A small cluster of triangles of one part of an `IndexedMesh`: `vertices`
+lists the mesh vertices it uses, and `triangles` indexes into that list
+*/
+#[derive(Debug, Clone)]
+pub struct Meshlet {
+    part: usize,
+    vertices: Vec<u32>,
+    triangles: Vec<[u8; 3]>,
+    bounds: BoundingSphere,
+    cone: NormalCone,
+}
+
+impl Meshlet {
+    /// Immutable getter for `part` of `Meshlet`, an index into `IndexedMesh.parts`
+    pub fn part(&self) -> usize {
+        self.part
+    }
+
+    /// Immutable getter for `vertices` of `Meshlet`
+    pub fn vertices(&self) -> &Vec<u32> {
+        &self.vertices
+    }
+
+    /// Immutable getter for `triangles` of `Meshlet`
+    pub fn triangles(&self) -> &Vec<[u8; 3]> {
+        &self.triangles
+    }
+
+    /// Immutable getter for `bounds` of `Meshlet`
+    pub fn bounds(&self) -> &BoundingSphere {
+        &self.bounds
+    }
+
+    /// Immutable getter for `cone` of `Meshlet`
+    pub fn cone(&self) -> &NormalCone {
+        &self.cone
+    }
+}
+
+impl IndexedMesh {
+    /**
+    `IndexedMesh.build_meshlets`
+    ---
+    Splits every part of `self` into meshlets within the limits of `options`.
+    Each meshlet grows from a seed triangle by adding the neighbouring
+    triangle that brings in the fewest new vertices, then the one nearest
+    its centre and closest in orientation, then the first in index order;
+    the next seed is the first free triangle next to the last meshlet.
+    Only index order breaks ties, so the same mesh always gives the same
+    meshlets.
+    */
+    pub fn build_meshlets(&self, options: &MeshletOptions) -> Vec<Meshlet> {
+        let positions: Vec<Vec3> = self.positions().iter().map(math::from_vector).collect();
+        let max_vertices = options.max_vertices().clamp(3, 256);
+        let max_triangles = options.max_triangles().max(1);
+        let mut meshlets = vec![];
+
+        for (part, range) in self.parts().iter().enumerate() {
+            let indices = &self.indices()[range.first_index()..][..range.index_count()];
+            let mut builder = MeshletBuilder::new(indices, &positions);
+
+            let mut seed = 0;
+            while let Some(start) = builder.next_seed(&mut seed) {
+                let (vertices, triangles) = builder.grow(start, max_vertices, max_triangles);
+                meshlets.push(builder.meshlet(part, vertices, triangles));
+            }
+        }
+
+        meshlets
+    }
+}
+
+/// Triangle adjacency of one part, and the triangles already placed in a meshlet
+struct MeshletBuilder<'a> {
+    indices: &'a [u32],
+    positions: &'a [Vec3],
+    normals: Vec<Option<Vec3>>,
+
+    // triangles of each vertex, as ranges of `vertex_triangles`
+    offsets: Vec<usize>,
+    vertex_triangles: Vec<usize>,
+    used: Vec<bool>,
+
+    // free triangles next to the last meshlet, by index
+    frontier: Vec<usize>,
+}
+
+impl<'a> MeshletBuilder<'a> {
+    fn new(indices: &'a [u32], positions: &'a [Vec3]) -> Self {
+        let triangles = indices.len() / 3;
+        let (offsets, vertex_triangles) = IndexedMesh::vertex_triangles(indices, positions.len());
+
+        let normals = (0..triangles)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|k| positions[indices[t * 3 + k] as usize]);
+                math::normalize(math::cross(math::sub(b, a), math::sub(c, a)))
+            })
+            .collect();
+
+        Self {
+            indices,
+            positions,
+            normals,
+            offsets,
+            vertex_triangles,
+            used: vec![false; triangles],
+            frontier: vec![],
+        }
+    }
+
+    fn corners(&self, t: usize) -> &'a [u32] {
+        &self.indices[t * 3..t * 3 + 3]
+    }
+
+    fn triangles_of(&self, vertex: u32) -> &[usize] {
+        let v = vertex as usize;
+        &self.vertex_triangles[self.offsets[v]..self.offsets[v + 1]]
+    }
+
+    /// Picks the seed of the next meshlet, `None` once every triangle is placed
+    fn next_seed(&mut self, cursor: &mut usize) -> Option<usize> {
+        if let Some(&t) = self.frontier.iter().find(|&&t| !self.used[t]) {
+            return Some(t);
+        }
+        while *cursor < self.used.len() && self.used[*cursor] {
+            *cursor += 1;
+        }
+        (*cursor < self.used.len()).then_some(*cursor)
+    }
+
+    /// Grows a meshlet from the triangle `start`, returning its vertices and triangles
+    fn grow(
+        &mut self,
+        start: usize,
+        max_vertices: usize,
+        max_triangles: usize,
+    ) -> (Vec<u32>, Vec<usize>) {
+        let mut vertices: Vec<u32> = vec![];
+        let mut triangles = vec![];
+        let mut candidates = vec![start];
+        let mut normal = [0.0; 3];
+        let mut centroid = [0.0; 3];
+
+        while triangles.len() < max_triangles {
+            // fewest new vertices first, then the nearest triangle, as it keeps the
+            // meshlet round, favouring those that face the same way as the rest
+            let center = match vertices.is_empty() {
+                true => centroid,
+                false => math::scale(centroid, 1.0 / vertices.len() as f32),
+            };
+            let mut best: Option<(usize, usize, f32)> = None;
+            for &t in &candidates {
+                if self.used[t] {
+                    continue;
+                }
+                let corners = self.corners(t);
+                let new = corners.iter().filter(|v| !vertices.contains(v)).count();
+                if vertices.len() + new > max_vertices {
+                    continue;
+                }
+                let facing = match (math::normalize(normal), self.normals[t]) {
+                    (Some(axis), Some(n)) => math::dot(axis, n),
+                    _ => 0.0,
+                };
+                let middle = corners.iter().fold([0.0; 3], |sum, &v| {
+                    math::add(sum, math::scale(self.positions[v as usize], 1.0 / 3.0))
+                });
+                let score = math::length(math::sub(middle, center)) * (2.0 - facing);
+                let better = best.is_none_or(|(_, best_new, best_score)| {
+                    new < best_new || (new == best_new && score < best_score)
+                });
+                if better {
+                    best = Some((t, new, score));
+                }
+            }
+            let Some((t, _, _)) = best else {
+                break;
+            };
+
+            self.used[t] = true;
+            triangles.push(t);
+            if let Some(n) = self.normals[t] {
+                normal = math::add(normal, n);
+            }
+            for &v in self.corners(t) {
+                if !vertices.contains(&v) {
+                    vertices.push(v);
+                    centroid = math::add(centroid, self.positions[v as usize]);
+                    let free = self.triangles_of(v).iter().filter(|&&t| !self.used[t]);
+                    candidates.extend(free);
+                }
+            }
+            candidates.retain(|&t| !self.used[t]);
+            candidates.sort_unstable();
+            candidates.dedup();
+        }
+
+        self.frontier = candidates;
+        (vertices, triangles)
+    }
+
+    /// Builds the `Meshlet` of `triangles`, with its bounds and normal cone
+    fn meshlet(&self, part: usize, vertices: Vec<u32>, triangles: Vec<usize>) -> Meshlet {
+        let points: Vec<Vec3> = vertices
+            .iter()
+            .map(|&v| self.positions[v as usize])
+            .collect();
+        let bounds = BoundingSphere::from_points(&points);
+        let center = math::from_vector(bounds.center());
+
+        let normals: Vec<(usize, Vec3)> = triangles
+            .iter()
+            .filter_map(|&t| Some((t, self.normals[t]?)))
+            .collect();
+        let sum = normals
+            .iter()
+            .fold([0.0; 3], |sum, &(_, n)| math::add(sum, n));
+        let axis = math::normalize(sum).unwrap_or([0.0, 0.0, 0.0]);
+        let spread = normals
+            .iter()
+            .map(|&(_, n)| math::dot(axis, n))
+            .fold(1.0, f32::min);
+
+        let cone = match spread > 0.0 && !normals.is_empty() {
+            true => {
+                // move the apex back until every triangle's plane passes in front of it
+                let behind = normals
+                    .iter()
+                    .map(|&(t, n)| {
+                        let corner = self.positions[self.corners(t)[0] as usize];
+                        math::dot(math::sub(center, corner), n) / math::dot(axis, n)
+                    })
+                    .fold(0.0, f32::max);
+                NormalCone {
+                    apex: math::to_vector(math::sub(center, math::scale(axis, behind))),
+                    axis: math::to_vector(axis),
+                    cutoff: (1.0 - spread * spread).sqrt(),
+                }
+            }
+            false => NormalCone {
+                apex: math::to_vector(center),
+                axis: math::to_vector(axis),
+                cutoff: 1.0,
+            },
+        };
+
+        let local = |v: &u32| vertices.iter().position(|w| w == v).unwrap_or(0) as u8;
+        Meshlet {
+            part,
+            triangles: triangles
+                .iter()
+                .map(|&t| [0, 1, 2].map(|k| local(&self.corners(t)[k])))
+                .collect(),
+            vertices,
+            bounds,
+            cone,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::{Face, FaceElement};
+    use crate::obj_mesh::ObjMesh;
+    use crate::{Mtl, MtlMaterial};
+
+    /// A gently curved grid of `n` by `n` quads facing `+z`, the left half in another material
+    fn grid(n: u32) -> IndexedMesh {
+        let mut mtl = Mtl::empty();
+        let materials = [
+            mtl.add_material(MtlMaterial::empty()),
+            mtl.add_material(MtlMaterial::empty()),
+        ];
+
+        let mut mesh = ObjMesh::empty();
+        for j in 0..=n {
+            for i in 0..=n {
+                let (x, y) = (i as f32 / n as f32 - 0.5, j as f32 / n as f32 - 0.5);
+                mesh.load_position(Vector3::new(x, y, -0.2 * (x * x + y * y)));
+            }
+        }
+        for (half, material) in materials.into_iter().enumerate() {
+            mesh.set_material(material);
+            for j in 0..n {
+                for i in (0..n).filter(|&i| (i < n / 2) == (half == 0)) {
+                    let corner = |i: u32, j: u32| FaceElement::new(j * (n + 1) + i + 1, 0, 0);
+                    mesh.load_face(Face::new(vec![
+                        corner(i, j),
+                        corner(i + 1, j),
+                        corner(i + 1, j + 1),
+                        corner(i, j + 1),
+                    ]));
+                }
+            }
+        }
+        mesh.to_indexed()
+    }
+
+    /// Each triangle as its part and mesh vertices, rotated to start at the lowest
+    fn rotated(part: usize, mut triangle: [u32; 3]) -> (usize, [u32; 3]) {
+        let lowest = (0..3).min_by_key(|&k| triangle[k]).unwrap_or(0);
+        triangle.rotate_left(lowest);
+        (part, triangle)
+    }
+
+    fn is_culled(cone: &NormalCone, camera: [f32; 3]) -> bool {
+        let apex = math::from_vector(cone.apex());
+        let view = math::normalize(math::sub(apex, camera)).unwrap_or([0.0; 3]);
+        math::dot(view, math::from_vector(cone.axis())) >= cone.cutoff()
+    }
+
+    #[test]
+    fn meshlets_stay_within_limits() {
+        let mesh = grid(12);
+        let mut options = MeshletOptions::default();
+        options.set_max_vertices(16);
+        options.set_max_triangles(10);
+
+        let meshlets = mesh.build_meshlets(&options);
+        assert!(meshlets.len() >= 12 * 12 * 2 / 10);
+        for meshlet in &meshlets {
+            let vertices = meshlet.vertices();
+            assert!(vertices.len() <= 16 && !meshlet.triangles().is_empty());
+            assert!(meshlet.triangles().len() <= 10);
+
+            let mut unique = vertices.clone();
+            unique.sort_unstable();
+            unique.dedup();
+            assert_eq!(unique.len(), vertices.len());
+            assert!(meshlet
+                .triangles()
+                .iter()
+                .flatten()
+                .all(|&local| (local as usize) < vertices.len()));
+        }
+    }
+
+    #[test]
+    fn every_triangle_lands_in_one_meshlet() {
+        let mesh = grid(10);
+        let meshlets = mesh.build_meshlets(&MeshletOptions::default());
+
+        let mut expected: Vec<(usize, [u32; 3])> = vec![];
+        for (part, range) in mesh.parts().iter().enumerate() {
+            let indices = &mesh.indices()[range.first_index()..][..range.index_count()];
+            for triangle in indices.chunks(3) {
+                expected.push(rotated(part, [triangle[0], triangle[1], triangle[2]]));
+            }
+        }
+        let mut found: Vec<(usize, [u32; 3])> = meshlets
+            .iter()
+            .flat_map(|meshlet| {
+                meshlet.triangles().iter().map(|triangle| {
+                    let corners = triangle.map(|local| meshlet.vertices()[local as usize]);
+                    rotated(meshlet.part(), corners)
+                })
+            })
+            .collect();
+
+        expected.sort_unstable();
+        found.sort_unstable();
+        assert_eq!(mesh.parts().len(), 2);
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn meshlets_are_deterministic() {
+        let mesh = grid(10);
+        let mut options = MeshletOptions::default();
+        options.set_max_vertices(20);
+        options.set_max_triangles(16);
+
+        let summary = |meshlets: Vec<Meshlet>| -> Vec<(usize, Vec<u32>, Vec<[u8; 3]>)> {
+            meshlets
+                .into_iter()
+                .map(|m| (m.part(), m.vertices().clone(), m.triangles().clone()))
+                .collect()
+        };
+        let first = summary(mesh.build_meshlets(&options));
+        assert_eq!(first, summary(mesh.build_meshlets(&options)));
+        assert_eq!(first, summary(grid(10).build_meshlets(&options)));
+    }
+
+    #[test]
+    fn cone_culls_only_back_facing_views() {
+        let mesh = grid(4);
+        let meshlets = mesh.build_meshlets(&MeshletOptions::default());
+        assert_eq!(meshlets.len(), 2);
+
+        for meshlet in &meshlets {
+            let cone = meshlet.cone();
+            assert!(cone.cutoff() < 1.0);
+            assert!(is_culled(cone, [0.0, 0.0, -10.0]));
+            assert!(!is_culled(cone, [0.0, 0.0, 10.0]));
+            // every triangle still faces a camera just above the surface
+            assert!(!is_culled(cone, [0.0, 0.0, 0.5]));
+        }
+    }
+}
@@ -1,5 +1,49 @@
+pub mod combine;
 pub mod face;
+pub mod freeform;
 pub mod obj_mesh;
+pub(crate) mod obj_parser;
+pub mod obj_writer;
+pub mod tessellate;
 
 pub mod obj;
 pub use obj::*;
+
+pub mod obj_options;
+pub use obj_options::*;
+
+pub mod obj_reader;
+pub use obj_reader::*;
+
+pub mod validate;
+pub use validate::*;
+
+pub mod weld;
+pub use weld::*;
+
+pub mod simplify;
+pub use simplify::*;
+
+pub mod indexed;
+pub use indexed::*;
+
+pub mod optimize;
+pub use optimize::*;
+
+pub mod meshlet;
+pub use meshlet::*;
+
+pub mod bounds;
+pub use bounds::*;
+
+pub mod coordinates;
+pub use coordinates::*;
+
+pub mod transform;
+pub use transform::*;
+
+pub mod bvh;
+pub use bvh::*;
+
+pub mod hull;
+pub use hull::*;
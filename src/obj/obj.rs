@@ -1,19 +1,41 @@
-use suoi_types::{Vector2, Vector3};
+use std::io::BufReader;
 
-use crate::face::Face;
-use crate::face::FaceElement;
+use crate::bounds::BoundsCache;
 use crate::fs::Path;
 use crate::obj_mesh::ObjMesh;
+use crate::obj_parser::ObjBuilder;
+use crate::AxisConversion;
 use crate::Fs;
 use crate::ImportError;
+use crate::ImportWarning;
+use crate::MaterialHandle;
 use crate::Mtl;
+use crate::MtlMaterial;
+use crate::ObjImportOptions;
+use crate::ObjReader;
 use crate::PathBuf;
 use crate::Resource;
+use crate::Tokens;
+
+/// Size of the read buffer, which most statements are parsed in place in
+const READ_BUFFER_SIZE: usize = 1 << 16;
 
 #[derive(Debug)]
 pub struct Obj {
     path: PathBuf,
     meshes: Vec<ObjMesh>,
+
+    // token: mtllib
+    material_libraries: Vec<String>,
+
+    // materials of every library, referenced by the meshes
+    mtl: Mtl,
+
+    // problems recovered from during import
+    warnings: Vec<ImportWarning>,
+
+    // bounds of every mesh, cleared when they may change
+    bounds: BoundsCache,
 }
 
 impl Obj {
@@ -21,9 +43,106 @@ impl Obj {
         &self.meshes
     }
     
+    /// Mutable getter for the meshes of `Obj`, to weld or repair them in place
+    pub fn meshes_mut(&mut self) -> &mut Vec<ObjMesh> {
+        self.bounds.invalidate();
+        &mut self.meshes
+    }
+
+    /// Immutable getter for `bounds` of `Obj`
+    pub(crate) fn bounds_cache(&self) -> &BoundsCache {
+        &self.bounds
+    }
+
     pub fn path(&self) -> &PathBuf {
         &self.path
     }
+
+    /// Material library file names, as written in the `mtllib` statements
+    pub fn material_libraries(&self) -> &Vec<String> {
+        &self.material_libraries
+    }
+
+    /**
+    `Obj.materials`
+    ---
+    Material table shared by every mesh: the merged
+    `mtllib` libraries, plus a copy of the default material
+    for each unknown name the import had to fall back on
+    */
+    pub fn materials(&self) -> &Mtl {
+        &self.mtl
+    }
+
+    /// Mutable getter for the material table of `Obj`
+    pub fn materials_mut(&mut self) -> &mut Mtl {
+        &mut self.mtl
+    }
+
+    /// Returns the material referenced by `handle`
+    pub fn material(&self, handle: MaterialHandle) -> Option<&MtlMaterial> {
+        self.mtl.get(handle)
+    }
+
+    /**
+    `Obj.material_mut`
+    ---
+    Returns the material referenced by `handle` for editing.
+    Every mesh using `handle` sees the change.
+    */
+    pub fn material_mut(&mut self, handle: MaterialHandle) -> Option<&mut MtlMaterial> {
+        self.mtl.get_mut(handle)
+    }
+
+    /// Problems recovered from while importing, such as missing materials,
+    /// including those of the material libraries it loaded
+    pub fn warnings(&self) -> &Vec<ImportWarning> {
+        &self.warnings
+    }
+}
+
+impl Obj {
+    pub(crate) fn from_parts(
+        path: PathBuf,
+        meshes: Vec<ObjMesh>,
+        material_libraries: Vec<String>,
+        mtl: Mtl,
+        warnings: Vec<ImportWarning>,
+    ) -> Self {
+        Self {
+            path,
+            meshes,
+            material_libraries,
+            mtl,
+            warnings,
+            bounds: BoundsCache::default(),
+        }
+    }
+
+    /**
+    `Obj::split_libraries`
+    ---
+    Splits the arguments of an `mtllib` statement into file names.
+    The statement may list several whitespace separated libraries,
+    but older files also write a single name containing spaces:
+    if the whole remainder names an existing file in `dir`, it is
+    taken as one library, otherwise it is split on whitespace.
+    */
+    pub(crate) fn split_libraries<'a>(
+        dir: &Path,
+        tokens: &mut Tokens<'a>,
+    ) -> Result<Vec<&'a str>, ImportError> {
+        let remainder = tokens.remainder().ok_or(ImportError::InvalidData)?.trim();
+        if remainder.is_empty() {
+            return Err(ImportError::InvalidData);
+        }
+
+        if dir.join(remainder).is_file() {
+            return Ok(vec![remainder]);
+        }
+
+        Ok(remainder.split_ascii_whitespace().collect())
+    }
 }
 
 impl Resource for Obj {
@@ -35,101 +154,55 @@ impl Resource for Obj {
     said file, wrapped in a `Result`.
     */
     fn import(path: &Path) -> Result<Self, ImportError> {
+        Self::import_with_options(path, &ObjImportOptions::default())
+    }
+}
+
+impl Obj {
+    /**
+    `Obj::import_with_options`
+    ---
+    Import the `.obj` file located at `path` using the
+    supplied `options`. Missing material libraries and
+    unknown materials fall back to the default material
+    of `options`, keeping their name, and are reported through `Obj.warnings`,
+    unless `options` asks for strict materials.
+    */
+    pub fn import_with_options(
+        path: &Path,
+        options: &ObjImportOptions,
+    ) -> Result<Self, ImportError> {
         let mut file = Fs::open_file(path)?;
-        let text = Fs::read_file(&mut file)?;
-
-        let mut meshes: Vec<ObjMesh> = vec![];
-        let mut mtl = Mtl::empty();
-
-        Fs::parse_lines(text, |mut tokens, cmd| {
-            match cmd {
-                "mtllib" => {
-                    // material library
-                    let mtl_file = tokens.remainder().ok_or(ImportError::InvalidData)?;
-                    let mut mtl_path = path
-                        .parent()
-                        .ok_or(ImportError::InvalidPath(path.to_owned()))?
-                        .to_owned();
-                    mtl_path.push(mtl_file);
-
-                    mtl = Mtl::import(&mtl_path)?;
-                }
-                "usemtl" => {
-                    // use material
-                    let mat_name = tokens.remainder().ok_or(ImportError::InvalidData)?;
-                    let new_mat = mtl.get_material(mat_name).ok_or(ImportError::InvalidData)?;
-
-                    meshes
-                        .last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .set_material(new_mat.clone());
-                }
-                "o" => {
-                    // object name
-                    let mut new_mesh = ObjMesh::empty();
-                    new_mesh.set_name(tokens.remainder().ok_or(ImportError::InvalidData)?);
+        let threads = match options.threads() {
+            0 => std::thread::available_parallelism().map_or(1, |count| count.get()),
+            threads => threads,
+        };
 
-                    meshes.push(new_mesh);
-                }
-                "v" => {
-                    // vertex definition
-                    let x = Fs::parse_float(&mut tokens)?;
-                    let y = Fs::parse_float(&mut tokens)?;
-                    let z = Fs::parse_float(&mut tokens)?;
-
-                    let vertex = Vector3::new(x, y, z);
-                    meshes
-                        .last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .load_position(vertex);
-                }
-                "vn" => {
-                    // vertex normal
-                    let x = Fs::parse_float(&mut tokens)?;
-                    let y = Fs::parse_float(&mut tokens)?;
-                    let z = Fs::parse_float(&mut tokens)?;
-
-                    let normal = Vector3::new(x, y, z);
-                    meshes
-                        .last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .load_normal(normal);
-                }
-                "vt" => {
-                    // vertex texture (UV)
-                    let x = Fs::parse_float(&mut tokens)?;
-                    let y = Fs::parse_float(&mut tokens)?;
-
-                    let uv = Vector2 { x, y };
-                    meshes
-                        .last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .load_uv(uv);
-                }
-                "s" => {
-                    // surface roughness
-                }
-                "#" => {
-                    // comment
-                }
-                "f" => {
-                    // face data
-                    let face_elements = tokens
-                        .into_iter()
-                        .map(|token| FaceElement::parse(token))
-                        .collect();
-                    let face: Face = Face::new(face_elements);
-                    meshes
-                        .last_mut()
-                        .ok_or(ImportError::InvalidData)?
-                        .load_face(face);
+        let mut builder = ObjBuilder::new(path, options);
+        if threads > 1 || options.memory_map() {
+            let bytes = Fs::load_bytes(&mut file, options.memory_map())?;
+            match threads {
+                1 => builder.read_all(ObjReader::new(&bytes[..]))?,
+                _ => builder.apply_parallel(&bytes, threads)?,
+            }
+        } else {
+            builder.read_all(ObjReader::new(BufReader::with_capacity(
+                READ_BUFFER_SIZE,
+                file,
+            )))?;
+        }
+
+        let mut obj = builder.finish();
+        let conversion = AxisConversion::between(options.source_system(), options.target_system());
+        if !conversion.is_identity() || options.flip_v() {
+            for mesh in obj.meshes_mut() {
+                mesh.convert_coordinates(&conversion);
+                if options.flip_v() {
+                    mesh.flip_v();
                 }
-                "" => (),
-                _ => return Err(ImportError::UnrecognisedToken(cmd.to_owned())),
             }
-            Ok(())
-        })?;
+        }
 
-        Ok(Self { meshes, path: path.to_owned() })
+        Ok(obj)
     }
 }
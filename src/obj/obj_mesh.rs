@@ -1,6 +1,11 @@
 use suoi_types::{Vector2, Vector3};
 
-use crate::{face::Face, MtlMaterial};
+use crate::{
+    bounds::BoundsCache,
+    face::{Face, FaceElement, FaceGroup, MaterialSpan},
+    freeform::{Curve, Curve2, FreeformElement, Surface, SurfaceConnection},
+    math, MaterialHandle, VertexAttribute,
+};
 
 #[allow(unused)]
 #[derive(Debug)]
@@ -13,11 +18,36 @@ pub struct ObjMesh {
     normal_data: Vec<Vector3>,
     uv_data: Vec<Vector2>,
     
+    // token: v (w component), only stored
+    // up to the last position with a weight other than 1
+    weight_data: Vec<f32>,
+
+    // token: vp
+    parameter_data: Vec<Vector3>,
+
     // token: f
     face_data: Vec<Face>,
 
+    // tokens: curv, curv2, surf, con
+    curve_data: Vec<Curve>,
+    curve2_data: Vec<Curve2>,
+    surface_data: Vec<Surface>,
+    connection_data: Vec<SurfaceConnection>,
+
+    // token: g
+    group_data: Vec<FaceGroup>,
+
+    // number of positions, uvs and normals defined
+    // in the file before this mesh, see `set_index_base`
+    position_base: u32,
+    uv_base: u32,
+    normal_base: u32,
+
     // token: usemtl
-    active_material: MtlMaterial,
+    material_data: Vec<MaterialSpan>,
+
+    // bounds of `position_data`, cleared whenever it changes
+    bounds: BoundsCache,
 }
 
 impl ObjMesh {
@@ -32,8 +62,19 @@ impl ObjMesh {
             position_data: vec![],
             normal_data: vec![],
             uv_data: vec![],
+            weight_data: vec![],
+            parameter_data: vec![],
             face_data: vec![],
-            active_material: MtlMaterial::empty(),
+            curve_data: vec![],
+            curve2_data: vec![],
+            surface_data: vec![],
+            connection_data: vec![],
+            group_data: vec![],
+            position_base: 0,
+            uv_base: 0,
+            normal_base: 0,
+            material_data: vec![],
+            bounds: BoundsCache::default(),
         }
     }
 
@@ -57,6 +98,101 @@ impl ObjMesh {
         &self.face_data
     }
 
+    /// Mutable getter for `face_data` of `ObjMesh`, for edits that keep the number of faces
+    pub(crate) fn faces_mut(&mut self) -> &mut Vec<Face> {
+        &mut self.face_data
+    }
+
+    /// Immutable getter for `bounds` of `ObjMesh`
+    pub(crate) fn bounds_cache(&self) -> &BoundsCache {
+        &self.bounds
+    }
+
+    /// Mutable getter for `normal_data` of `ObjMesh`
+    pub(crate) fn normals_mut(&mut self) -> &mut Vec<Vector3> {
+        &mut self.normal_data
+    }
+
+    /// Mutable getter for `position_data` of `ObjMesh`, clearing the cached bounds
+    pub(crate) fn positions_mut(&mut self) -> &mut Vec<Vector3> {
+        self.bounds.invalidate();
+        &mut self.position_data
+    }
+
+    /// Mutable getter for `uv_data` of `ObjMesh`
+    pub(crate) fn uvs_mut(&mut self) -> &mut Vec<Vector2> {
+        &mut self.uv_data
+    }
+
+    /**
+    `ObjMesh.weight`
+    ---
+    Returns the rational weight (`w` component) of the
+    position with index `index`, `1.0` when none was given
+    */
+    pub fn weight(&self, index: usize) -> f32 {
+        self.weight_data.get(index).copied().unwrap_or(1.0)
+    }
+
+    /// Immutable getter for `parameter_data` of `ObjMesh`
+    pub fn parameters(&self) -> &Vec<Vector3> {
+        &self.parameter_data
+    }
+
+    /// Immutable getter for `curve_data` of `ObjMesh`
+    pub fn curves(&self) -> &Vec<Curve> {
+        &self.curve_data
+    }
+
+    /// Immutable getter for `curve2_data` of `ObjMesh`
+    pub fn curves2(&self) -> &Vec<Curve2> {
+        &self.curve2_data
+    }
+
+    /// Immutable getter for `surface_data` of `ObjMesh`
+    pub fn surfaces(&self) -> &Vec<Surface> {
+        &self.surface_data
+    }
+
+    /// Immutable getter for `connection_data` of `ObjMesh`
+    pub fn connections(&self) -> &Vec<SurfaceConnection> {
+        &self.connection_data
+    }
+
+    /// Immutable getter for `group_data` of `ObjMesh`
+    pub fn groups(&self) -> &Vec<FaceGroup> {
+        &self.group_data
+    }
+
+    /// Immutable getter for `position_base` of `ObjMesh`
+    pub fn position_base(&self) -> u32 {
+        self.position_base
+    }
+
+    /// Immutable getter for `uv_base` of `ObjMesh`
+    pub fn uv_base(&self) -> u32 {
+        self.uv_base
+    }
+
+    /// Immutable getter for `normal_base` of `ObjMesh`
+    pub fn normal_base(&self) -> u32 {
+        self.normal_base
+    }
+
+    /**
+    `ObjMesh.set_index_base`
+    ---
+    Face indices of an `.obj` file count from the start of the file,
+    not from the start of the mesh. The base is the number of positions,
+    uvs and normals that precede the mesh, so that an index `i` refers to
+    element `i - base - 1` of the mesh's own buffers.
+    */
+    pub fn set_index_base(&mut self, position_base: u32, uv_base: u32, normal_base: u32) {
+        self.position_base = position_base;
+        self.uv_base = uv_base;
+        self.normal_base = normal_base;
+    }
+
     /// Getter for `name` of `ObjMesh`
     pub fn get_name(&self) -> &str {
         &self.name
@@ -67,14 +203,78 @@ impl ObjMesh {
         self.name = value.to_owned()
     }
 
-    /// Getter for `material` of `ObjMesh`
-    pub fn get_material(&self) -> &MtlMaterial {
-        &self.active_material
+    /// Immutable getter for `material_data` of `ObjMesh`
+    pub fn material_spans(&self) -> &Vec<MaterialSpan> {
+        &self.material_data
+    }
+
+    /**
+    `ObjMesh.get_material`
+    ---
+    Returns the handle of the active material,
+    the one set by the last `usemtl` statement
+    */
+    pub fn get_material(&self) -> Option<MaterialHandle> {
+        self.material_data.last().and_then(|span| span.material())
+    }
+
+    /**
+    `ObjMesh.set_material`
+    ---
+    Makes `value` the material of every face loaded after this call.
+    The handle refers to the material table of the owning `Obj`.
+    */
+    pub fn set_material(&mut self, value: MaterialHandle) {
+        self.use_material(Some(value));
+    }
+
+    /**
+    `ObjMesh.clear_material`
+    ---
+    Leaves the faces loaded after this call without a material,
+    like a `usemtl` statement without a name
+    */
+    pub fn clear_material(&mut self) {
+        self.use_material(None);
+    }
+
+    /// Starts a material span with `value` at the next face loaded
+    pub(crate) fn use_material(&mut self, value: Option<MaterialHandle>) {
+        self.use_material_from(value, self.face_data.len());
+    }
+
+    /**
+    `ObjMesh.use_material_from`
+    ---
+    Starts a material span with `value` at the face with index `first_face`,
+    which must not come before the start of the last span
+    */
+    pub(crate) fn use_material_from(&mut self, value: Option<MaterialHandle>, first_face: usize) {
+        if let Some(span) = self.material_data.last_mut() {
+            if span.first_face() == first_face {
+                *span = MaterialSpan::new(value, first_face);
+                return;
+            }
+        } else if value.is_none() {
+            // faces have no material until the first span
+            return;
+        }
+
+        self.material_data
+            .push(MaterialSpan::new(value, first_face));
     }
 
-    /// Setter for `material` of `ObjMesh`
-    pub fn set_material(&mut self, value: MtlMaterial) {
-        self.active_material = value;
+    /**
+    `ObjMesh.face_material`
+    ---
+    Returns the handle of the material used by the face with index `face`
+    */
+    pub fn face_material(&self, face: usize) -> Option<MaterialHandle> {
+        self.material_data
+            .iter()
+            .take_while(|span| span.first_face() <= face)
+            .last()
+            .and_then(|span| span.material())
     }
 
     /**
@@ -83,9 +283,55 @@ impl ObjMesh {
     Loads a `Vector3` into the `vertex_data` buffer of `self`
     */
     pub fn load_position(&mut self, value: Vector3) {
+        self.bounds.invalidate();
         self.position_data.push(value)
     }
 
+    /**
+    `ObjMesh.load_weighted_position`
+    ---
+    Loads a `Vector3` into the `position_data` buffer of `self`,
+    along with the rational `weight` used by free-form geometry
+    */
+    pub fn load_weighted_position(&mut self, value: Vector3, weight: f32) {
+        if weight != 1.0 {
+            self.weight_data.resize(self.position_data.len(), 1.0);
+            self.weight_data.push(weight);
+        }
+        self.load_position(value);
+    }
+
+    /**
+    `ObjMesh.load_parameter`
+    ---
+    Loads a `Vector3` into the `parameter_data` buffer of `self`
+    */
+    pub fn load_parameter(&mut self, value: Vector3) {
+        self.parameter_data.push(value)
+    }
+
+    /**
+    `ObjMesh.load_freeform`
+    ---
+    Loads a completed curve or surface into the matching buffer of `self`
+    */
+    pub fn load_freeform(&mut self, element: FreeformElement) {
+        match element {
+            FreeformElement::Curve(curve) => self.curve_data.push(curve),
+            FreeformElement::Curve2(curve) => self.curve2_data.push(curve),
+            FreeformElement::Surface(surface) => self.surface_data.push(surface),
+        }
+    }
+
+    /**
+    `ObjMesh.load_connection`
+    ---
+    Loads a `SurfaceConnection` into the `connection_data` buffer of `self`
+    */
+    pub fn load_connection(&mut self, value: SurfaceConnection) {
+        self.connection_data.push(value)
+    }
+
     /**
     `ObjMesh.load_normal`
     ---
@@ -113,6 +359,196 @@ impl ObjMesh {
         self.face_data.push(face);
     }
 
+    /**
+    `ObjMesh.continue_with`
+    ---
+    Moves every element of `other`, which continues `self` in the same
+    file, to the end of the buffers of `self`. The groups and material
+    spans of `other` keep starting at the same faces.
+    */
+    pub(crate) fn continue_with(&mut self, mut other: ObjMesh) {
+        let face_offset = self.face_data.len();
+
+        if !other.weight_data.is_empty() {
+            self.weight_data.resize(self.position_data.len(), 1.0);
+            self.weight_data.append(&mut other.weight_data);
+        }
+        self.bounds.invalidate();
+        self.position_data.append(&mut other.position_data);
+        self.normal_data.append(&mut other.normal_data);
+        self.uv_data.append(&mut other.uv_data);
+        self.parameter_data.append(&mut other.parameter_data);
+        self.face_data.append(&mut other.face_data);
+        self.curve_data.append(&mut other.curve_data);
+        self.curve2_data.append(&mut other.curve2_data);
+        self.surface_data.append(&mut other.surface_data);
+        self.connection_data.append(&mut other.connection_data);
+
+        for group in other.group_data {
+            self.group_data.push(FaceGroup::new(
+                group.names().to_vec(),
+                group.first_face() + face_offset,
+            ));
+        }
+        for span in other.material_data {
+            self.use_material_from(span.material(), span.first_face() + face_offset);
+        }
+    }
+
+    /**
+    `ObjMesh.load_group`
+    ---
+    Starts a new `FaceGroup` with the given `names`,
+    applying to every face loaded after it
+    */
+    pub fn load_group(&mut self, names: Vec<String>) {
+        self.group_data
+            .push(FaceGroup::new(names, self.face_data.len()));
+    }
+
+    /**
+    `ObjMesh.retain_faces`
+    ---
+    Keeps the faces whose entry in `keep` is `true` and drops the others,
+    moving the start of every group and material span along with them
+    */
+    pub(crate) fn retain_faces(&mut self, keep: &[bool]) {
+        // number of kept faces before each face, and in total
+        let mut kept_before = Vec::with_capacity(keep.len() + 1);
+        let mut kept = 0;
+        for &keep in keep {
+            kept_before.push(kept);
+            kept += keep as usize;
+        }
+        kept_before.push(kept);
+        let rebase = |first_face: usize| kept_before[first_face.min(keep.len())];
+
+        let mut index = 0;
+        self.face_data.retain(|_| {
+            index += 1;
+            keep[index - 1]
+        });
+
+        // spans left empty are replaced by the next one starting at the same face
+        let mut groups: Vec<FaceGroup> = vec![];
+        for group in self.group_data.drain(..) {
+            let first_face = rebase(group.first_face());
+            if groups
+                .last()
+                .is_some_and(|last| last.first_face() == first_face)
+            {
+                groups.pop();
+            }
+            groups.push(FaceGroup::new(group.names().clone(), first_face));
+        }
+        self.group_data = groups;
+
+        let mut spans: Vec<MaterialSpan> = vec![];
+        for span in self.material_data.drain(..) {
+            let first_face = rebase(span.first_face());
+            if spans
+                .last()
+                .is_some_and(|last| last.first_face() == first_face)
+            {
+                spans.pop();
+            }
+            spans.push(MaterialSpan::new(span.material(), first_face));
+        }
+        self.material_data = spans;
+    }
+
+    /**
+    `ObjMesh.remap`
+    ---
+    Moves element `i` of the `attribute` buffer to index `map[i]`, dropping
+    the elements mapped to `None`, and rewrites every face and free-form index
+    referring to the buffer. The new indices must cover `0..n` without gaps;
+    when several elements map to the same index, the first one is kept.
+    References to dropped elements become `0`, and indices outside the
+    buffer are left as they are.
+    */
+    pub(crate) fn remap(&mut self, attribute: VertexAttribute, map: &[Option<u32>]) {
+        fn remap_data<T: Clone>(data: &[T], map: &[Option<u32>]) -> Vec<T> {
+            let count = map
+                .iter()
+                .flatten()
+                .map(|&i| i as usize + 1)
+                .max()
+                .unwrap_or(0);
+            let mut remapped: Vec<Option<T>> = vec![None; count];
+            for (value, &target) in data.iter().zip(map) {
+                if let Some(slot) = target.map(|i| &mut remapped[i as usize]) {
+                    slot.get_or_insert_with(|| value.clone());
+                }
+            }
+            remapped.into_iter().flatten().collect()
+        }
+
+        let base = match attribute {
+            VertexAttribute::Position => self.position_base,
+            VertexAttribute::Uv => self.uv_base,
+            VertexAttribute::Normal => self.normal_base,
+        };
+        let len = map.len() as u32;
+        let rewrite = |index: u32| match index.checked_sub(base + 1) {
+            Some(local) if local < len => map[local as usize].map_or(0, |i| base + 1 + i),
+            _ => index,
+        };
+        let rewrite_element = |element: &FaceElement| match attribute {
+            VertexAttribute::Position => FaceElement::new(
+                rewrite(element.position_index()),
+                element.normal_index(),
+                element.uv_index(),
+            ),
+            VertexAttribute::Uv => FaceElement::new(
+                element.position_index(),
+                element.normal_index(),
+                rewrite(element.uv_index()),
+            ),
+            VertexAttribute::Normal => FaceElement::new(
+                element.position_index(),
+                rewrite(element.normal_index()),
+                element.uv_index(),
+            ),
+        };
+
+        for face in &mut self.face_data {
+            for element in face.elements_mut() {
+                *element = rewrite_element(element);
+            }
+        }
+        for surface in &mut self.surface_data {
+            for element in surface.control_points_mut() {
+                *element = rewrite_element(element);
+            }
+        }
+
+        match attribute {
+            VertexAttribute::Position => {
+                for curve in &mut self.curve_data {
+                    for index in curve.control_points_mut() {
+                        *index = rewrite(*index);
+                    }
+                }
+
+                let weights: Vec<f32> = (0..self.position_data.len())
+                    .map(|index| self.weight(index))
+                    .collect();
+                self.position_data = remap_data(&self.position_data, map);
+                self.bounds.invalidate();
+                self.weight_data = remap_data(&weights, map);
+                while self.weight_data.last() == Some(&1.0) {
+                    self.weight_data.pop();
+                }
+            }
+            VertexAttribute::Uv => self.uv_data = remap_data(&self.uv_data, map),
+            VertexAttribute::Normal => self.normal_data = remap_data(&self.normal_data, map),
+        }
+    }
+
+    #[deprecated(
+        note = "indices are 1-based: use `ObjMesh.position_base() + 1`, after `ObjMesh.compact` if needed"
+    )]
     pub fn min_pos_index(&self) -> u32 {
         let mut min = u32::MAX;
         for face in self.faces() {
@@ -125,6 +561,9 @@ impl ObjMesh {
         min
     }
 
+    #[deprecated(
+        note = "indices are 1-based: use `ObjMesh.normal_base() + 1`, after `ObjMesh.compact` if needed"
+    )]
     pub fn min_nrm_index(&self) -> u32 {
         let mut min = u32::MAX;
         for face in self.faces() {
@@ -137,6 +576,9 @@ impl ObjMesh {
         min
     }
 
+    #[deprecated(
+        note = "indices are 1-based: use `ObjMesh.uv_base() + 1`, after `ObjMesh.compact` if needed"
+    )]
     pub fn min_uvs_index(&self) -> u32 {
         let mut min = u32::MAX;
         for face in self.faces() {
@@ -149,3 +591,50 @@ impl ObjMesh {
         min
     }
 }
+
+impl PartialEq for ObjMesh {
+    /// Compares every buffer of both meshes, ignoring their cached bounds
+    fn eq(&self, other: &Self) -> bool {
+        let positions = |mesh: &Self| {
+            mesh.position_data
+                .iter()
+                .map(math::from_vector)
+                .collect::<Vec<_>>()
+        };
+        let normals = |mesh: &Self| {
+            mesh.normal_data
+                .iter()
+                .map(math::from_vector)
+                .collect::<Vec<_>>()
+        };
+        let parameters = |mesh: &Self| {
+            mesh.parameter_data
+                .iter()
+                .map(math::from_vector)
+                .collect::<Vec<_>>()
+        };
+        let uvs = |mesh: &Self| {
+            mesh.uv_data
+                .iter()
+                .map(|uv| [uv.x, uv.y])
+                .collect::<Vec<_>>()
+        };
+
+        self.name == other.name
+            && positions(self) == positions(other)
+            && normals(self) == normals(other)
+            && uvs(self) == uvs(other)
+            && self.weight_data == other.weight_data
+            && parameters(self) == parameters(other)
+            && self.face_data == other.face_data
+            && self.curve_data == other.curve_data
+            && self.curve2_data == other.curve2_data
+            && self.surface_data == other.surface_data
+            && self.connection_data == other.connection_data
+            && self.group_data == other.group_data
+            && self.position_base == other.position_base
+            && self.uv_base == other.uv_base
+            && self.normal_base == other.normal_base
+            && self.material_data == other.material_data
+    }
+}
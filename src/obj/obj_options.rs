@@ -0,0 +1,143 @@
+use crate::CoordinateSystem;
+use crate::MtlMaterial;
+
+/**
+`obj::ObjImportOptions`
+---
+Settings for `Obj::import_with_options`.
+The defaults are the ones used by `Obj::import`.
+*/
+#[derive(Debug, Clone)]
+pub struct ObjImportOptions {
+    // material used when a library or material can't be found
+    default_material: MtlMaterial,
+
+    // fail on missing libraries and materials instead of falling back
+    strict_materials: bool,
+
+    // memory-map the file instead of reading it, with the `mmap` feature
+    memory_map: bool,
+
+    // worker threads used to parse the file, 1 parses it on the calling thread
+    threads: usize,
+
+    // conventions the file was written in, and the ones to convert it to
+    source_system: CoordinateSystem,
+    target_system: CoordinateSystem,
+
+    // replace v with 1 - v in texture coordinates
+    flip_v: bool,
+}
+
+impl Default for ObjImportOptions {
+    fn default() -> Self {
+        Self {
+            default_material: MtlMaterial::fallback(),
+            strict_materials: false,
+            memory_map: false,
+            threads: 1,
+            source_system: CoordinateSystem::default(),
+            target_system: CoordinateSystem::default(),
+            flip_v: false,
+        }
+    }
+}
+
+impl ObjImportOptions {
+    /// Immutable getter for `default_material` of `ObjImportOptions`
+    pub fn default_material(&self) -> &MtlMaterial {
+        &self.default_material
+    }
+
+    /// Setter for `default_material` of `ObjImportOptions`
+    pub fn set_default_material(&mut self, value: MtlMaterial) {
+        self.default_material = value;
+    }
+
+    /// Immutable getter for `strict_materials` of `ObjImportOptions`
+    pub fn strict_materials(&self) -> bool {
+        self.strict_materials
+    }
+
+    /**
+    `ObjImportOptions.set_strict_materials`
+    ---
+    When `true`, a missing `mtllib` file fails the import with
+    `ImportError::InvalidPath` and an unknown `usemtl` name fails it
+    with `ImportError::InvalidData`, instead of falling back to the
+    default material and reporting an `ImportWarning`
+    */
+    pub fn set_strict_materials(&mut self, value: bool) {
+        self.strict_materials = value;
+    }
+
+    /// Immutable getter for `memory_map` of `ObjImportOptions`
+    pub fn memory_map(&self) -> bool {
+        self.memory_map
+    }
+
+    /**
+    `ObjImportOptions.set_memory_map`
+    ---
+    When `true`, the `.obj` file is memory-mapped instead of being read
+    into memory, which saves a copy of very large files. Only has an
+    effect when the crate is built with the `mmap` feature.
+    */
+    pub fn set_memory_map(&mut self, value: bool) {
+        self.memory_map = value;
+    }
+
+    /// Immutable getter for `threads` of `ObjImportOptions`
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /**
+    `ObjImportOptions.set_threads`
+    ---
+    Sets the number of threads used to parse the file. With more than
+    one, the file is split into chunks at line boundaries that are parsed
+    in parallel and merged back in file order, giving the same `Obj` as a
+    serial import. `0` uses one thread per available core.
+    */
+    pub fn set_threads(&mut self, value: usize) {
+        self.threads = value;
+    }
+
+    /// Immutable getter for `source_system` of `ObjImportOptions`
+    pub fn source_system(&self) -> &CoordinateSystem {
+        &self.source_system
+    }
+
+    /**
+    `ObjImportOptions.set_source_system`
+    ---
+    Sets the conventions the file was written in, such as right-handed
+    Z-up in centimeters for a file exported from a Z-up tool. Positions
+    and normals are converted from it to `target_system`, and faces are
+    reversed when the handedness changes, see `ObjMesh.convert_coordinates`.
+    */
+    pub fn set_source_system(&mut self, value: CoordinateSystem) {
+        self.source_system = value;
+    }
+
+    /// Immutable getter for `target_system` of `ObjImportOptions`
+    pub fn target_system(&self) -> &CoordinateSystem {
+        &self.target_system
+    }
+
+    /// Setter for `target_system` of `ObjImportOptions`, the conventions of the application
+    pub fn set_target_system(&mut self, value: CoordinateSystem) {
+        self.target_system = value;
+    }
+
+    /// Immutable getter for `flip_v` of `ObjImportOptions`
+    pub fn flip_v(&self) -> bool {
+        self.flip_v
+    }
+
+    /// Setter for `flip_v` of `ObjImportOptions`, see `ObjMesh.flip_v`
+    pub fn set_flip_v(&mut self, value: bool) {
+        self.flip_v = value;
+    }
+}
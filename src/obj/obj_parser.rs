@@ -0,0 +1,733 @@
+use std::io::BufRead;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::face::{Face, FaceElement};
+use crate::freeform::{ElementCounts, FreeformState};
+use crate::fs::Path;
+use crate::obj_mesh::ObjMesh;
+use crate::ByteLines;
+use crate::ImportError;
+use crate::ImportWarning;
+use crate::MaterialHandle;
+use crate::Mtl;
+use crate::Obj;
+use crate::ObjEvent;
+use crate::ObjImportOptions;
+use crate::ObjReader;
+use crate::Resource;
+use crate::Tokens;
+
+/// Size of the chunks a file is split into for parsing in parallel
+const CHUNK_SIZE: usize = 4 << 20;
+
+/**
+`obj::Deferred`
+---
+A statement of a chunk that depends on the statements before the chunk,
+which a `MeshFragment` leaves to `ObjBuilder.join`
+*/
+enum Deferred {
+    /// `usemtl`, whose material handle depends on the libraries loaded before
+    UseMaterial(Option<String>),
+    /// `mtllib`, whose materials must be loaded in file order
+    MaterialLibrary(String),
+    /// Free-form statements, which are read in file order
+    /// and resolve their indices against the element counts
+    Freeform(String, String),
+    /// A face with relative indices, which replaces its placeholder face
+    RelativeFace(String),
+    /// The first vertex color of the chunk, which is warned about
+    VertexColors,
+}
+
+/**
+`obj::DeferredStatement`
+---
+A `Deferred` statement, along with where it was found
+*/
+struct DeferredStatement {
+    statement: Deferred,
+    // line within the chunk
+    line: usize,
+    // mesh of the fragment the statement applies to
+    mesh: usize,
+    // number of faces of that mesh before the statement
+    face: usize,
+    // element counts of the chunk before the statement
+    counts: ElementCounts,
+}
+
+/**
+`obj::MeshFragment`
+---
+The meshes read from one chunk of a file by a worker thread, with
+element counts and index bases counting from the start of the chunk.
+The first mesh continues the mesh the chunk starts in, and the others
+start at the `o` statements of the chunk. Statements that depend on the
+rest of the file are deferred to `ObjBuilder.join`, which fixes the
+index bases and places relative faces and material spans when the
+fragments are joined in file order.
+*/
+pub(crate) struct MeshFragment {
+    meshes: Vec<ObjMesh>,
+    counts: ElementCounts,
+    deferred: Vec<DeferredStatement>,
+
+    // line of the first statement that adds to the first mesh, which
+    // fails if the chunk starts before the first `o` of the file
+    continues_mesh: Option<usize>,
+
+    // error that ended the chunk early, and its line
+    error: Option<(usize, ImportError)>,
+    lines: usize,
+}
+
+impl MeshFragment {
+    /// Builds the fragment of the chunk `bytes`, which starts at a statement boundary
+    fn build(bytes: &[u8]) -> Self {
+        let mut reader = ObjReader::part(bytes);
+        let mut fragment = Self {
+            meshes: vec![ObjMesh::empty()],
+            counts: ElementCounts::default(),
+            deferred: vec![],
+            continues_mesh: None,
+            error: None,
+            lines: 0,
+        };
+
+        loop {
+            match reader.read_event() {
+                Ok(Some(event)) => fragment.apply(event, reader.line()),
+                Ok(None) => break,
+                Err(err) => {
+                    fragment.error = Some((reader.line(), err));
+                    break;
+                }
+            }
+        }
+        fragment.lines = reader.lines_read();
+
+        fragment
+    }
+
+    /// Adds `event`, read on line `line` of the chunk, to the fragment
+    fn apply(&mut self, event: ObjEvent, line: usize) {
+        match event {
+            ObjEvent::Vertex(vertex, w) => {
+                self.mesh(line).load_weighted_position(vertex, w);
+                self.counts.positions += 1;
+            }
+            ObjEvent::ColoredVertex(vertex) => {
+                if !self
+                    .deferred
+                    .iter()
+                    .any(|deferred| matches!(deferred.statement, Deferred::VertexColors))
+                {
+                    self.defer(Deferred::VertexColors, line);
+                }
+                self.mesh(line).load_position(vertex);
+                self.counts.positions += 1;
+            }
+            ObjEvent::Normal(normal) => {
+                self.mesh(line).load_normal(normal);
+                self.counts.normals += 1;
+            }
+            ObjEvent::Uv(uv) => {
+                self.mesh(line).load_uv(uv);
+                self.counts.uvs += 1;
+            }
+            ObjEvent::Parameter(parameter) => {
+                self.mesh(line).load_parameter(parameter);
+                self.counts.parameters += 1;
+            }
+            ObjEvent::Face(face) => self.mesh(line).load_face(face),
+            ObjEvent::RelativeFace(arguments) => {
+                self.defer(Deferred::RelativeFace(arguments), line);
+                self.mesh(line).load_face(Face::new(vec![]));
+            }
+            ObjEvent::Object(name) => {
+                let mut new_mesh = ObjMesh::empty();
+                new_mesh.set_name(&name);
+                new_mesh.set_index_base(
+                    self.counts.positions,
+                    self.counts.uvs,
+                    self.counts.normals,
+                );
+
+                self.meshes.push(new_mesh);
+            }
+            ObjEvent::Group(names) => self.mesh(line).load_group(names),
+            ObjEvent::UseMaterial(name) => self.defer(Deferred::UseMaterial(name), line),
+            ObjEvent::MaterialLibrary(libraries) => {
+                self.defer(Deferred::MaterialLibrary(libraries), line)
+            }
+            ObjEvent::Freeform(cmd, arguments) => {
+                self.defer(Deferred::Freeform(cmd, arguments), line)
+            }
+        }
+    }
+
+    /// The mesh the statement on line `line` adds to
+    fn mesh(&mut self, line: usize) -> &mut ObjMesh {
+        if self.meshes.len() == 1 && self.continues_mesh.is_none() {
+            self.continues_mesh = Some(line);
+        }
+
+        self.meshes.last_mut().unwrap()
+    }
+
+    fn defer(&mut self, statement: Deferred, line: usize) {
+        let mesh = self.meshes.len() - 1;
+        self.deferred.push(DeferredStatement {
+            statement,
+            line,
+            mesh,
+            face: self.meshes[mesh].faces().len(),
+            counts: self.counts,
+        });
+    }
+}
+
+/**
+`obj::ObjBuilder`
+---
+Assembles an `Obj` from the events of its file, in file order
+*/
+pub(crate) struct ObjBuilder<'a> {
+    path: &'a Path,
+    options: &'a ObjImportOptions,
+
+    meshes: Vec<ObjMesh>,
+    mtl: Mtl,
+    material_libraries: Vec<String>,
+    warnings: Vec<ImportWarning>,
+
+    // global element counts, used as the index base of new meshes
+    position_count: u32,
+    uv_count: u32,
+    normal_count: u32,
+    parameter_count: u32,
+
+    freeform: FreeformState,
+}
+
+impl<'a> ObjBuilder<'a> {
+    pub(crate) fn new(path: &'a Path, options: &'a ObjImportOptions) -> Self {
+        Self {
+            path,
+            options,
+            meshes: vec![],
+            mtl: Mtl::empty(),
+            material_libraries: vec![],
+            warnings: vec![],
+            position_count: 0,
+            uv_count: 0,
+            normal_count: 0,
+            parameter_count: 0,
+            freeform: FreeformState::default(),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Obj {
+        Obj::from_parts(
+            self.path.to_owned(),
+            self.meshes,
+            self.material_libraries,
+            self.mtl,
+            self.warnings,
+        )
+    }
+
+    fn current_mesh(&mut self) -> Result<&mut ObjMesh, ImportError> {
+        self.meshes.last_mut().ok_or(ImportError::InvalidData)
+    }
+
+    /**
+    `ObjBuilder.read_all`
+    ---
+    Applies every event of `reader`, in order
+    */
+    pub(crate) fn read_all<R: BufRead>(
+        &mut self,
+        mut reader: ObjReader<R>,
+    ) -> Result<(), ImportError> {
+        while let Some(event) = reader.next_event()? {
+            self.apply(event)
+                .map_err(|err| ImportError::AtLine(reader.line(), Box::new(err)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds `event` to the `Obj` being built
+    pub(crate) fn apply(&mut self, event: ObjEvent) -> Result<(), ImportError> {
+        match event {
+            ObjEvent::Vertex(vertex, w) => {
+                self.current_mesh()?.load_weighted_position(vertex, w);
+                self.position_count += 1;
+            }
+            ObjEvent::ColoredVertex(vertex) => {
+                self.warn_vertex_colors();
+                self.current_mesh()?.load_position(vertex);
+                self.position_count += 1;
+            }
+            ObjEvent::Normal(normal) => {
+                self.current_mesh()?.load_normal(normal);
+                self.normal_count += 1;
+            }
+            ObjEvent::Uv(uv) => {
+                self.current_mesh()?.load_uv(uv);
+                self.uv_count += 1;
+            }
+            ObjEvent::Parameter(parameter) => {
+                self.current_mesh()?.load_parameter(parameter);
+                self.parameter_count += 1;
+            }
+            ObjEvent::Face(face) => self.current_mesh()?.load_face(face),
+            ObjEvent::RelativeFace(arguments) => {
+                let face = Self::resolve_face(&arguments, self.counts())?;
+                self.current_mesh()?.load_face(face);
+            }
+            ObjEvent::Object(name) => {
+                let mut new_mesh = ObjMesh::empty();
+                new_mesh.set_name(&name);
+                new_mesh.set_index_base(self.position_count, self.uv_count, self.normal_count);
+
+                self.meshes.push(new_mesh);
+            }
+            ObjEvent::Group(names) => self.current_mesh()?.load_group(names),
+            ObjEvent::UseMaterial(name) => {
+                let handle = self.material_handle(name.as_deref())?;
+                self.current_mesh()?.use_material(handle);
+            }
+            ObjEvent::MaterialLibrary(libraries) => self.load_libraries(&libraries)?,
+            ObjEvent::Freeform(cmd, arguments) => {
+                let mesh = self.meshes.len().checked_sub(1);
+                let mesh = mesh.ok_or(ImportError::InvalidData)?;
+                let counts = self.counts();
+                self.load_freeform(mesh, counts, &cmd, &mut arguments.split_ascii_whitespace())?
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+    `ObjBuilder.apply_parallel`
+    ---
+    Reads `bytes` on `threads` worker threads. Workers take chunks in order
+    and each builds a `MeshFragment` of its chunk; fragments are joined as
+    soon as all the chunks before them are, so that indices, objects,
+    groups and materials come out exactly as they do when reading
+    serially, errors included.
+    */
+    pub(crate) fn apply_parallel(
+        &mut self,
+        bytes: &[u8],
+        threads: usize,
+    ) -> Result<(), ImportError> {
+        let chunks = ByteLines::split(bytes, (bytes.len() / CHUNK_SIZE).max(threads));
+        let next = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::sync_channel(threads);
+            for _ in 0..threads {
+                let sender = sender.clone();
+                let (chunks, next) = (&chunks, &next);
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(chunk) = chunks.get(index) else {
+                        break;
+                    };
+                    // the receiver is gone once an error has been found
+                    if sender.send((index, MeshFragment::build(chunk))).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            // fragments that finished before the ones ahead of them
+            let mut finished: Vec<Option<MeshFragment>> = chunks.iter().map(|_| None).collect();
+            let mut joined = 0;
+            let mut first_line = 0;
+            for (index, fragment) in receiver {
+                finished[index] = Some(fragment);
+                while let Some(fragment) = finished.get_mut(joined).and_then(Option::take) {
+                    first_line += self.join(fragment, first_line)?;
+                    joined += 1;
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /**
+    `ObjBuilder.join`
+    ---
+    Adds the meshes of `fragment`, whose chunk starts on line `first_line + 1`
+    of the file, and returns its number of lines. The first mesh of the
+    fragment continues the last mesh read, the index bases of the others
+    are moved past the elements before the chunk, and the deferred statements
+    are applied in file order, at the faces they were read before.
+    Returns the error a serial read would have found first, if any.
+    */
+    fn join(&mut self, fragment: MeshFragment, first_line: usize) -> Result<usize, ImportError> {
+        let at_line = |line: usize, err| ImportError::AtLine(first_line + line, Box::new(err));
+        let base = self.counts();
+
+        // the first error of the chunk, leaving out its deferred statements
+        let missing_mesh = fragment
+            .continues_mesh
+            .filter(|_| self.meshes.is_empty())
+            .map(|line| (line, ImportError::InvalidData));
+        let error = match (missing_mesh, fragment.error) {
+            (Some(missing), Some(error)) if error.0 < missing.0 => Some(error),
+            (Some(missing), _) => Some(missing),
+            (None, error) => error,
+        };
+
+        // where each mesh of the fragment ends up, and the faces before it
+        let mut placement = vec![];
+        let mut meshes = fragment.meshes.into_iter();
+        let first = meshes.next().unwrap_or_else(ObjMesh::empty);
+        let last = self.meshes.len().wrapping_sub(1);
+        match self.meshes.last_mut() {
+            Some(mesh) => {
+                placement.push((last, mesh.faces().len()));
+                mesh.continue_with(first);
+            }
+            // statements of the first mesh then fail, as there is no mesh yet
+            None => placement.push((last, 0)),
+        }
+        for mut mesh in meshes {
+            mesh.set_index_base(
+                base.positions + mesh.position_base(),
+                base.uvs + mesh.uv_base(),
+                base.normals + mesh.normal_base(),
+            );
+            placement.push((self.meshes.len(), 0));
+            self.meshes.push(mesh);
+        }
+
+        for deferred in fragment.deferred {
+            if error
+                .as_ref()
+                .is_some_and(|(line, _)| *line < deferred.line)
+            {
+                break;
+            }
+            let (mesh, faces) = placement[deferred.mesh];
+            self.apply_deferred(
+                deferred.statement,
+                mesh,
+                faces + deferred.face,
+                base + deferred.counts,
+            )
+            .map_err(|err| at_line(deferred.line, err))?;
+        }
+        if let Some((line, err)) = error {
+            return Err(at_line(line, err));
+        }
+
+        let counts = base + fragment.counts;
+        self.position_count = counts.positions;
+        self.uv_count = counts.uvs;
+        self.normal_count = counts.normals;
+        self.parameter_count = counts.parameters;
+
+        Ok(fragment.lines)
+    }
+
+    /**
+    `ObjBuilder.apply_deferred`
+    ---
+    Applies a statement deferred by a `MeshFragment` to the mesh with index
+    `mesh`, where it came before the face with index `face`. `counts` are
+    the element counts of the file before the statement.
+    */
+    fn apply_deferred(
+        &mut self,
+        statement: Deferred,
+        mesh: usize,
+        face: usize,
+        counts: ElementCounts,
+    ) -> Result<(), ImportError> {
+        match statement {
+            Deferred::UseMaterial(name) => {
+                let handle = self.material_handle(name.as_deref())?;
+                let mesh = self.meshes.get_mut(mesh).ok_or(ImportError::InvalidData)?;
+                mesh.use_material_from(handle, face);
+            }
+            Deferred::MaterialLibrary(libraries) => self.load_libraries(&libraries)?,
+            Deferred::Freeform(cmd, arguments) => {
+                self.load_freeform(mesh, counts, &cmd, &mut arguments.split_ascii_whitespace())?
+            }
+            Deferred::RelativeFace(arguments) => {
+                let resolved = Self::resolve_face(&arguments, counts)?;
+                let mesh = self.meshes.get_mut(mesh).ok_or(ImportError::InvalidData)?;
+                mesh.faces_mut()[face] = resolved;
+            }
+            Deferred::VertexColors => self.warn_vertex_colors(),
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the indices of a face with relative indices, given the element counts before it
+    fn resolve_face(arguments: &str, counts: ElementCounts) -> Result<Face, ImportError> {
+        let elements = arguments
+            .split_ascii_whitespace()
+            .map(|token| FaceElement::resolve(token, counts))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Face::new(elements))
+    }
+
+    /// Warns, once, that vertex colors were found and left out
+    fn warn_vertex_colors(&mut self) {
+        if !self.warnings.contains(&ImportWarning::VertexColorsIgnored) {
+            self.warnings.push(ImportWarning::VertexColorsIgnored);
+        }
+    }
+
+    /// Loads and merges the material libraries of an `mtllib` statement
+    fn load_libraries(&mut self, libraries: &str) -> Result<(), ImportError> {
+        let dir = self
+            .path
+            .parent()
+            .ok_or(ImportError::InvalidPath(self.path.to_owned()))?;
+
+        for mtl_file in Obj::split_libraries(dir, &mut libraries.split_ascii_whitespace())? {
+            self.material_libraries.push(mtl_file.to_owned());
+
+            match Mtl::import(&dir.join(mtl_file)) {
+                Ok(mut library) => {
+                    self.warnings.append(&mut library.take_warnings());
+                    self.mtl.merge(library);
+                }
+                Err(ImportError::InvalidPath(mtl_path)) if !self.options.strict_materials() => {
+                    self.warnings
+                        .push(ImportWarning::MissingMaterialLibrary(mtl_path));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+    `ObjBuilder.material_handle`
+    ---
+    Looks up the material named `mat_name`, or `None` for a `usemtl`
+    without a name. If it can't be found, a copy of the default material
+    is added under that name, so that faces keep the name they
+    referenced when the `Obj` is written back
+    */
+    fn material_handle(
+        &mut self,
+        mat_name: Option<&str>,
+    ) -> Result<Option<MaterialHandle>, ImportError> {
+        let Some(mat_name) = mat_name else {
+            return Ok(None);
+        };
+
+        let handle = match self.mtl.get_handle(mat_name) {
+            Some(handle) => handle,
+            None if !self.options.strict_materials() => {
+                self.warnings
+                    .push(ImportWarning::UnknownMaterial(mat_name.to_owned()));
+                let mut fallback = self.options.default_material().clone();
+                fallback.set_name(mat_name);
+                self.mtl.add_material(fallback)
+            }
+            None => return Err(ImportError::InvalidData),
+        };
+
+        Ok(Some(handle))
+    }
+
+    /// Number of elements of each kind read so far
+    fn counts(&self) -> ElementCounts {
+        ElementCounts {
+            positions: self.position_count,
+            uvs: self.uv_count,
+            normals: self.normal_count,
+            parameters: self.parameter_count,
+        }
+    }
+
+    /**
+    `ObjBuilder.load_freeform`
+    ---
+    Handles free-form curve and surface statements, and surface
+    connectivity, for the mesh with index `mesh`, given the
+    element counts of the file before the statement
+    */
+    fn load_freeform(
+        &mut self,
+        mesh: usize,
+        counts: ElementCounts,
+        cmd: &str,
+        tokens: &mut Tokens,
+    ) -> Result<(), ImportError> {
+        let mesh = self.meshes.get_mut(mesh).ok_or(ImportError::InvalidData)?;
+        if cmd == "con" {
+            mesh.load_connection(FreeformState::parse_connection(tokens)?);
+            return Ok(());
+        }
+
+        let material = mesh.get_material();
+        if let Some(element) = self
+            .freeform
+            .parse_statement(cmd, tokens, counts, material)?
+        {
+            mesh.load_freeform(element);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An `.obj` file with several objects, groups and materials, relative
+    /// indices, vertex colors and weights, a curve, a face continued over
+    /// most of the file and no newline at its end
+    fn sample() -> String {
+        let mut text = String::from("mtllib parallel.mtl\n# objects\n");
+        for object in 0..4 {
+            text += &format!("o part{}\n", object);
+            for corner in 0..4 {
+                text += &format!("v {} {} 0\nvt {} 0\nvn 0 0 1\n", object, corner, corner);
+            }
+            text += &format!(
+                "g side{} shared\nusemtl {}\n",
+                object,
+                ["red", "blue"][object % 2]
+            );
+            text += "f -4/-4/-4 -3/-3/-3 -2/-2/-2\nf -4//-4 -2//-2 -1//-1 # quad half\n";
+            match object {
+                1 => text += "v 1 1 1 0.5 0.5 0.5\nusemtl\nf -1 -2 -3\n",
+                2 => text += "v 0 0 1 2\nv 1 0 1\ncstype bezier\ndeg 1\ncurv 0 1 -2 -1\nend\n",
+                _ => (),
+            }
+        }
+
+        // the middle of the file, where every split cuts, is inside this face
+        text += "g long\nusemtl missing\nf";
+        for corner in 0..400 {
+            text += &format!(" {} \\\n", corner % 16 + 1);
+        }
+        text += " 1\nusemtl red\nf 1 2 3";
+        text
+    }
+
+    fn import(path: &Path, threads: usize) -> Result<Obj, ImportError> {
+        let mut options = ObjImportOptions::default();
+        options.set_threads(threads);
+        Obj::import_with_options(path, &options)
+    }
+
+    #[test]
+    fn parallel_import_matches_serial_import() {
+        let dir = std::env::temp_dir().join("suoi_simp_parallel_import");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("parallel.mtl"),
+            "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n",
+        )
+        .unwrap();
+        let text = sample();
+        std::fs::write(dir.join("parallel.obj"), &text).unwrap();
+
+        let middle = text.len() / 2;
+        let long = text.find("g long").unwrap();
+        assert!(long < middle && text[middle..].contains("\\\n 1\n"));
+
+        let serial = import(&dir.join("parallel.obj"), 1).unwrap();
+        for threads in [2, 3, 4, 7, 16] {
+            let parallel = import(&dir.join("parallel.obj"), threads).unwrap();
+            assert_eq!(serial.meshes(), parallel.meshes(), "{} threads", threads);
+            assert_eq!(
+                serial.materials().materials(),
+                parallel.materials().materials()
+            );
+            assert_eq!(serial.material_libraries(), parallel.material_libraries());
+            assert_eq!(serial.warnings(), parallel.warnings());
+        }
+        assert_eq!(serial.meshes().len(), 4);
+        assert_eq!(
+            serial.meshes()[3].faces().last().unwrap().elements().len(),
+            3
+        );
+        assert_eq!(serial.meshes()[3].faces()[2].elements().len(), 401);
+        assert_eq!(serial.meshes()[2].curves().len(), 1);
+        assert_eq!(serial.meshes()[2].weight(4), 2.0);
+        assert_eq!(
+            serial.warnings(),
+            &vec![
+                ImportWarning::VertexColorsIgnored,
+                ImportWarning::UnknownMaterial("missing".to_owned()),
+            ]
+        );
+
+        // errors found while reading, while joining and before the first object
+        let broken = [
+            text.replace("vn 0 0 1\ng side1", "vn 0 0 x\ng side1"),
+            text.replace("usemtl\nf -1 -2 -3", "usemtl\nf -1 -2 -30")
+                .replace("vn 0 0 1\ng side3", "vn 0 0 x\ng side3"),
+            format!("v 0 0 0\n{}", text),
+        ];
+        for broken in broken {
+            std::fs::write(dir.join("broken.obj"), broken).unwrap();
+            let serial = format!("{:?}", import(&dir.join("broken.obj"), 1).unwrap_err());
+            assert!(serial.starts_with("AtLine"));
+            for threads in [2, 3, 4, 7, 16] {
+                let parallel = format!(
+                    "{:?}",
+                    import(&dir.join("broken.obj"), threads).unwrap_err()
+                );
+                assert_eq!(serial, parallel);
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_library_problems_once() {
+        let dir = std::env::temp_dir().join("suoi_simp_library_warnings");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("spectral.mtl"),
+            "newmtl glow\nKe spectral glow.rfl\n",
+        )
+        .unwrap();
+        let text = "mtllib spectral.mtl absent.mtl\no tri\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+        std::fs::write(dir.join("libraries.obj"), text).unwrap();
+
+        let obj = import(&dir.join("libraries.obj"), 1).unwrap();
+        assert_eq!(
+            obj.warnings(),
+            &vec![
+                ImportWarning::SpectralColor("glow".to_owned()),
+                ImportWarning::MissingMaterialLibrary(dir.join("absent.mtl")),
+            ]
+        );
+        assert!(obj.materials().warnings().is_empty());
+        assert_eq!(obj.material_libraries().len(), 2);
+
+        let mut options = ObjImportOptions::default();
+        options.set_strict_materials(true);
+        let strict = Obj::import_with_options(&dir.join("libraries.obj"), &options);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(format!("{:?}", strict.unwrap_err()).contains("InvalidPath"));
+    }
+}
@@ -0,0 +1,573 @@
+use std::io::BufRead;
+
+use suoi_types::{Vector2, Vector3};
+
+use crate::face::Face;
+use crate::face::FaceElement;
+use crate::ByteLines;
+use crate::ByteTokens;
+use crate::Fs;
+use crate::ImportError;
+
+/**
+`obj::ObjEvent`
+---
+A single statement of an `.obj` file, as read by `ObjReader`.
+Indices in `Face` are the raw, file-wide indices of the statement.
+*/
+#[derive(Debug, Clone)]
+pub enum ObjEvent {
+    /// `v`: a position and its rational weight, `1` when not given
+    Vertex(Vector3, f32),
+    /// `v` followed by a vertex color, which is not kept:
+    /// the position, with a weight of `1`
+    ColoredVertex(Vector3),
+    /// `vn`: a vertex normal
+    Normal(Vector3),
+    /// `vt`: a texture coordinate
+    Uv(Vector2),
+    /// `vp`: a parameter space vertex of free-form geometry
+    Parameter(Vector3),
+    /// `f`: a polygonal face
+    Face(Face),
+    /// `f` with negative (relative) indices, which can only be
+    /// resolved once the elements defined before it are counted
+    RelativeFace(String),
+    /// `o`: the start of a new object, with its name
+    Object(String),
+    /// `g`: the group names of the faces that follow
+    Group(Vec<String>),
+    /// `usemtl`: the material of the faces that follow,
+    /// `None` when the statement has no name and clears it
+    UseMaterial(Option<String>),
+    /// `mtllib`: the material library file names, as written
+    MaterialLibrary(String),
+    /// Free-form geometry and connectivity statements such as
+    /// `cstype`, `curv`, `surf` or `con`: the command and its arguments
+    Freeform(String, String),
+}
+
+impl ObjEvent {
+    /**
+    `ObjEvent::parse`
+    ---
+    Parses a single statement, returning `None` for empty statements and
+    for the ones that carry no data for `Obj`, such as smoothing groups.
+    Vertex data and faces, which make up almost all of a file, are parsed
+    straight from bytes; the rest are decoded as text first.
+    */
+    pub fn parse(statement: &[u8]) -> Result<Option<Self>, ImportError> {
+        Self::parse_with(statement, &mut vec![])
+    }
+
+    /**
+    `ObjEvent::parse_with`
+    ---
+    Like `ObjEvent::parse`, reading the corners of a face into `elements`
+    first, so that a reader can reuse its allocation from face to face
+    */
+    pub(crate) fn parse_with(
+        statement: &[u8],
+        elements: &mut Vec<FaceElement>,
+    ) -> Result<Option<Self>, ImportError> {
+        let mut tokens = ByteTokens::new(statement);
+
+        Ok(Some(match tokens.next() {
+            Some(b"v") => {
+                // vertex definition, followed by either a rational weight
+                // or a vertex color (`r g b`, or `r g b a`)
+                let x = tokens.next_float()?;
+                let y = tokens.next_float()?;
+                let z = tokens.next_float()?;
+                let position = Vector3::new(x, y, z);
+
+                let mut rest = [0.0; 4];
+                let mut count = 0;
+                while tokens.remainder().is_some() {
+                    *rest.get_mut(count).ok_or(ImportError::InvalidData)? = tokens.next_float()?;
+                    count += 1;
+                }
+                match rest[..count] {
+                    [] => ObjEvent::Vertex(position, 1.0),
+                    [w] if w > 0.0 => ObjEvent::Vertex(position, w),
+                    [_, _, _] | [_, _, _, _] => ObjEvent::ColoredVertex(position),
+                    _ => return Err(ImportError::InvalidData),
+                }
+            }
+            Some(b"vn") => {
+                // vertex normal
+                let x = tokens.next_float()?;
+                let y = tokens.next_float()?;
+                let z = tokens.next_float()?;
+
+                ObjEvent::Normal(Vector3::new(x, y, z))
+            }
+            Some(b"vt") => {
+                // vertex texture (UV)
+                let x = tokens.next_float()?;
+                let y = tokens.next_float()?;
+
+                ObjEvent::Uv(Vector2 { x, y })
+            }
+            Some(b"f") => {
+                // face data
+                let arguments = tokens.remainder().unwrap_or(b"");
+                Self::parse_face(arguments, elements)
+            }
+            Some(_) => return Self::parse_text(&Fs::decode_line(statement)),
+            None => return Ok(None),
+        }))
+    }
+
+    /**
+    `ObjEvent::parse_face`
+    ---
+    Parses the `arguments` of an `f` statement. Corners made of plain
+    digits and slashes are read in one pass; relative indices make a
+    `RelativeFace`, and other syntax goes through `FaceElement::parse_bytes`.
+    */
+    fn parse_face(arguments: &[u8], elements: &mut Vec<FaceElement>) -> Self {
+        elements.clear();
+
+        let mut rest = arguments;
+        while !rest.is_empty() {
+            match FaceElement::read_bytes(rest) {
+                Some((element, end)) => {
+                    elements.push(element);
+                    rest = rest[end..].trim_ascii_start();
+                }
+                None if arguments.contains(&b'-') => {
+                    return ObjEvent::RelativeFace(Fs::decode_line(arguments).into_owned())
+                }
+                None => {
+                    return ObjEvent::Face(Face::new(
+                        ByteTokens::new(arguments)
+                            .map(FaceElement::parse_bytes)
+                            .collect(),
+                    ))
+                }
+            }
+        }
+
+        ObjEvent::Face(Face::new(elements.to_vec()))
+    }
+
+    /// Parses the statements that are handled as text
+    fn parse_text(statement: &str) -> Result<Option<Self>, ImportError> {
+        let mut tokens = statement.split_ascii_whitespace();
+        let cmd = tokens.next().unwrap_or("");
+        let remainder = |tokens: std::str::SplitAsciiWhitespace| {
+            tokens
+                .remainder()
+                .map(|text| text.to_owned())
+                .ok_or(ImportError::InvalidData)
+        };
+
+        Ok(Some(match cmd {
+            "mtllib" => ObjEvent::MaterialLibrary(remainder(tokens)?),
+            "usemtl" => ObjEvent::UseMaterial(remainder(tokens).ok()),
+            "o" => ObjEvent::Object(remainder(tokens)?),
+            "g" => ObjEvent::Group(tokens.map(|name| name.to_owned()).collect()),
+            "vp" => {
+                // parameter space vertex
+                let u = Fs::parse_float(&mut tokens)?;
+                let v = tokens.next().map_or(Ok(0.0), str::parse::<f32>);
+                let w = tokens.next().map_or(Ok(1.0), str::parse::<f32>);
+
+                ObjEvent::Parameter(Vector3::new(
+                    u,
+                    v.or(Err(ImportError::InvalidData))?,
+                    w.or(Err(ImportError::InvalidData))?,
+                ))
+            }
+            "cstype" | "deg" | "bmat" | "step" | "curv" | "curv2" | "surf" | "parm" | "trim"
+            | "hole" | "scrv" | "sp" | "end" | "con" => {
+                let arguments = tokens.remainder().unwrap_or("");
+                ObjEvent::Freeform(cmd.to_owned(), arguments.to_owned())
+            }
+            "s" => {
+                // smoothing group
+                return Ok(None);
+            }
+            "" => return Ok(None),
+            _ => return Err(ImportError::UnrecognisedToken(cmd.to_owned())),
+        }))
+    }
+}
+
+/**
+`obj::ObjReader`
+---
+Streaming reader of `.obj` files over any `BufRead`. Yields the
+statements of the file one `ObjEvent` at a time, in file order,
+without keeping anything but the current statement in memory,
+so that tools which only need to look at the data once never
+have to build the whole `Obj`. `Obj::import` is built on it.
+
+Statements follow the rules of `ByteLines`. Errors are
+wrapped in `ImportError::AtLine`, and end the reading.
+*/
+pub struct ObjReader<R: BufRead> {
+    reader: R,
+
+    // statements that can't be parsed in place in the
+    // read buffer, see `ObjReader.read_event`
+    buffer: Vec<u8>,
+
+    // corners of the last face, reused from face to face
+    elements: Vec<FaceElement>,
+
+    // physical lines read so far
+    lines: usize,
+
+    // line the current statement starts on
+    line: usize,
+
+    // whether a byte order mark may still need to be skipped
+    at_start: bool,
+
+    // set once an error has ended the reading
+    failed: bool,
+}
+
+impl<R: BufRead> ObjReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![],
+            elements: vec![],
+            lines: 0,
+            line: 0,
+            at_start: true,
+            failed: false,
+        }
+    }
+
+    /**
+    `ObjReader::part`
+    ---
+    Creates an `ObjReader` over part of a file, which starts at a statement
+    boundary, so a leading byte order mark isn't skipped
+    */
+    pub(crate) fn part(reader: R) -> Self {
+        Self {
+            at_start: false,
+            ..Self::new(reader)
+        }
+    }
+
+    /// The line the last statement read starts on, counting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Number of lines read so far
+    pub fn lines_read(&self) -> usize {
+        self.lines
+    }
+
+    /// Reads the next event, or `None` at the end of the file
+    pub fn next_event(&mut self) -> Result<Option<ObjEvent>, ImportError> {
+        if self.failed {
+            return Ok(None);
+        }
+
+        self.read_event().map_err(|err| {
+            self.failed = true;
+            ImportError::AtLine(self.line, Box::new(err))
+        })
+    }
+
+    /**
+    `ObjReader.read_event`
+    ---
+    Like `ObjReader.next_event`, without wrapping errors in `ImportError::AtLine`.
+    A statement on a single line without a comment, which is almost every
+    statement of a large file, is parsed in place in the buffer of the
+    reader, in one pass. The others are read line by line into
+    `self.buffer` and go through `ByteLines` first.
+    */
+    pub(crate) fn read_event(&mut self) -> Result<Option<ObjEvent>, ImportError> {
+        loop {
+            self.line = self.lines + 1;
+            let available = match self.at_start {
+                true => &[][..],
+                false => self.reader.fill_buf().or(Err(ImportError::InvalidData))?,
+            };
+
+            let event = match Self::single_line(available) {
+                Some((statement, end)) => {
+                    let event = ObjEvent::parse_with(statement, &mut self.elements);
+                    self.reader.consume(end);
+                    self.lines += 1;
+                    event?
+                }
+                None => {
+                    if !self.read_statement()? {
+                        return Ok(None);
+                    }
+                    let statement = ByteLines::part(&self.buffer)
+                        .next()
+                        .map(|(_, statement)| statement)
+                        .unwrap_or_default();
+                    ObjEvent::parse_with(&statement, &mut self.elements)?
+                }
+            };
+
+            if let Some(event) = event {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    /**
+    `ObjReader::single_line`
+    ---
+    Returns the first line of `bytes` without its line ending, and the
+    number of bytes up to the start of the next line, if it is a whole
+    statement: complete, without a `#` and not continued on the next line
+    */
+    fn single_line(bytes: &[u8]) -> Option<(&[u8], usize)> {
+        let end = ByteLines::find_line_end(bytes)?;
+        if bytes[end] != b'\n' {
+            return None;
+        }
+
+        let line = &bytes[..end];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        match line.trim_ascii_end().ends_with(b"\\") {
+            true => None,
+            false => Some((line, end + 1)),
+        }
+    }
+
+    /**
+    `ObjReader.read_statement`
+    ---
+    Reads the lines of the next statement into `self.buffer`,
+    returning `false` at the end of the file
+    */
+    fn read_statement(&mut self) -> Result<bool, ImportError> {
+        self.buffer.clear();
+        self.line = self.lines + 1;
+
+        loop {
+            let read = self
+                .reader
+                .read_until(b'\n', &mut self.buffer)
+                .or(Err(ImportError::InvalidData))?;
+            if read == 0 {
+                return Ok(!self.buffer.is_empty());
+            }
+            self.lines += 1;
+
+            if self.at_start {
+                if self.buffer.starts_with(b"\xEF\xBB\xBF") {
+                    self.buffer.drain(..3);
+                }
+                self.at_start = false;
+            }
+
+            if !ByteLines::continues(&self.buffer) {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ObjReader<R> {
+    type Item = Result<ObjEvent, ImportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::freeform::ElementCounts;
+    use crate::obj_mesh::ObjMesh;
+    use crate::{Obj, Resource};
+
+    fn vertex(statement: &str) -> Result<Option<ObjEvent>, ImportError> {
+        ObjEvent::parse(statement.as_bytes())
+    }
+
+    #[test]
+    fn reads_the_weight_of_four_number_vertices() {
+        match vertex("v 1 2 3") {
+            Ok(Some(ObjEvent::Vertex(position, w))) => {
+                assert_eq!(
+                    [position.x, position.y, position.z, w],
+                    [1.0, 2.0, 3.0, 1.0]
+                )
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match vertex("v 1 2 3 0.5") {
+            Ok(Some(ObjEvent::Vertex(_, w))) => assert_eq!(w, 0.5),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_weights_that_are_not_positive() {
+        assert!(vertex("v 1 2 3 0").is_err());
+        assert!(vertex("v 1 2 3 -1").is_err());
+    }
+
+    #[test]
+    fn rejects_comments_without_leading_whitespace() {
+        let mut reader = ObjReader::new(&b"v 1 2 3 #note\nv 1 2 3#note\n"[..]);
+        assert!(matches!(
+            reader.next_event(),
+            Ok(Some(ObjEvent::Vertex(..)))
+        ));
+        assert!(matches!(
+            reader.next_event(),
+            Err(ImportError::AtLine(2, _))
+        ));
+    }
+
+    #[test]
+    fn reads_vertex_colors_as_colored_vertices() {
+        for statement in ["v 1 2 3 0.5 0.25 1", "v 1 2 3 0.5 0.25 1 0.8"] {
+            match vertex(statement) {
+                Ok(Some(ObjEvent::ColoredVertex(position))) => {
+                    assert_eq!([position.x, position.y, position.z], [1.0, 2.0, 3.0])
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_other_vertex_lengths() {
+        assert!(vertex("v 1 2").is_err());
+        assert!(vertex("v 1 2 3 4 5").is_err());
+        assert!(vertex("v 1 2 3 4 5 6 7 8").is_err());
+    }
+
+    #[test]
+    fn leaves_relative_faces_to_the_builder() {
+        match ObjEvent::parse(b"f -3/-2/-1 -2/-1/-1 -1//-1") {
+            Ok(Some(ObjEvent::RelativeFace(arguments))) => {
+                assert_eq!(arguments, "-3/-2/-1 -2/-1/-1 -1//-1")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let counts = ElementCounts {
+            positions: 10,
+            uvs: 4,
+            normals: 2,
+            parameters: 0,
+        };
+        let element = FaceElement::resolve("-3/-2/-1", counts).unwrap();
+        assert_eq!(element, FaceElement::new(8, 2, 3));
+        assert_eq!(
+            FaceElement::resolve("-1//-2", counts).unwrap(),
+            FaceElement::new(10, 1, 0)
+        );
+        assert!(FaceElement::resolve("-11", counts).is_err());
+    }
+
+    fn events(bytes: &[u8]) -> Vec<ObjEvent> {
+        ObjReader::new(bytes).collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn skips_a_byte_order_mark_at_the_start_only() {
+        match &events(b"\xEF\xBB\xBFo part\n")[..] {
+            [ObjEvent::Object(name)] => assert_eq!(name, "part"),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let mut reader = ObjReader::new(&b"o part\n\xEF\xBB\xBFo other\n"[..]);
+        assert!(reader.next_event().is_ok());
+        assert!(reader.next_event().is_err());
+    }
+
+    #[test]
+    fn joins_continued_lines_ending_with_crlf() {
+        match &events(b"f 1 2 \\\r\n  3 4\r\no next\r\n")[..] {
+            [ObjEvent::Face(face), ObjEvent::Object(name)] => {
+                let positions: Vec<u32> = face
+                    .elements()
+                    .iter()
+                    .map(|element| element.position_index())
+                    .collect();
+                assert_eq!(positions, vec![1, 2, 3, 4]);
+                assert_eq!(name, "next");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_latin1_names() {
+        match &events(b"o caf\xE9\nusemtl caf\xC3\xA9\n")[..] {
+            [ObjEvent::Object(latin1), ObjEvent::UseMaterial(Some(utf8))] => {
+                assert_eq!(latin1, "café");
+                assert_eq!(utf8, "café");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_the_physical_line_after_a_continued_statement() {
+        let text = b"o a\nf 1 \\\n2 \\\n3\nv 1 2 x\n";
+        let mut reader = ObjReader::new(&text[..]);
+        assert!(matches!(reader.next_event(), Ok(Some(ObjEvent::Object(_)))));
+        assert!(matches!(reader.next_event(), Ok(Some(ObjEvent::Face(_)))));
+        assert_eq!(reader.line(), 2);
+
+        match reader.next_event() {
+            Err(ImportError::AtLine(line, err)) => {
+                assert_eq!(line, 5);
+                assert!(matches!(*err, ImportError::InvalidData));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(reader.next_event(), Ok(None)));
+    }
+
+    #[test]
+    fn reads_statements_split_across_buffer_refills_alike() {
+        let text = "# header\no part\nv 1.5 -2 3e2\nvt 0.25 1\nvn 0 0 1\nf 1/1/1 2/2/1 \\\n 3/3/1\r\nf -3 -2 -1 # relative\ng a b\nf 1 2 3";
+        let whole: Vec<String> = ObjReader::new(text.as_bytes())
+            .map(|event| format!("{:?}", event.unwrap()))
+            .collect();
+        assert_eq!(whole.len(), 8);
+
+        for capacity in [1, 2, 5, 16] {
+            let reader = std::io::BufReader::with_capacity(capacity, text.as_bytes());
+            let split: Vec<String> = ObjReader::new(reader)
+                .map(|event| format!("{:?}", event.unwrap()))
+                .collect();
+            assert_eq!(whole, split, "capacity {}", capacity);
+        }
+    }
+
+    #[test]
+    fn resolves_relative_faces_against_the_whole_file() {
+        let path = std::env::temp_dir().join("suoi_simp_relative_faces.obj");
+        let text = "o first\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf -4 -3 -2\no second\nv 0 0 1\nv 1 0 1\nv 0 1 1\nf -3 -2 -1\n";
+        std::fs::write(&path, text).unwrap();
+        let obj = Obj::import(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let positions = |mesh: &ObjMesh| -> Vec<u32> {
+            mesh.faces()[0]
+                .elements()
+                .iter()
+                .map(|element| element.position_index())
+                .collect()
+        };
+        let obj = obj.unwrap();
+        assert_eq!(positions(&obj.meshes()[0]), vec![1, 2, 3]);
+        assert_eq!(positions(&obj.meshes()[1]), vec![5, 6, 7]);
+    }
+}
@@ -0,0 +1,515 @@
+use std::io::{BufWriter, Write};
+
+use crate::face::{FaceElement, MaterialSpan};
+use crate::freeform::{FreeformAttributes, TrimCurve};
+use crate::obj_mesh::ObjMesh;
+use crate::{Export, FloatFormat, Fs, ImportError, Mtl, Obj, Path, VertexAttribute};
+
+/**
+`obj::IndexOffset`
+---
+Running counts of positions, uvs and normals
+already written to the output, used to rebase
+the face indices of each mesh
+*/
+#[derive(Debug, Clone, Copy, Default)]
+struct IndexOffset {
+    position: i64,
+    uv: i64,
+    normal: i64,
+}
+
+impl Obj {
+    /**
+    `Obj.write`
+    ---
+    Serializes `self` as `.obj` text into `out`.
+    The output contains the `mtllib` statements, then every mesh
+    with its positions, uvs, normals, groups, material and faces,
+    and can be read back with `Obj::import`.
+    */
+    pub fn write<W: Write>(&self, out: &mut W, format: FloatFormat) -> std::io::Result<()> {
+        for library in self.material_libraries() {
+            writeln!(out, "mtllib {}", library)?;
+        }
+
+        let mut offset = IndexOffset::default();
+        for mesh in self.meshes() {
+            mesh.write_with_offset(out, self.materials(), format, offset)?;
+
+            offset.position += mesh.positions().len() as i64;
+            offset.uv += mesh.uvs().len() as i64;
+            offset.normal += mesh.normals().len() as i64;
+        }
+
+        Ok(())
+    }
+}
+
+impl Export for Obj {
+    /**
+    `Obj::export`
+    ---
+    Writes `self` to the `.obj` file located at `path`
+    */
+    fn export(&self, path: &Path, format: FloatFormat) -> Result<(), ImportError> {
+        let mut out = BufWriter::new(Fs::create_file(path)?);
+        self.write(&mut out, format)
+            .and_then(|_| out.flush())
+            .or(Err(ImportError::WriteFailed(path.to_owned())))
+    }
+}
+
+impl ObjMesh {
+    /**
+    `ObjMesh.write`
+    ---
+    Serializes `self` as a standalone `.obj` object into `out`,
+    with face indices counting from the start of the mesh.
+    Material names are looked up in `mtl`, the table
+    the mesh's material handles refer to.
+    */
+    pub fn write<W: Write>(
+        &self,
+        out: &mut W,
+        mtl: &Mtl,
+        format: FloatFormat,
+    ) -> std::io::Result<()> {
+        self.write_with_offset(out, mtl, format, IndexOffset::default())
+    }
+
+    fn write_with_offset<W: Write>(
+        &self,
+        out: &mut W,
+        mtl: &Mtl,
+        format: FloatFormat,
+        offset: IndexOffset,
+    ) -> std::io::Result<()> {
+        let float = |value: f32| Fs::format_float(value, format);
+
+        writeln!(out, "o {}", self.get_name())?;
+
+        for (index, position) in self.positions().iter().enumerate() {
+            write!(
+                out,
+                "v {} {} {}",
+                float(position.x),
+                float(position.y),
+                float(position.z)
+            )?;
+            match self.weight(index) {
+                1.0 => writeln!(out)?,
+                weight => writeln!(out, " {}", float(weight))?,
+            }
+        }
+
+        for uv in self.uvs() {
+            writeln!(out, "vt {} {}", float(uv.x), float(uv.y))?;
+        }
+
+        for normal in self.normals() {
+            writeln!(
+                out,
+                "vn {} {} {}",
+                float(normal.x),
+                float(normal.y),
+                float(normal.z)
+            )?;
+        }
+
+        for parameter in self.parameters() {
+            writeln!(
+                out,
+                "vp {} {} {}",
+                float(parameter.x),
+                float(parameter.y),
+                float(parameter.z)
+            )?;
+        }
+
+        // a `usemtl` without a name clears the material
+        let use_material =
+            |span: &MaterialSpan| match span.material().and_then(|material| mtl.get(material)) {
+                Some(mat) => format!("usemtl {}", mat.get_name()),
+                None => "usemtl".to_owned(),
+            };
+
+        let mut groups = self.groups().iter().peekable();
+        let mut materials = self.material_spans().iter().peekable();
+        for (index, face) in self.faces().iter().enumerate() {
+            while let Some(group) = groups.next_if(|group| group.first_face() == index) {
+                writeln!(out, "g {}", group.names().join(" "))?;
+            }
+            while let Some(span) = materials.next_if(|span| span.first_face() == index) {
+                writeln!(out, "{}", use_material(span))?;
+            }
+
+            write!(out, "f")?;
+            for element in face.elements() {
+                write!(out, " {}", self.element_to_string(element, offset)?)?;
+            }
+            writeln!(out)?;
+        }
+
+        // groups and materials declared after the last face
+        for group in groups {
+            writeln!(out, "g {}", group.names().join(" "))?;
+        }
+        for span in materials {
+            writeln!(out, "{}", use_material(span))?;
+        }
+
+        self.write_freeform(out, format, offset)
+    }
+
+    /**
+    `ObjMesh.write_freeform`
+    ---
+    Writes the curves, surfaces and connections of `self`.
+    Position indices are rebased like face indices, `vp`, `curv2`
+    and surface indices are written as they were read.
+    */
+    fn write_freeform<W: Write>(
+        &self,
+        out: &mut W,
+        format: FloatFormat,
+        offset: IndexOffset,
+    ) -> std::io::Result<()> {
+        let float = |value: f32| Fs::format_float(value, format);
+        let floats = |values: &[f32]| {
+            values
+                .iter()
+                .map(|&value| float(value))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let indices = |values: &[u32]| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let trim = |curves: &[TrimCurve]| {
+            curves
+                .iter()
+                .map(|curve| {
+                    format!(
+                        "{} {} {}",
+                        float(curve.start()),
+                        float(curve.end()),
+                        curve.curve2_index()
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+
+        for curve in self.curves() {
+            Self::write_attributes(out, curve.attributes(), format)?;
+            let control_points: Vec<u32> = curve
+                .control_points()
+                .iter()
+                .map(|&index| (index as i64 - self.position_base() as i64 + offset.position) as u32)
+                .collect();
+            writeln!(
+                out,
+                "curv {} {} {}",
+                float(curve.start()),
+                float(curve.end()),
+                indices(&control_points)
+            )?;
+            if !curve.knots().is_empty() {
+                writeln!(out, "parm u {}", floats(curve.knots()))?;
+            }
+            if !curve.special_points().is_empty() {
+                writeln!(out, "sp {}", indices(curve.special_points()))?;
+            }
+            writeln!(out, "end")?;
+        }
+
+        for curve in self.curves2() {
+            Self::write_attributes(out, curve.attributes(), format)?;
+            writeln!(out, "curv2 {}", indices(curve.control_points()))?;
+            if !curve.knots().is_empty() {
+                writeln!(out, "parm u {}", floats(curve.knots()))?;
+            }
+            if !curve.special_points().is_empty() {
+                writeln!(out, "sp {}", indices(curve.special_points()))?;
+            }
+            writeln!(out, "end")?;
+        }
+
+        for surface in self.surfaces() {
+            Self::write_attributes(out, surface.attributes(), format)?;
+            let (start_u, end_u) = surface.range_u();
+            let (start_v, end_v) = surface.range_v();
+            write!(
+                out,
+                "surf {} {} {} {}",
+                float(start_u),
+                float(end_u),
+                float(start_v),
+                float(end_v)
+            )?;
+            for element in surface.control_points() {
+                write!(out, " {}", self.element_to_string(element, offset)?)?;
+            }
+            writeln!(out)?;
+
+            if !surface.knots_u().is_empty() {
+                writeln!(out, "parm u {}", floats(surface.knots_u()))?;
+            }
+            if !surface.knots_v().is_empty() {
+                writeln!(out, "parm v {}", floats(surface.knots_v()))?;
+            }
+            for curves in surface.trims() {
+                writeln!(out, "trim {}", trim(curves))?;
+            }
+            for curves in surface.holes() {
+                writeln!(out, "hole {}", trim(curves))?;
+            }
+            for curves in surface.special_curves() {
+                writeln!(out, "scrv {}", trim(curves))?;
+            }
+            if !surface.special_points().is_empty() {
+                writeln!(out, "sp {}", indices(surface.special_points()))?;
+            }
+            writeln!(out, "end")?;
+        }
+
+        for connection in self.connections() {
+            let [surf_1, surf_2] = connection.surfaces();
+            let [curve_1, curve_2] = connection.curves();
+            writeln!(
+                out,
+                "con {} {} {} {}",
+                surf_1,
+                trim(&[curve_1]),
+                surf_2,
+                trim(&[curve_2])
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the `cstype`, `deg`, `bmat` and `step` state of a free-form element
+    fn write_attributes<W: Write>(
+        out: &mut W,
+        attributes: &FreeformAttributes,
+        format: FloatFormat,
+    ) -> std::io::Result<()> {
+        let float = |value: f32| Fs::format_float(value, format);
+        let floats = |values: &[f32]| {
+            values
+                .iter()
+                .map(|&value| float(value))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+
+        match attributes.rational() {
+            true => writeln!(out, "cstype rat {}", attributes.curve_type().token())?,
+            false => writeln!(out, "cstype {}", attributes.curve_type().token())?,
+        }
+        match attributes.degree_u() == attributes.degree_v() {
+            true => writeln!(out, "deg {}", attributes.degree_u())?,
+            false => writeln!(
+                out,
+                "deg {} {}",
+                attributes.degree_u(),
+                attributes.degree_v()
+            )?,
+        }
+        if !attributes.basis_u().is_empty() {
+            writeln!(out, "bmat u {}", floats(attributes.basis_u()))?;
+        }
+        if !attributes.basis_v().is_empty() {
+            writeln!(out, "bmat v {}", floats(attributes.basis_v()))?;
+        }
+        match (attributes.step_u(), attributes.step_v()) {
+            (Some(step_u), Some(step_v)) => {
+                writeln!(out, "step {} {}", float(step_u), float(step_v))?
+            }
+            (Some(step_u), None) => writeln!(out, "step {}", float(step_u))?,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /**
+    `ObjMesh.element_to_string`
+    ---
+    Formats a `FaceElement` as `p`, `p/t`, `p//n` or `p/t/n`,
+    rebasing each index from this mesh's index base onto `offset`.
+    A zero index marks a missing uv or normal and is omitted.
+    Fails with `InvalidData` when an index lies outside the buffers of `self`,
+    since it could not be written as an index of the same element.
+    */
+    fn element_to_string(
+        &self,
+        element: &FaceElement,
+        offset: IndexOffset,
+    ) -> std::io::Result<String> {
+        let rebase = |attribute: VertexAttribute, index: u32, offset: i64| match self
+            .local_index(attribute, index)
+        {
+            Some(local) => Ok(local as i64 + 1 + offset),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{:?} index {} is out of range", attribute, index),
+            )),
+        };
+
+        let position = rebase(
+            VertexAttribute::Position,
+            element.position_index(),
+            offset.position,
+        )?;
+        let uv = match element.uv_index() {
+            0 => None,
+            index => Some(rebase(VertexAttribute::Uv, index, offset.uv)?),
+        };
+        let normal = match element.normal_index() {
+            0 => None,
+            index => Some(rebase(VertexAttribute::Normal, index, offset.normal)?),
+        };
+
+        Ok(match (uv, normal) {
+            (None, None) => format!("{}", position),
+            (Some(uv), None) => format!("{}/{}", position, uv),
+            (None, Some(normal)) => format!("{}//{}", position, normal),
+            (Some(uv), Some(normal)) => format!("{}/{}/{}", position, uv, normal),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::Face;
+    use crate::{ImportWarning, MtlMaterial, Resource};
+    use suoi_types::Vector3;
+
+    const LIBRARY: &str = "\
+newmtl red
+Kd 1 0 0
+map_Kd -s 2 2 1 -blendu off red.png
+newmtl blue
+Kd 0 0 1
+";
+
+    const OBJECT: &str = "\
+mtllib round_trip.mtl
+o first
+v 0 0 0
+v 1 0 0
+v 1 1 0 0.5
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+g top side
+usemtl red
+f 1/1/1 2/2/1 3/3/1
+f 1//1 3//1 4//1
+g bottom
+usemtl blue
+f 4 3 2
+o second
+v 0 0 1
+v 1 0 1
+v 0 1 1
+g top
+usemtl red
+f -3 -2 -1
+";
+
+    #[test]
+    fn round_trips_through_the_writer() {
+        let dir = std::env::temp_dir().join("suoi_simp_obj_round_trip");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("round_trip.mtl"), LIBRARY).unwrap();
+        std::fs::write(dir.join("source.obj"), OBJECT).unwrap();
+
+        let first = Obj::import(&dir.join("source.obj")).unwrap();
+        first
+            .export(&dir.join("copy.obj"), FloatFormat::Shortest)
+            .unwrap();
+        let second = Obj::import(&dir.join("copy.obj")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(first.warnings().is_empty());
+        assert_eq!(first.meshes().len(), 2);
+        assert_eq!(first.meshes(), second.meshes());
+        assert_eq!(
+            first.materials().materials(),
+            second.materials().materials()
+        );
+    }
+
+    #[test]
+    fn keeps_the_names_of_unknown_materials() {
+        let dir = std::env::temp_dir().join("suoi_simp_obj_unknown_material");
+        std::fs::create_dir_all(&dir).unwrap();
+        let text = "o quad\nv 0 0 0\nv 1 0 0\nv 1 1 0\nusemtl lost\nf 1 2 3\nusemtl other\nf 3 2 1\nusemtl lost\nf 1 3 2\n";
+        std::fs::write(dir.join("unknown.obj"), text).unwrap();
+
+        let obj = Obj::import(&dir.join("unknown.obj")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            obj.warnings(),
+            &vec![
+                ImportWarning::UnknownMaterial("lost".to_owned()),
+                ImportWarning::UnknownMaterial("other".to_owned()),
+            ]
+        );
+        let lost = obj.materials().get_material("lost").unwrap();
+        assert_eq!(
+            lost.get_diffuse_color().x,
+            MtlMaterial::fallback().get_diffuse_color().x
+        );
+
+        let mut out = vec![];
+        obj.write(&mut out, FloatFormat::Shortest).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        let used: Vec<&str> = written
+            .lines()
+            .filter(|line| line.starts_with("usemtl"))
+            .collect();
+        assert_eq!(used, vec!["usemtl lost", "usemtl other", "usemtl lost"]);
+    }
+
+    #[test]
+    fn rejects_faces_with_indices_outside_the_mesh() {
+        let mut mesh = ObjMesh::empty();
+        for position in [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            mesh.load_position(Vector3::new(position[0], position[1], position[2]));
+        }
+        mesh.load_face(Face::new(vec![
+            FaceElement::new(0, 0, 0),
+            FaceElement::new(2, 0, 0),
+            FaceElement::new(3, 0, 0),
+        ]));
+
+        let mut out = vec![];
+        let error = mesh
+            .write(&mut out, &Mtl::empty(), FloatFormat::Shortest)
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(!String::from_utf8(out).unwrap().contains("f 0"));
+
+        mesh.faces_mut()[0] = Face::new(vec![
+            FaceElement::new(1, 0, 0),
+            FaceElement::new(2, 0, 0),
+            FaceElement::new(4, 0, 0),
+        ]);
+        assert!(mesh
+            .write(&mut vec![], &Mtl::empty(), FloatFormat::Shortest)
+            .is_err());
+    }
+}
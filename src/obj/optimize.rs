@@ -0,0 +1,493 @@
+use crate::indexed::IndexedMesh;
+use crate::math::{self, Vec3};
+
+/// Size of the LRU cache modelled by `IndexedMesh.optimize_vertex_cache`
+const SCORE_CACHE_SIZE: usize = 32;
+
+/// Size of the FIFO cache simulated to split clusters in `IndexedMesh.optimize_overdraw`
+const CLUSTER_CACHE_SIZE: usize = 16;
+
+/**
+`obj::VertexCacheStats`
+---
+Efficiency of an index order on a FIFO post-transform cache:
+`acmr` is the average number of vertices transformed per triangle,
+between `0.5` at best on large regular meshes and `3` at worst, and
+`atvr` the average number of times each vertex is transformed, `1`
+at best
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VertexCacheStats {
+    transformed: usize,
+    acmr: f32,
+    atvr: f32,
+}
+
+impl VertexCacheStats {
+    /// Immutable getter for `transformed` of `VertexCacheStats`
+    pub fn transformed(&self) -> usize {
+        self.transformed
+    }
+
+    /// Immutable getter for `acmr` of `VertexCacheStats`
+    pub fn acmr(&self) -> f32 {
+        self.acmr
+    }
+
+    /// Immutable getter for `atvr` of `VertexCacheStats`
+    pub fn atvr(&self) -> f32 {
+        self.atvr
+    }
+}
+
+impl IndexedMesh {
+    /**
+    `IndexedMesh.cache_stats`
+    ---
+    Simulates a FIFO post-transform cache holding `cache_size` vertices
+    over the indices of `self`, and measures the vertices transformed
+    */
+    pub fn cache_stats(&self, cache_size: usize) -> VertexCacheStats {
+        let mut timestamps = vec![0usize; self.vertex_count()];
+        let mut used = vec![false; self.vertex_count()];
+        let mut time = cache_size + 1;
+        let mut transformed = 0;
+
+        for &index in self.indices() {
+            let index = index as usize;
+            used[index] = true;
+            if time - timestamps[index] > cache_size {
+                timestamps[index] = time;
+                time += 1;
+                transformed += 1;
+            }
+        }
+
+        let triangles = self.indices().len() / 3;
+        let vertices = used.iter().filter(|&&used| used).count();
+        VertexCacheStats {
+            transformed,
+            acmr: transformed as f32 / triangles.max(1) as f32,
+            atvr: transformed as f32 / vertices.max(1) as f32,
+        }
+    }
+
+    /**
+    `IndexedMesh.optimize_vertex_cache`
+    ---
+    Reorders the triangles of each part to reuse the vertices still in the
+    post-transform cache, with Tom Forsyth's linear-speed algorithm. The
+    order works well on any cache size up to 32 vertices.
+    */
+    pub fn optimize_vertex_cache(&mut self) {
+        let vertex_count = self.vertex_count();
+        for indices in self.part_indices_mut() {
+            let optimized = forsyth(indices, vertex_count);
+            indices.copy_from_slice(&optimized);
+        }
+    }
+
+    /**
+    `IndexedMesh.optimize_overdraw`
+    ---
+    Reorders clusters of triangles so that those facing outwards from the
+    centre of each part are drawn first and hide what lies behind them.
+    Clusters are cut from the current order, which should already suit
+    the vertex cache, where that makes the cache miss rate at most
+    `threshold` times worse: `1.05` trades a little cache efficiency for
+    less overdraw, and `1` keeps the cache order nearly intact.
+    */
+    pub fn optimize_overdraw(&mut self, threshold: f32) {
+        let positions: Vec<Vec3> = self.positions().iter().map(math::from_vector).collect();
+        for indices in self.part_indices_mut() {
+            let optimized = sort_clusters(indices, &positions, threshold);
+            indices.copy_from_slice(&optimized);
+        }
+    }
+
+    /**
+    `IndexedMesh.optimize_vertex_fetch`
+    ---
+    Renumbers the vertices in the order the indices first use them, so that
+    the vertex data is read front to back, and drops unused vertices. Run it
+    after the passes that reorder triangles.
+    */
+    pub fn optimize_vertex_fetch(&mut self) {
+        let mut map: Vec<Option<u32>> = vec![None; self.vertex_count()];
+        let mut count = 0;
+        for &index in self.indices() {
+            map[index as usize].get_or_insert_with(|| {
+                count += 1;
+                count - 1
+            });
+        }
+
+        self.reorder_vertices(&map);
+    }
+}
+
+/// Forsyth score of a vertex at `cache_position` in the LRU cache, used by `remaining` triangles
+fn vertex_score(cache_position: Option<usize>, remaining: u32) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+
+    let cache = match cache_position {
+        None => 0.0,
+        // the last triangle's vertices score the same, so that no direction is favoured
+        Some(position) if position < 3 => 0.75,
+        Some(position) => (1.0 - (position - 3) as f32 / (SCORE_CACHE_SIZE - 3) as f32).powf(1.5),
+    };
+
+    // vertices with few triangles left are worth finishing off
+    cache + 2.0 * (remaining as f32).powf(-0.5)
+}
+
+/**
+`optimize::forsyth`
+---
+Greedily emits the triangle with the highest score among those using
+the vertices in a modelled LRU cache, updating the scores as vertices
+move through the cache
+*/
+fn forsyth(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangles = indices.len() / 3;
+
+    // triangles of each vertex, as ranges of `vertex_triangles`
+    let (offsets, vertex_triangles) = IndexedMesh::vertex_triangles(indices, vertex_count);
+
+    let mut remaining: Vec<u32> = (0..vertex_count)
+        .map(|v| (offsets[v + 1] - offsets[v]) as u32)
+        .collect();
+    let mut scores: Vec<f32> = remaining
+        .iter()
+        .map(|&remaining| vertex_score(None, remaining))
+        .collect();
+    let triangle_score = |scores: &[f32], t: usize| {
+        indices[t * 3..t * 3 + 3]
+            .iter()
+            .map(|&v| scores[v as usize])
+            .sum()
+    };
+    let mut triangle_scores: Vec<f32> =
+        (0..triangles).map(|t| triangle_score(&scores, t)).collect();
+
+    let mut emitted = vec![false; triangles];
+    let mut cache: Vec<u32> = vec![];
+    let mut output = Vec::with_capacity(triangles * 3);
+    let mut best =
+        (0..triangles).max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+    let mut cursor = 0;
+
+    for _ in 0..triangles {
+        let t = match best {
+            Some(t) => t,
+            None => {
+                while emitted[cursor] {
+                    cursor += 1;
+                }
+                cursor
+            }
+        };
+        let triangle = &indices[t * 3..t * 3 + 3];
+        emitted[t] = true;
+        output.extend_from_slice(triangle);
+
+        for &v in triangle {
+            remaining[v as usize] -= 1;
+        }
+        let mut updated = triangle.to_vec();
+        updated.extend(cache.iter().filter(|v| !triangle.contains(v)));
+        let evicted = updated.split_off(updated.len().min(SCORE_CACHE_SIZE));
+        cache = updated;
+
+        for (position, &v) in cache.iter().enumerate() {
+            scores[v as usize] = vertex_score(Some(position), remaining[v as usize]);
+        }
+        for &v in &evicted {
+            scores[v as usize] = vertex_score(None, remaining[v as usize]);
+        }
+
+        // only triangles around the cache compete for the next pick
+        best = None;
+        let mut best_score = f32::MIN;
+        for (in_cache, &v) in cache
+            .iter()
+            .map(|v| (true, v))
+            .chain(evicted.iter().map(|v| (false, v)))
+        {
+            let v = v as usize;
+            for &t in &vertex_triangles[offsets[v]..offsets[v + 1]] {
+                if emitted[t] {
+                    continue;
+                }
+                triangle_scores[t] = triangle_score(&scores, t);
+                if in_cache && triangle_scores[t] > best_score {
+                    best_score = triangle_scores[t];
+                    best = Some(t);
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/**
+`optimize::cluster_bounds`
+---
+Splits `indices` into clusters, returning the first triangle of each.
+A cluster starts wherever the simulated FIFO cache misses all three
+vertices of a triangle, and is cut further wherever the miss rate
+since its start stays within `threshold` of the whole cluster's.
+*/
+fn cluster_bounds(indices: &[u32], vertex_count: usize, threshold: f32) -> Vec<usize> {
+    let triangles = indices.len() / 3;
+    let mut timestamps = vec![0usize; vertex_count];
+    let mut time = CLUSTER_CACHE_SIZE + 1;
+    let misses = |corners: &[u32], time: &mut usize, timestamps: &mut Vec<usize>| {
+        let mut count = 0;
+        for &v in corners {
+            if *time - timestamps[v as usize] > CLUSTER_CACHE_SIZE {
+                timestamps[v as usize] = *time;
+                *time += 1;
+                count += 1;
+            }
+        }
+        count
+    };
+
+    let mut hard = vec![0];
+    let mut hard_misses = vec![];
+    let mut cluster_misses = 0;
+    for t in 0..triangles {
+        let count = misses(&indices[t * 3..t * 3 + 3], &mut time, &mut timestamps);
+        if count == 3 && t > 0 {
+            hard.push(t);
+            hard_misses.push(cluster_misses);
+            cluster_misses = 0;
+        }
+        cluster_misses += count;
+    }
+    hard_misses.push(cluster_misses);
+
+    let mut bounds = vec![];
+    for (i, &start) in hard.iter().enumerate() {
+        let end = hard.get(i + 1).copied().unwrap_or(triangles);
+        let limit = threshold * hard_misses[i] as f32 / (end - start) as f32;
+        bounds.push(start);
+
+        // a cut flushes the cache, so each soft cluster is simulated from scratch
+        time += CLUSTER_CACHE_SIZE + 1;
+        let mut first = start;
+        let mut soft_misses = 0;
+        for t in start..end {
+            soft_misses += misses(&indices[t * 3..t * 3 + 3], &mut time, &mut timestamps);
+            if t + 1 < end && soft_misses as f32 / (t + 1 - first) as f32 <= limit {
+                bounds.push(t + 1);
+                first = t + 1;
+                soft_misses = 0;
+                time += CLUSTER_CACHE_SIZE + 1;
+            }
+        }
+    }
+
+    bounds
+}
+
+/**
+`optimize::sort_clusters`
+---
+Sorts the clusters of `indices` by how far their area weighted centre
+lies along their average normal, measured from the centre of them all
+*/
+fn sort_clusters(indices: &[u32], positions: &[Vec3], threshold: f32) -> Vec<u32> {
+    let triangles = indices.len() / 3;
+    if triangles == 0 {
+        return indices.to_vec();
+    }
+
+    let bounds = cluster_bounds(indices, positions.len(), threshold);
+    let mut clusters = vec![];
+    let mut centre = [0.0; 3];
+    let mut total_area = 0.0;
+
+    for (i, &start) in bounds.iter().enumerate() {
+        let end = bounds.get(i + 1).copied().unwrap_or(triangles);
+        let mut normal = [0.0; 3];
+        let mut centroid = [0.0; 3];
+        let mut area = 0.0;
+
+        for t in start..end {
+            let [a, b, c] = [0, 1, 2].map(|k| positions[indices[t * 3 + k] as usize]);
+            let cross = math::cross(math::sub(b, a), math::sub(c, a));
+            let weight = math::length(cross);
+            normal = math::add(normal, cross);
+            centroid = math::add(
+                centroid,
+                math::scale(math::add(math::add(a, b), c), weight / 3.0),
+            );
+            area += weight;
+        }
+
+        centre = math::add(centre, centroid);
+        total_area += area;
+        clusters.push((start, end, normal, centroid, area));
+    }
+    let centre = match total_area > 0.0 {
+        true => math::scale(centre, 1.0 / total_area),
+        false => centre,
+    };
+
+    let mut keyed: Vec<(f32, usize, usize)> = clusters
+        .into_iter()
+        .map(|(start, end, normal, centroid, area)| {
+            let centroid = match area > 0.0 {
+                true => math::scale(centroid, 1.0 / area),
+                false => centroid,
+            };
+            let key = math::normalize(normal)
+                .map_or(0.0, |normal| math::dot(math::sub(centroid, centre), normal));
+            (key, start, end)
+        })
+        .collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    keyed
+        .into_iter()
+        .flat_map(|(_, start, end)| indices[start * 3..end * 3].iter().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use suoi_types::Vector3;
+
+    use super::*;
+    use crate::face::{Face, FaceElement};
+    use crate::obj_mesh::ObjMesh;
+    use crate::{Mtl, MtlMaterial};
+
+    /// A UV sphere of quads, the northern and southern halves in different materials
+    fn sphere(rings: u32, segments: u32) -> IndexedMesh {
+        let mut mtl = Mtl::empty();
+        let materials = [
+            mtl.add_material(MtlMaterial::empty()),
+            mtl.add_material(MtlMaterial::empty()),
+        ];
+
+        let mut mesh = ObjMesh::empty();
+        for ring in 0..=rings {
+            let theta = std::f32::consts::PI * ring as f32 / rings as f32;
+            for segment in 0..segments {
+                let phi = std::f32::consts::TAU * segment as f32 / segments as f32;
+                mesh.load_position(Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ));
+            }
+        }
+        for ring in 0..rings {
+            if ring == 0 || ring == rings / 2 {
+                mesh.set_material(materials[(ring != 0) as usize]);
+            }
+            for segment in 0..segments {
+                let corner =
+                    |r: u32, s: u32| FaceElement::new(r * segments + s % segments + 1, 0, 0);
+                mesh.load_face(Face::new(vec![
+                    corner(ring, segment),
+                    corner(ring, segment + 1),
+                    corner(ring + 1, segment + 1),
+                    corner(ring + 1, segment),
+                ]));
+            }
+        }
+        mesh.to_indexed()
+    }
+
+    /// The triangles of each part as corner positions, each rotated
+    /// to start at its lowest corner to keep the winding comparable
+    fn triangles(mesh: &IndexedMesh) -> Vec<Vec<[[u32; 3]; 3]>> {
+        mesh.parts()
+            .iter()
+            .map(|part| {
+                let indices = &mesh.indices()[part.first_index()..][..part.index_count()];
+                let mut triangles: Vec<[[u32; 3]; 3]> = indices
+                    .chunks(3)
+                    .map(|triangle| {
+                        let corners = [triangle[0], triangle[1], triangle[2]].map(|index| {
+                            let position = &mesh.positions()[index as usize];
+                            [position.x, position.y, position.z].map(f32::to_bits)
+                        });
+                        let first = (0..3).min_by_key(|&i| corners[i]).unwrap_or(0);
+                        [0, 1, 2].map(|i| corners[(first + i) % 3])
+                    })
+                    .collect();
+                triangles.sort_unstable();
+                triangles
+            })
+            .collect()
+    }
+
+    #[test]
+    fn optimizing_the_vertex_cache_lowers_the_miss_rate() {
+        let mut mesh = sphere(32, 64);
+        let before = mesh.cache_stats(16);
+        mesh.optimize_vertex_cache();
+        let after = mesh.cache_stats(16);
+
+        assert!(before.acmr() > 1.0, "{:?}", before);
+        assert!(after.acmr() < 0.75, "{:?}", after);
+        assert!(after.transformed() < before.transformed());
+    }
+
+    #[test]
+    fn reordering_passes_keep_the_triangles_of_each_part() {
+        let mut mesh = sphere(16, 24);
+        let original = triangles(&mesh);
+        let parts = mesh.parts().clone();
+        assert_eq!(parts.len(), 2);
+
+        mesh.optimize_vertex_cache();
+        mesh.optimize_overdraw(1.05);
+        assert_eq!(triangles(&mesh), original);
+        assert_eq!(mesh.parts(), &parts);
+
+        mesh.optimize_vertex_fetch();
+        assert_eq!(triangles(&mesh), original);
+        assert_eq!(mesh.parts(), &parts);
+    }
+
+    #[test]
+    fn optimizing_the_vertex_fetch_drops_unused_vertices() {
+        let mut mesh = sphere(8, 12);
+        let used = mesh.vertex_count();
+        let (first, count) = (mesh.parts()[1].first_index(), mesh.parts()[1].index_count());
+        // repeat the first triangle over the first part, leaving most of its vertices unused
+        if let Some(part) = mesh.part_indices_mut().into_iter().next() {
+            let first = [part[0], part[1], part[2]];
+            for triangle in part.chunks_mut(3) {
+                triangle.copy_from_slice(&first);
+            }
+        }
+
+        mesh.optimize_vertex_fetch();
+        let mut seen = vec![false; mesh.vertex_count()];
+        let mut next = 0;
+        for &index in mesh.indices() {
+            // vertices are numbered in the order they are first used
+            if !seen[index as usize] {
+                assert_eq!(index, next);
+                seen[index as usize] = true;
+                next += 1;
+            }
+        }
+        assert!(seen.iter().all(|&seen| seen));
+        assert!(mesh.vertex_count() < used);
+        assert_eq!(
+            (mesh.parts()[1].first_index(), mesh.parts()[1].index_count()),
+            (first, count)
+        );
+    }
+}
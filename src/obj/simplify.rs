@@ -0,0 +1,795 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::face::{Face, FaceElement};
+use crate::math::{self, Vec3};
+use crate::obj_mesh::ObjMesh;
+use crate::{MaterialHandle, VertexAttribute};
+
+/// Weight of the planes that hold borders and seams in place, relative to the faces
+const BORDER_WEIGHT: f64 = 10.0;
+
+/**
+`obj::SimplifyOptions`
+---
+When `ObjMesh.simplify` stops collapsing edges: once the mesh is down to
+`target_triangles`, or when the error of the next collapse, as described
+by `Lod`, would exceed `max_error`, `0.01` by default.
+*/
+#[derive(Debug, Clone)]
+pub struct SimplifyOptions {
+    target_triangles: usize,
+    max_error: f32,
+}
+
+impl Default for SimplifyOptions {
+    fn default() -> Self {
+        Self {
+            target_triangles: 0,
+            max_error: 0.01,
+        }
+    }
+}
+
+impl SimplifyOptions {
+    /// Immutable getter for `target_triangles` of `SimplifyOptions`
+    pub fn target_triangles(&self) -> usize {
+        self.target_triangles
+    }
+
+    /// Setter for `target_triangles` of `SimplifyOptions`
+    pub fn set_target_triangles(&mut self, value: usize) {
+        self.target_triangles = value;
+    }
+
+    /// Immutable getter for `max_error` of `SimplifyOptions`
+    pub fn max_error(&self) -> f32 {
+        self.max_error
+    }
+
+    /// Setter for `max_error` of `SimplifyOptions`, `f32::INFINITY` for no bound
+    pub fn set_max_error(&mut self, value: f32) {
+        self.max_error = value;
+    }
+}
+
+/**
+`obj::Lod`
+---
+A simplified `ObjMesh` and its error, relative to the mesh's bounding box
+diagonal. Each collapse is measured by the quadric error metric, as the
+root mean square distance of the kept position to the planes of the faces
+around it, weighted by their area; the error is the largest of these. It
+is an estimate, and points of the surface may lie further away than it.
+*/
+#[derive(Debug)]
+pub struct Lod {
+    mesh: ObjMesh,
+    error: f32,
+}
+
+impl Lod {
+    /// Immutable getter for `mesh` of `Lod`
+    pub fn mesh(&self) -> &ObjMesh {
+        &self.mesh
+    }
+
+    /// Immutable getter for `error` of `Lod`
+    pub fn error(&self) -> f32 {
+        self.error
+    }
+
+    /// Returns the simplified mesh, consuming `self`
+    pub fn into_mesh(self) -> ObjMesh {
+        self.mesh
+    }
+}
+
+impl ObjMesh {
+    /**
+    `ObjMesh.simplify`
+    ---
+    Simplifies `self` by quadric error metric edge collapses, until one of
+    the bounds of `options` is reached. Faces with more than three corners
+    are split into triangle fans first.
+
+    Every collapse moves a vertex onto one of its neighbours, so the result
+    only uses positions, uvs and normals of `self`. Vertices on the border
+    of the mesh, on uv and normal seams and between materials only move along
+    those lines, keeping them in place; corners where they meet never move.
+    Groups and materials of the faces are kept, free-form geometry is not.
+    */
+    pub fn simplify(&self, options: &SimplifyOptions) -> Lod {
+        let mut simplifier = Simplifier::new(self);
+        simplifier.run(options.target_triangles(), options.max_error());
+
+        Lod {
+            mesh: simplifier.mesh(),
+            error: simplifier.error,
+        }
+    }
+
+    /**
+    `ObjMesh.lod_chain`
+    ---
+    Simplifies `self` into a chain of levels of detail, each with about
+    `ratio` times the triangles of the one before, starting with `self`
+    triangulated at an error of `0`. The chain ends once a bound of
+    `options` is reached or no more edges can be collapsed. The levels
+    come from a single simplification, so their errors never decrease.
+    */
+    pub fn lod_chain(&self, ratio: f32, options: &SimplifyOptions) -> Vec<Lod> {
+        let mut simplifier = Simplifier::new(self);
+        let mut chain = vec![Lod {
+            mesh: simplifier.mesh(),
+            error: 0.0,
+        }];
+
+        let ratio = ratio.clamp(0.0, 1.0);
+        while simplifier.count > options.target_triangles() {
+            let before = simplifier.count;
+            let target = ((before as f32 * ratio) as usize)
+                .min(before - 1)
+                .max(options.target_triangles());
+
+            simplifier.run(target, options.max_error());
+            if simplifier.count == before {
+                break;
+            }
+            chain.push(Lod {
+                mesh: simplifier.mesh(),
+                error: simplifier.error,
+            });
+            if simplifier.count > target {
+                break;
+            }
+        }
+
+        chain
+    }
+}
+
+/**
+`obj::Quadric`
+---
+Sum of squared distances to a set of weighted planes, as the
+symmetric matrix `a`, the vector `b` and the constant `c`
+*/
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric {
+    a: [f64; 6],
+    b: [f64; 3],
+    c: f64,
+    weight: f64,
+}
+
+impl Quadric {
+    /// Quadric of the plane `normal . p + d = 0`, with a unit `normal`
+    fn plane(normal: Vec3, d: f32, weight: f64) -> Self {
+        let [x, y, z] = normal.map(|c| c as f64);
+        let d = d as f64;
+
+        Self {
+            a: [x * x, x * y, x * z, y * y, y * z, z * z].map(|v| v * weight),
+            b: [x * d, y * d, z * d].map(|v| v * weight),
+            c: d * d * weight,
+            weight,
+        }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self {
+            a: std::array::from_fn(|i| self.a[i] + other.a[i]),
+            b: std::array::from_fn(|i| self.b[i] + other.b[i]),
+            c: self.c + other.c,
+            weight: self.weight + other.weight,
+        }
+    }
+
+    /// Weighted mean squared distance of `p` to the planes
+    fn error(&self, p: Vec3) -> f64 {
+        if self.weight <= 0.0 {
+            return 0.0;
+        }
+
+        let [x, y, z] = p.map(|c| c as f64);
+        let a = &self.a;
+        let quadratic = a[0] * x * x
+            + a[3] * y * y
+            + a[5] * z * z
+            + 2.0 * (a[1] * x * y + a[2] * x * z + a[4] * y * z);
+        let linear = 2.0 * (self.b[0] * x + self.b[1] * y + self.b[2] * z);
+
+        ((quadratic + linear + self.c) / self.weight).max(0.0)
+    }
+}
+
+/// A candidate collapse of position `from` onto position `to`
+#[derive(Debug, Clone, Copy)]
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // reversed, so that `BinaryHeap` pops the cheapest collapse first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// A distinct corner: position, uv and normal (`0` when absent) and material
+type Wedge = (usize, u32, u32, Option<MaterialHandle>);
+
+/**
+`obj::Simplifier`
+---
+Edge collapse state of a triangulated `ObjMesh`. Triangles refer to
+wedges rather than positions, so that a collapse can tell the sides
+of a seam apart and give each corner the uv, normal and material of
+its own side.
+*/
+struct Simplifier<'a> {
+    source: &'a ObjMesh,
+    positions: Vec<Vec3>,
+    wedges: Vec<Wedge>,
+
+    // wedges of each triangle, with the face it was split from
+    triangles: Vec<[usize; 3]>,
+    faces: Vec<usize>,
+    alive: Vec<bool>,
+    count: usize,
+
+    // triangles around each position
+    adjacency: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+
+    // positions on non-manifold edges, which never move
+    locked: Vec<bool>,
+    heap: BinaryHeap<Collapse>,
+
+    // bounding box diagonal, and the largest error so far relative to it
+    scale: f64,
+    error: f32,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(source: &'a ObjMesh) -> Self {
+        let positions: Vec<Vec3> = source.positions().iter().map(math::from_vector).collect();
+        let mut wedges = vec![];
+        let mut wedge_ids: HashMap<Wedge, usize> = HashMap::new();
+        let mut triangles = vec![];
+        let mut faces = vec![];
+
+        for (index, face) in source.faces().iter().enumerate() {
+            let material = source.face_material(index);
+            let corners: Option<Vec<Wedge>> = face
+                .elements()
+                .iter()
+                .map(|element| {
+                    let local = |attribute, index| {
+                        source
+                            .local_index(attribute, index)
+                            .map_or(0, |local| local as u32 + 1)
+                    };
+                    let position =
+                        source.local_index(VertexAttribute::Position, element.position_index())?;
+                    Some((
+                        position,
+                        local(VertexAttribute::Uv, element.uv_index()),
+                        local(VertexAttribute::Normal, element.normal_index()),
+                        material,
+                    ))
+                })
+                .collect();
+            let Some(corners) = corners.filter(|corners| corners.len() >= 3) else {
+                continue;
+            };
+            if !corners
+                .iter()
+                .all(|corner| math::is_finite(positions[corner.0]))
+            {
+                continue;
+            }
+
+            let mut ids = corners.into_iter().map(|corner| {
+                *wedge_ids.entry(corner).or_insert_with(|| {
+                    wedges.push(corner);
+                    wedges.len() - 1
+                })
+            });
+            let first = ids.next().unwrap_or_default();
+            let rest: Vec<usize> = ids.collect();
+            for pair in rest.windows(2) {
+                let triangle = [first, pair[0], pair[1]];
+                let [a, b, c] = triangle.map(|w| wedges[w].0);
+                if a != b && b != c && a != c {
+                    triangles.push(triangle);
+                    faces.push(index);
+                }
+            }
+        }
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for triangle in &triangles {
+            for &w in triangle {
+                let p = positions[wedges[w].0];
+                min = std::array::from_fn(|i| min[i].min(p[i]));
+                max = std::array::from_fn(|i| max[i].max(p[i]));
+            }
+        }
+        let scale = match triangles.is_empty() {
+            true => 1.0,
+            false => (math::length(math::sub(max, min)) as f64).max(f64::MIN_POSITIVE),
+        };
+
+        let count = triangles.len();
+        let mut simplifier = Self {
+            source,
+            adjacency: vec![vec![]; positions.len()],
+            quadrics: vec![Quadric::default(); positions.len()],
+            locked: vec![false; positions.len()],
+            positions,
+            wedges,
+            triangles,
+            faces,
+            alive: vec![true; count],
+            count,
+            heap: BinaryHeap::new(),
+            scale,
+            error: 0.0,
+        };
+        simplifier.build();
+        simplifier
+    }
+
+    /// Builds the adjacency and quadrics, and queues the collapses of every edge
+    fn build(&mut self) {
+        // triangles using each edge, by its positions in ascending order
+        let mut edges: HashMap<[usize; 2], Vec<usize>> = HashMap::new();
+
+        for (t, triangle) in self.triangles.iter().enumerate() {
+            let points = triangle.map(|w| self.wedges[w].0);
+            let [a, b, c] = points.map(|p| self.positions[p]);
+            let normal = math::cross(math::sub(b, a), math::sub(c, a));
+            let area = math::length(normal) as f64 / 2.0;
+
+            if let Some(normal) = math::normalize(normal) {
+                let quadric = Quadric::plane(normal, -math::dot(normal, a), area);
+                for p in points {
+                    self.quadrics[p] = self.quadrics[p].add(&quadric);
+                }
+            }
+            for (i, &p) in points.iter().enumerate() {
+                self.adjacency[p].push(t);
+                let q = points[(i + 1) % 3];
+                edges.entry([p.min(q), p.max(q)]).or_default().push(t);
+            }
+        }
+
+        let mut keys: Vec<[usize; 2]> = edges.keys().copied().collect();
+        keys.sort_unstable();
+        for edge in keys {
+            let faces = &edges[&edge];
+            let seam = match faces[..] {
+                [_] => true,
+                [s, t] => edge
+                    .iter()
+                    .any(|&p| self.wedge_at(s, p) != self.wedge_at(t, p)),
+                _ => {
+                    self.locked[edge[0]] = true;
+                    self.locked[edge[1]] = true;
+                    false
+                }
+            };
+
+            // a plane through the edge, across the face, holds the line in place
+            if seam {
+                let [a, b] = edge.map(|p| self.positions[p]);
+                let along = math::sub(b, a);
+                let triangle = self.triangles[faces[0]].map(|w| self.positions[self.wedges[w].0]);
+                let normal = math::cross(
+                    math::sub(triangle[1], triangle[0]),
+                    math::sub(triangle[2], triangle[0]),
+                );
+                if let Some(across) = math::normalize(math::cross(along, normal)) {
+                    let weight = math::dot(along, along) as f64 * BORDER_WEIGHT;
+                    let quadric = Quadric::plane(across, -math::dot(across, a), weight);
+                    for p in edge {
+                        self.quadrics[p] = self.quadrics[p].add(&quadric);
+                    }
+                }
+            }
+
+            self.queue(edge[0], edge[1]);
+            self.queue(edge[1], edge[0]);
+        }
+    }
+
+    /// Returns the wedge of triangle `t` at position `p`
+    fn wedge_at(&self, t: usize, p: usize) -> Option<usize> {
+        self.triangles[t]
+            .iter()
+            .copied()
+            .find(|&w| self.wedges[w].0 == p)
+    }
+
+    fn cost(&self, from: usize, to: usize) -> f64 {
+        self.quadrics[from]
+            .add(&self.quadrics[to])
+            .error(self.positions[to])
+    }
+
+    fn queue(&mut self, from: usize, to: usize) {
+        let cost = self.cost(from, to);
+        self.heap.push(Collapse { cost, from, to });
+    }
+
+    /**
+    `Simplifier.wedge_map`
+    ---
+    Checks whether `from` can collapse onto `to`, returning the wedge of
+    `to` that replaces each wedge of `from`. The collapse must keep the
+    mesh manifold without flipping faces, a vertex on the border may only
+    move along it, and every wedge of `from` must have a counterpart on its
+    own side of the edge, which keeps seams and material borders in place.
+    */
+    fn wedge_map(&self, from: usize, to: usize) -> Option<HashMap<usize, usize>> {
+        if self.locked[from] {
+            return None;
+        }
+
+        let around: Vec<usize> = self.adjacency[from]
+            .iter()
+            .copied()
+            .filter(|&t| self.alive[t])
+            .collect();
+        let (shared, moved): (Vec<usize>, Vec<usize>) = around
+            .iter()
+            .partition(|&&t| self.wedge_at(t, to).is_some());
+        if shared.is_empty() {
+            return None;
+        }
+
+        // triangles on each edge around `from`, by its other end
+        let mut edges: HashMap<usize, usize> = HashMap::new();
+        for &t in &around {
+            for w in self.triangles[t] {
+                let p = self.wedges[w].0;
+                if p != from {
+                    *edges.entry(p).or_default() += 1;
+                }
+            }
+        }
+        if edges.values().any(|&count| count > 2) {
+            return None;
+        }
+        if edges.values().any(|&count| count == 1) && edges.get(&to) != Some(&1) {
+            return None;
+        }
+
+        let mut map = HashMap::new();
+        for &t in &shared {
+            let (source, target) = (self.wedge_at(t, from)?, self.wedge_at(t, to)?);
+            if *map.entry(source).or_insert(target) != target {
+                return None;
+            }
+        }
+        if moved
+            .iter()
+            .any(|&t| !map.contains_key(&self.wedge_at(t, from).unwrap_or(usize::MAX)))
+        {
+            return None;
+        }
+
+        // the link condition: the ends may only share the neighbours across the edge
+        let neighbours: HashSet<usize> = self.adjacency[to]
+            .iter()
+            .filter(|&&t| self.alive[t])
+            .flat_map(|&t| self.triangles[t].map(|w| self.wedges[w].0))
+            .collect();
+        let common = edges
+            .keys()
+            .filter(|&&p| p != to && neighbours.contains(&p))
+            .count();
+        if common != shared.len() {
+            return None;
+        }
+
+        for &t in &moved {
+            let before = self.triangles[t].map(|w| self.positions[self.wedges[w].0]);
+            let after = self.triangles[t].map(|w| match self.wedges[w].0 == from {
+                true => self.positions[to],
+                false => self.positions[self.wedges[w].0],
+            });
+            let normal = |[a, b, c]: [Vec3; 3]| math::cross(math::sub(b, a), math::sub(c, a));
+            if math::dot(normal(before), normal(after)) <= 0.0 {
+                return None;
+            }
+        }
+
+        Some(map)
+    }
+
+    /// Collapses `from` onto `to`, and queues the edges around `to` again
+    fn collapse(&mut self, from: usize, to: usize, map: &HashMap<usize, usize>) {
+        let around = std::mem::take(&mut self.adjacency[from]);
+        for t in around {
+            if !self.alive[t] {
+                continue;
+            }
+            if self.wedge_at(t, to).is_some() {
+                self.alive[t] = false;
+                self.count -= 1;
+                continue;
+            }
+            for w in &mut self.triangles[t] {
+                if let Some(&target) = map.get(w) {
+                    *w = target;
+                }
+            }
+            self.adjacency[to].push(t);
+        }
+
+        let alive = &self.alive;
+        self.adjacency[to].retain(|&t| alive[t]);
+        self.adjacency[to].sort_unstable();
+        self.adjacency[to].dedup();
+        self.quadrics[to] = self.quadrics[to].add(&self.quadrics[from]);
+
+        let mut neighbours: Vec<usize> = self.adjacency[to]
+            .iter()
+            .flat_map(|&t| self.triangles[t].map(|w| self.wedges[w].0))
+            .filter(|&p| p != to)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        for p in neighbours {
+            self.queue(to, p);
+            self.queue(p, to);
+        }
+    }
+
+    /// Collapses edges until `target` triangles are left, or the next collapse exceeds `max_error`
+    fn run(&mut self, target: usize, max_error: f32) {
+        while self.count > target {
+            let Some(candidate) = self.heap.pop() else {
+                break;
+            };
+
+            // a cost changes once either end is collapsed into, so entries
+            // that have grown since they were queued go back at their new cost
+            let cost = self.cost(candidate.from, candidate.to);
+            if cost > candidate.cost * (1.0 + 1e-6) + f64::MIN_POSITIVE {
+                self.heap.push(Collapse { cost, ..candidate });
+                continue;
+            }
+
+            let error = (cost.sqrt() / self.scale) as f32;
+            if error > max_error {
+                self.heap.push(candidate);
+                break;
+            }
+
+            if let Some(map) = self.wedge_map(candidate.from, candidate.to) {
+                self.collapse(candidate.from, candidate.to, &map);
+                self.error = self.error.max(error);
+            }
+        }
+    }
+
+    /**
+    `Simplifier.mesh`
+    ---
+    Builds an `ObjMesh` from the triangles left, in the order of the
+    faces they come from, with the groups and materials of those faces
+    */
+    fn mesh(&self) -> ObjMesh {
+        let source = self.source;
+        let mut mesh = ObjMesh::empty();
+        mesh.set_name(source.get_name());
+
+        for (index, position) in source.positions().iter().enumerate() {
+            mesh.load_weighted_position(position.clone(), source.weight(index));
+        }
+        for uv in source.uvs() {
+            mesh.load_uv(uv.clone());
+        }
+        for normal in source.normals() {
+            mesh.load_normal(normal.clone());
+        }
+
+        let groups = source.groups();
+        let mut group = 0;
+        let mut material = None;
+        for (t, triangle) in self.triangles.iter().enumerate() {
+            if !self.alive[t] {
+                continue;
+            }
+
+            // groups of the dropped faces before this one are skipped
+            let face = self.faces[t];
+            let started = groups[group..]
+                .iter()
+                .take_while(|next| next.first_face() <= face)
+                .count();
+            if started > 0 {
+                group += started;
+                mesh.load_group(groups[group - 1].names().clone());
+            }
+            let (_, _, _, face_material) = self.wedges[triangle[0]];
+            if face_material != material {
+                mesh.use_material(face_material);
+                material = face_material;
+            }
+
+            mesh.load_face(Face::new(
+                triangle
+                    .iter()
+                    .map(|&w| {
+                        let (position, uv, normal, _) = self.wedges[w];
+                        FaceElement::new(position as u32 + 1, normal, uv)
+                    })
+                    .collect(),
+            ));
+        }
+
+        mesh.compact();
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Mtl, MtlMaterial};
+    use suoi_types::{Vector2, Vector3};
+
+    /// A grid of `n` by `n` unit quads, with `z` at each corner given by `height`
+    fn grid(n: u32, height: impl Fn(f32, f32) -> f32) -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for j in 0..=n {
+            for i in 0..=n {
+                let (x, y) = (i as f32, j as f32);
+                mesh.load_position(Vector3::new(x, y, height(x, y)));
+            }
+        }
+        for j in 0..n {
+            for i in 0..n {
+                let corner = |i: u32, j: u32| FaceElement::new(j * (n + 1) + i + 1, 0, 0);
+                let corners = vec![
+                    corner(i, j),
+                    corner(i + 1, j),
+                    corner(i + 1, j + 1),
+                    corner(i, j + 1),
+                ];
+                mesh.load_face(Face::new(corners));
+            }
+        }
+        mesh
+    }
+
+    fn bumps(x: f32, y: f32) -> f32 {
+        (x * 0.9).sin() * (y * 0.7).cos() * 0.5
+    }
+
+    #[test]
+    fn simplify_stops_at_target_triangles() {
+        let mesh = grid(8, |_, _| 0.0);
+        let mut options = SimplifyOptions::default();
+        options.set_target_triangles(20);
+
+        let lod = mesh.simplify(&options);
+        let triangles = lod.mesh().faces().len();
+        assert!((19..=20).contains(&triangles), "{triangles} triangles");
+        assert!(lod.mesh().faces().iter().all(|f| f.elements().len() == 3));
+        // a flat grid collapses without moving the surface
+        assert!(lod.error() < 1e-6);
+    }
+
+    #[test]
+    fn simplify_keeps_uv_seams_and_material_borders() {
+        let n = 8;
+        let half = (n / 2) as f32;
+        let mut mtl = Mtl::empty();
+        let materials = [
+            Some(mtl.add_material(MtlMaterial::empty())),
+            Some(mtl.add_material(MtlMaterial::empty())),
+        ];
+
+        // every position has a uv below and a uv above the seam at y = half
+        let source = grid(n, bumps);
+        let mut mesh = ObjMesh::empty();
+        for position in source.positions() {
+            mesh.load_position(position.clone());
+            for v in [position.y / n as f32, position.y / n as f32 + 1.0] {
+                mesh.load_uv(Vector2 {
+                    x: position.x / n as f32,
+                    y: v,
+                });
+            }
+        }
+        for material in materials {
+            mesh.use_material(material);
+            for face in source.faces() {
+                let first = face.elements()[0].position_index() as usize - 1;
+                let corner = &source.positions()[first];
+                if (corner.x < half) != (material == materials[0]) {
+                    continue;
+                }
+                let above = corner.y >= half;
+                let corners = face
+                    .elements()
+                    .iter()
+                    .map(|e| {
+                        let p = e.position_index();
+                        FaceElement::new(p, 0, 2 * p - 1 + above as u32)
+                    })
+                    .collect();
+                mesh.load_face(Face::new(corners));
+            }
+        }
+
+        let mut options = SimplifyOptions::default();
+        options.set_max_error(f32::INFINITY);
+        let lod = mesh.simplify(&options);
+        let simplified = lod.mesh();
+        assert!(simplified.faces().len() < mesh.faces().len() / 2);
+
+        for (index, face) in simplified.faces().iter().enumerate() {
+            let left = simplified.face_material(index) == materials[0];
+            for element in face.elements() {
+                let position = &simplified.positions()[element.position_index() as usize - 1];
+                let uv = &simplified.uvs()[element.uv_index() as usize - 1];
+                let above = uv.y >= 1.0;
+
+                // the kept uv still belongs to the position on its side of the seam
+                assert_eq!(uv.x * n as f32, position.x);
+                assert_eq!((uv.y - above as u32 as f32) * n as f32, position.y);
+                assert!(if above {
+                    position.y >= half
+                } else {
+                    position.y <= half
+                });
+                assert!(if left {
+                    position.x <= half
+                } else {
+                    position.x >= half
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn lod_chain_errors_never_decrease() {
+        let mesh = grid(12, bumps);
+        let mut options = SimplifyOptions::default();
+        options.set_max_error(f32::INFINITY);
+
+        let chain = mesh.lod_chain(0.5, &options);
+        assert!(chain.len() > 3);
+        assert_eq!(chain[0].error(), 0.0);
+        assert_eq!(chain[0].mesh().faces().len(), 12 * 12 * 2);
+        for pair in chain.windows(2) {
+            assert!(pair[1].mesh().faces().len() < pair[0].mesh().faces().len());
+            assert!(pair[1].error() >= pair[0].error());
+        }
+        assert!(chain.last().unwrap().error() > 0.0);
+    }
+}
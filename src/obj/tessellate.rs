@@ -0,0 +1,456 @@
+use suoi_types::{Vector2, Vector3};
+
+use crate::face::{Face, FaceElement};
+use crate::freeform::{CurveType, FreeformAttributes, Surface};
+use crate::math::{self, Vec3};
+use crate::obj_mesh::ObjMesh;
+use crate::Obj;
+
+/// Homogeneous control point: `(x * w, y * w, z * w, w)`
+type Point4 = [f32; 4];
+
+impl Obj {
+    /**
+    `Obj.tessellate_curves`
+    ---
+    Samples every Bézier and B-spline `curv` of `self` into a polyline.
+    Each non-empty knot span of the curve's `[start, end]` range is split
+    into `segments` steps. Curves of other bases are skipped.
+    */
+    pub fn tessellate_curves(&self, segments: u32) -> Vec<Vec<Vector3>> {
+        let net = self.control_net();
+        let mut polylines = vec![];
+
+        for mesh in self.meshes() {
+            for curve in mesh.curves() {
+                let points = curve
+                    .control_points()
+                    .iter()
+                    .map(|&index| Self::control_point(&net, index, curve.attributes()))
+                    .collect::<Option<Vec<Point4>>>();
+                let attributes = curve.attributes();
+                let degree = attributes.degree_u() as usize;
+                let knots = points.as_ref().and_then(|points| {
+                    Self::knot_vector(attributes.curve_type(), degree, curve.knots(), points.len())
+                });
+
+                if let (Some(points), Some(knots)) = (points, knots) {
+                    let params =
+                        Self::samples(&knots, degree, curve.start(), curve.end(), segments);
+                    polylines.push(
+                        params
+                            .iter()
+                            .map(|&u| {
+                                math::to_vector(Self::project(Self::de_boor(
+                                    &points, &knots, degree, u,
+                                )))
+                            })
+                            .collect(),
+                    );
+                }
+            }
+        }
+
+        polylines
+    }
+
+    /**
+    `Obj.tessellate_surfaces`
+    ---
+    Tessellates every Bézier and B-spline `surf` of `self` into a
+    triangulated `ObjMesh`, with one mesh per surface named after the
+    object it belongs to. Each non-empty knot span is split into
+    `segments` steps in both directions. The meshes carry normals,
+    uvs spanning the surface's parameter range as `0..1`, and the
+    material that was active when the surface was defined.
+
+    Trimming loops and holes are not applied: the whole parameter
+    range of the surface is tessellated. Surfaces of other bases,
+    or with control point counts that don't match their knot
+    vectors, are skipped.
+    */
+    pub fn tessellate_surfaces(&self, segments: u32) -> Vec<ObjMesh> {
+        let net = self.control_net();
+        let mut meshes = vec![];
+
+        for mesh in self.meshes() {
+            for surface in mesh.surfaces() {
+                if let Some(mut tessellated) = Self::tessellate_surface(&net, surface, segments) {
+                    tessellated.set_name(mesh.get_name());
+                    if let Some(material) = surface.material() {
+                        tessellated.set_material(material);
+                    }
+                    meshes.push(tessellated);
+                }
+            }
+        }
+
+        meshes
+    }
+
+    /**
+    `Obj.control_net`
+    ---
+    Collects the positions of every mesh, in file order, as homogeneous
+    points so that file-wide control point indices can be looked up
+    */
+    fn control_net(&self) -> Vec<(Vec3, f32)> {
+        let mut net = vec![];
+        for mesh in self.meshes() {
+            for (index, position) in mesh.positions().iter().enumerate() {
+                net.push((math::from_vector(position), mesh.weight(index)));
+            }
+        }
+
+        net
+    }
+
+    fn control_point(
+        net: &[(Vec3, f32)],
+        index: u32,
+        attributes: &FreeformAttributes,
+    ) -> Option<Point4> {
+        let (p, weight) = *net.get((index as usize).checked_sub(1)?)?;
+        let w = if attributes.rational() { weight } else { 1.0 };
+
+        Some([p[0] * w, p[1] * w, p[2] * w, w])
+    }
+
+    fn project(point: Point4) -> Vec3 {
+        match point[3] {
+            0.0 => [point[0], point[1], point[2]],
+            w => [point[0] / w, point[1] / w, point[2] / w],
+        }
+    }
+
+    /**
+    `Obj::knot_vector`
+    ---
+    Returns the knot vector of a curve direction with `count` control points.
+    B-spline knots are the `parm` values themselves. A Bézier curve is piecewise,
+    with `parm` giving the breakpoints between pieces, and is converted to the
+    equivalent clamped B-spline knot vector.
+    */
+    fn knot_vector(
+        curve_type: CurveType,
+        degree: usize,
+        parm: &[f32],
+        count: usize,
+    ) -> Option<Vec<f32>> {
+        if degree == 0 || count <= degree {
+            return None;
+        }
+
+        let knots = match curve_type {
+            CurveType::BSpline => parm.to_vec(),
+            CurveType::Bezier => {
+                if !(count - 1).is_multiple_of(degree) {
+                    return None;
+                }
+                let pieces = (count - 1) / degree;
+                let breakpoints: Vec<f32> = match parm.len() {
+                    0 => (0..=pieces).map(|piece| piece as f32).collect(),
+                    _ => parm.to_vec(),
+                };
+                if breakpoints.len() != pieces + 1 {
+                    return None;
+                }
+
+                let mut knots = vec![breakpoints[0]];
+                for &breakpoint in &breakpoints {
+                    knots.extend(std::iter::repeat_n(breakpoint, degree));
+                }
+                knots.push(breakpoints[pieces]);
+                knots
+            }
+            _ => return None,
+        };
+
+        match knots.len() == count + degree + 1 {
+            true => Some(knots),
+            false => None,
+        }
+    }
+
+    /**
+    `Obj::samples`
+    ---
+    Parameter values covering `[start, end]`, clamped to the domain of
+    `knots`: every knot inside the range is a sample, and each span
+    between them is split into `segments` steps
+    */
+    fn samples(knots: &[f32], degree: usize, start: f32, end: f32, segments: u32) -> Vec<f32> {
+        let count = knots.len() - degree - 1;
+        let (lo, hi) = (knots[degree], knots[count]);
+        let a = start.min(end).clamp(lo, hi);
+        let b = start.max(end).clamp(lo, hi);
+
+        let mut breakpoints = vec![a];
+        for &knot in knots {
+            if knot > *breakpoints.last().unwrap() && knot < b {
+                breakpoints.push(knot);
+            }
+        }
+        breakpoints.push(b);
+
+        let segments = segments.max(1);
+        let mut params = vec![];
+        for pair in breakpoints.windows(2) {
+            if pair[1] <= pair[0] {
+                continue;
+            }
+            for step in 0..segments {
+                params.push(pair[0] + (pair[1] - pair[0]) * step as f32 / segments as f32);
+            }
+        }
+        params.push(b);
+
+        params
+    }
+
+    /**
+    `Obj::de_boor`
+    ---
+    Evaluates the B-spline with control `points`, `knots` and `degree` at `u`
+    */
+    fn de_boor(points: &[Point4], knots: &[f32], degree: usize, u: f32) -> Point4 {
+        let count = points.len();
+        let mut span = degree;
+        while span < count - 1 && knots[span + 1] <= u {
+            span += 1;
+        }
+
+        let mut d: Vec<Point4> = (0..=degree).map(|j| points[j + span - degree]).collect();
+        for r in 1..=degree {
+            for j in (r..=degree).rev() {
+                let lo = knots[j + span - degree];
+                let hi = knots[j + 1 + span - r];
+                let alpha = if hi > lo { (u - lo) / (hi - lo) } else { 0.0 };
+                let prev = d[j - 1];
+                for (c, value) in d[j].iter_mut().enumerate() {
+                    *value = (1.0 - alpha) * prev[c] + alpha * *value;
+                }
+            }
+        }
+
+        d[degree]
+    }
+
+    fn tessellate_surface(
+        net: &[(Vec3, f32)],
+        surface: &Surface,
+        segments: u32,
+    ) -> Option<ObjMesh> {
+        let attributes = surface.attributes();
+        let degree_u = attributes.degree_u() as usize;
+        let degree_v = attributes.degree_v() as usize;
+
+        let points = surface
+            .control_points()
+            .iter()
+            .map(|element| Self::control_point(net, element.position_index(), attributes))
+            .collect::<Option<Vec<Point4>>>()?;
+
+        // the control point grid size follows from the knot vectors
+        let count_u = match attributes.curve_type() {
+            CurveType::BSpline => surface.knots_u().len().checked_sub(degree_u + 1)?,
+            _ => match surface.knots_u().len() {
+                0 => points.len() / Self::bezier_rows(points.len(), degree_u, degree_v)?,
+                len => (len - 1) * degree_u + 1,
+            },
+        };
+        if count_u == 0 || points.len() % count_u != 0 {
+            return None;
+        }
+        let count_v = points.len() / count_u;
+
+        let curve_type = attributes.curve_type();
+        let knots_u = Self::knot_vector(curve_type, degree_u, surface.knots_u(), count_u)?;
+        let knots_v = Self::knot_vector(curve_type, degree_v, surface.knots_v(), count_v)?;
+
+        let (start_u, end_u) = surface.range_u();
+        let (start_v, end_v) = surface.range_v();
+        let params_u = Self::samples(&knots_u, degree_u, start_u, end_u, segments);
+        let params_v = Self::samples(&knots_v, degree_v, start_v, end_v, segments);
+
+        // evaluate every row along u first, then each column along v
+        let mut grid: Vec<Vec3> = vec![[0.0; 3]; params_u.len() * params_v.len()];
+        for (i, &u) in params_u.iter().enumerate() {
+            let column: Vec<Point4> = points
+                .chunks(count_u)
+                .map(|row| Self::de_boor(row, &knots_u, degree_u, u))
+                .collect();
+            for (j, &v) in params_v.iter().enumerate() {
+                grid[j * params_u.len() + i] =
+                    Self::project(Self::de_boor(&column, &knots_v, degree_v, v));
+            }
+        }
+
+        Some(Self::grid_mesh(grid, &params_u, &params_v))
+    }
+
+    /**
+    `Obj::bezier_rows`
+    ---
+    Finds the number of control point rows of a Bézier surface without
+    `parm` statements, as the only split of `count` points into a grid
+    whose both sides are whole numbers of pieces of the given degrees
+    */
+    fn bezier_rows(count: usize, degree_u: usize, degree_v: usize) -> Option<usize> {
+        (1..=count)
+            .filter(|rows| count.is_multiple_of(*rows))
+            .find(|rows| {
+                (rows - 1).is_multiple_of(degree_v)
+                    && (count / rows - 1).is_multiple_of(degree_u)
+                    && *rows > degree_v
+            })
+    }
+
+    /**
+    `Obj::grid_mesh`
+    ---
+    Builds a triangulated `ObjMesh` from a grid of positions sampled
+    at `params_u` x `params_v`, with u varying fastest
+    */
+    fn grid_mesh(grid: Vec<Vec3>, params_u: &[f32], params_v: &[f32]) -> ObjMesh {
+        let width = params_u.len();
+        let height = params_v.len();
+        let index = |i: usize, j: usize| (j * width + i) as u32 + 1;
+
+        let mut triangles: Vec<[u32; 3]> = vec![];
+        for j in 0..height - 1 {
+            for i in 0..width - 1 {
+                triangles.push([index(i, j), index(i + 1, j), index(i + 1, j + 1)]);
+                triangles.push([index(i, j), index(i + 1, j + 1), index(i, j + 1)]);
+            }
+        }
+
+        // area weighted vertex normals, robust at degenerate rows (poles)
+        let mut normals: Vec<Vec3> = vec![[0.0; 3]; grid.len()];
+        for triangle in &triangles {
+            let [a, b, c] = triangle.map(|i| grid[i as usize - 1]);
+            let normal = math::cross(math::sub(b, a), math::sub(c, a));
+            for &i in triangle {
+                normals[i as usize - 1] = math::add(normals[i as usize - 1], normal);
+            }
+        }
+
+        let mut mesh = ObjMesh::empty();
+        let (u0, u1) = (params_u[0], params_u[width - 1]);
+        let (v0, v1) = (params_v[0], params_v[height - 1]);
+        let normalize = |value: f32, lo: f32, hi: f32| {
+            if hi > lo {
+                (value - lo) / (hi - lo)
+            } else {
+                0.0
+            }
+        };
+
+        for (k, (position, normal)) in grid.iter().zip(&normals).enumerate() {
+            mesh.load_position(math::to_vector(*position));
+            mesh.load_normal(math::to_vector(
+                math::normalize(*normal).unwrap_or([0.0, 0.0, 1.0]),
+            ));
+            mesh.load_uv(Vector2 {
+                x: normalize(params_u[k % width], u0, u1),
+                y: normalize(params_v[k / width], v0, v1),
+            });
+        }
+
+        for triangle in triangles {
+            mesh.load_face(Face::new(
+                triangle
+                    .iter()
+                    .map(|&i| FaceElement::new(i, i, i))
+                    .collect(),
+            ));
+        }
+
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resource;
+
+    fn import(name: &str, text: &str) -> Obj {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, text).unwrap();
+        let obj = Obj::import(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        obj
+    }
+
+    fn assert_close(point: &Vector3, expected: [f32; 3]) {
+        let actual = [point.x, point.y, point.z];
+        for (a, b) in actual.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn quadratic_bezier_passes_through_its_ends_and_midpoint() {
+        let obj = import(
+            "suoi_simp_bezier_curve.obj",
+            "o curve\nv 0 0 0\nv 1 2 0\nv 2 0 0\ncstype bezier\ndeg 2\ncurv 0 1 1 2 3\nend\n",
+        );
+
+        let polylines = obj.tessellate_curves(2);
+        assert_eq!(polylines.len(), 1);
+        let points = &polylines[0];
+        assert_eq!(points.len(), 3);
+        assert_close(&points[0], [0.0, 0.0, 0.0]);
+        // (1 - t)^2 p0 + 2t(1 - t) p1 + t^2 p2 at t = 0.5
+        assert_close(&points[1], [1.0, 1.0, 0.0]);
+        assert_close(&points[2], [2.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn rational_bspline_uses_the_vertex_weights() {
+        let curve = "cstype {} bspline\ndeg 2\ncurv 0 1 1 2 3\nparm u 0 0 0 1 1 1\nend\n";
+        let vertices = "o curve\nv 0 0 0 1\nv 1 1 0 2\nv 2 0 0 1\n";
+
+        let rational = import(
+            "suoi_simp_rational_curve.obj",
+            &(vertices.to_owned() + &curve.replace("{}", "rat")),
+        );
+        let polynomial = import(
+            "suoi_simp_polynomial_curve.obj",
+            &(vertices.to_owned() + &curve.replace("{} ", "")),
+        );
+
+        // at u = 0.5 the basis is (1/4, 1/2, 1/4): the weight of 2
+        // pulls the rational curve from y = 1/2 up to y = 2/3
+        assert_close(&polynomial.tessellate_curves(2)[0][1], [1.0, 0.5, 0.0]);
+        assert_close(&rational.tessellate_curves(2)[0][1], [1.0, 2.0 / 3.0, 0.0]);
+    }
+
+    #[test]
+    fn bilinear_patch_is_a_grid_of_segments_plus_one_squared() {
+        let obj = import(
+            "suoi_simp_bilinear_patch.obj",
+            "o patch\nv 0 0 0\nv 2 0 0\nv 0 2 0\nv 2 2 1\ncstype bezier\ndeg 1 1\nsurf 0 1 0 1 1 2 3 4\nend\n",
+        );
+
+        for segments in [1, 2, 5] {
+            let meshes = obj.tessellate_surfaces(segments);
+            assert_eq!(meshes.len(), 1);
+            let mesh = &meshes[0];
+            let side = segments as usize + 1;
+            assert_eq!(mesh.positions().len(), side * side);
+            assert_eq!(mesh.uvs().len(), side * side);
+            assert_eq!(
+                mesh.faces().len(),
+                2 * segments as usize * segments as usize
+            );
+            assert_eq!(mesh.get_name(), "patch");
+        }
+
+        let mesh = &obj.tessellate_surfaces(2)[0];
+        assert_close(&mesh.positions()[0], [0.0, 0.0, 0.0]);
+        assert_close(&mesh.positions()[4], [1.0, 1.0, 0.25]);
+        assert_close(&mesh.positions()[8], [2.0, 2.0, 1.0]);
+    }
+}
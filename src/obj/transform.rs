@@ -0,0 +1,238 @@
+use suoi_types::Vector3;
+
+use crate::math::{self, Vec3};
+use crate::obj_mesh::ObjMesh;
+
+/**
+`obj::Pivot`
+---
+The point of a mesh moved to the origin by `ObjMesh.recenter`:
+the centre of its bounding box, the centre of the bottom of that
+box (lowest `y`), the mean of its positions, or any given point
+*/
+#[derive(Debug, Clone)]
+pub enum Pivot {
+    BoundsCenter,
+    BoundsBottom,
+    Centroid,
+    Point(Vector3),
+}
+
+impl ObjMesh {
+    /**
+    `ObjMesh.transform`
+    ---
+    Applies the affine `matrix` to `self`. The matrix is row-major and
+    acts on column vectors, so the translation is in its last column and
+    its last row is ignored. Normals are transformed by the inverse
+    transpose and renormalized, and faces reverse their winding when the
+    matrix mirrors, so that they keep facing outwards. Free-form geometry
+    moves with its control points.
+    */
+    pub fn transform(&mut self, matrix: &[[f32; 4]; 4]) {
+        let linear: [Vec3; 3] = std::array::from_fn(|r| [matrix[r][0], matrix[r][1], matrix[r][2]]);
+        let translation = [matrix[0][3], matrix[1][3], matrix[2][3]];
+        let apply = |m: &[Vec3; 3], v: Vec3| m.map(|row| math::dot(row, v));
+
+        for position in self.positions_mut() {
+            let moved = math::add(apply(&linear, math::from_vector(position)), translation);
+            *position = math::to_vector(moved);
+        }
+
+        // the cofactor matrix is the inverse transpose scaled by the determinant,
+        // and stays defined when the matrix flattens the mesh
+        let [a, b, c] = linear;
+        let cofactor = [math::cross(b, c), math::cross(c, a), math::cross(a, b)];
+        let determinant = math::dot(a, math::cross(b, c));
+        let sign = match determinant < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+
+        for normal in self.normals_mut() {
+            let turned = math::scale(apply(&cofactor, math::from_vector(normal)), sign);
+            if let Some(turned) = math::normalize(turned) {
+                *normal = math::to_vector(turned);
+            }
+        }
+        if determinant < 0.0 {
+            for face in self.faces_mut() {
+                face.elements_mut().reverse();
+            }
+        }
+    }
+
+    /**
+    `ObjMesh.recenter`
+    ---
+    Translates `self` so that `pivot` lands on the origin, and returns
+    where the pivot was, to place the mesh back with a transform. Empty
+    meshes are left as they are.
+    */
+    pub fn recenter(&mut self, pivot: &Pivot) -> Vector3 {
+        let point = match pivot {
+            Pivot::BoundsCenter => self.bounding_box().map(|bounds| bounds.center()),
+            Pivot::BoundsBottom => self.bounding_box().map(|bounds| {
+                let center = bounds.center();
+                Vector3::new(center.x, bounds.min().y, center.z)
+            }),
+            Pivot::Centroid => (!self.positions().is_empty()).then(|| {
+                let sum = self
+                    .positions()
+                    .iter()
+                    .fold([0.0; 3], |sum, p| math::add(sum, math::from_vector(p)));
+                math::to_vector(math::scale(sum, 1.0 / self.positions().len() as f32))
+            }),
+            Pivot::Point(point) => Some(point.clone()),
+        };
+        let Some(point) = point else {
+            return Vector3::new(0.0, 0.0, 0.0);
+        };
+
+        let offset = math::from_vector(&point);
+        for position in self.positions_mut() {
+            *position = math::to_vector(math::sub(math::from_vector(position), offset));
+        }
+
+        point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::{Face, FaceElement};
+
+    const EPSILON: f32 = 1e-5;
+
+    /// A tetrahedron with outward faces, each with its own normal
+    fn tetrahedron() -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for [x, y, z] in [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+        ] {
+            mesh.load_position(Vector3::new(x, y, z));
+        }
+        let faces = [[1, 3, 2], [1, 2, 4], [1, 4, 3], [2, 3, 4]];
+        for (n, face) in faces.iter().enumerate() {
+            mesh.load_normal(math::to_vector(normal_of(&mesh, face)));
+            let elements = face
+                .iter()
+                .map(|&p| FaceElement::new(p, n as u32 + 1, 0))
+                .collect();
+            mesh.load_face(Face::new(elements));
+        }
+        mesh
+    }
+
+    /// Unit normal of the corners `face`, from their winding
+    fn normal_of(mesh: &ObjMesh, face: &[u32]) -> Vec3 {
+        let [a, b, c] =
+            [0, 1, 2].map(|k| math::from_vector(&mesh.positions()[face[k] as usize - 1]));
+        math::normalize(math::cross(math::sub(b, a), math::sub(c, a))).unwrap()
+    }
+
+    fn winding(mesh: &ObjMesh) -> Vec<u32> {
+        mesh.faces()[0]
+            .elements()
+            .iter()
+            .map(FaceElement::position_index)
+            .collect()
+    }
+
+    fn scale(x: f32, y: f32, z: f32) -> [[f32; 4]; 4] {
+        [
+            [x, 0.0, 0.0, 0.0],
+            [0.0, y, 0.0, 0.0],
+            [0.0, 0.0, z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+
+    /// Checks that every stored normal matches the winding of its face and points out of the mesh
+    fn assert_outward(mesh: &ObjMesh) {
+        let center = mesh
+            .positions()
+            .iter()
+            .fold([0.0; 3], |sum, p| math::add(sum, math::from_vector(p)));
+        let center = math::scale(center, 1.0 / mesh.positions().len() as f32);
+
+        for face in mesh.faces() {
+            let corners: Vec<u32> = face.elements().iter().map(|e| e.position_index()).collect();
+            let geometric = normal_of(mesh, &corners);
+            let stored =
+                math::from_vector(&mesh.normals()[face.elements()[0].normal_index() as usize - 1]);
+
+            assert!((math::length(stored) - 1.0).abs() < EPSILON);
+            assert!(math::length(math::cross(stored, geometric)) < EPSILON);
+            assert!(math::dot(stored, geometric) > 0.0);
+            let corner = math::from_vector(&mesh.positions()[corners[0] as usize - 1]);
+            assert!(math::dot(geometric, math::sub(corner, center)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn non_uniform_scale_keeps_normals_perpendicular() {
+        let mut mesh = tetrahedron();
+        let mut matrix = scale(3.0, 1.0, 0.5);
+        matrix[1][3] = 2.0;
+        mesh.transform(&matrix);
+
+        assert_eq!(math::from_vector(&mesh.positions()[1]), [3.0, 2.0, 0.0]);
+        assert_eq!(math::from_vector(&mesh.positions()[3]), [0.0, 2.0, 0.5]);
+        assert_outward(&mesh);
+        assert_eq!(winding(&mesh), vec![1, 3, 2]);
+    }
+
+    #[test]
+    fn mirror_reverses_winding_and_keeps_normals_outward() {
+        let mut mesh = tetrahedron();
+        mesh.transform(&scale(-1.0, 1.0, 1.0));
+
+        assert_eq!(winding(&mesh), vec![2, 3, 1]);
+        assert_outward(&mesh);
+        // the face on the mirror plane keeps its normal
+        assert_eq!(math::from_vector(&mesh.normals()[2]), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn recenter_moves_each_pivot_to_the_origin() {
+        let points = [
+            [1.0, 2.0, 3.0],
+            [3.0, 2.0, 3.0],
+            [3.0, 6.0, 3.0],
+            [1.0, 2.0, 7.0],
+        ];
+        let mesh = || {
+            let mut mesh = ObjMesh::empty();
+            for [x, y, z] in points {
+                mesh.load_position(Vector3::new(x, y, z));
+            }
+            mesh
+        };
+
+        let pivots = [
+            (Pivot::BoundsCenter, [2.0, 4.0, 5.0]),
+            (Pivot::BoundsBottom, [2.0, 2.0, 5.0]),
+            (Pivot::Centroid, [2.0, 3.0, 4.0]),
+            (Pivot::Point(Vector3::new(1.0, 1.0, 1.0)), [1.0, 1.0, 1.0]),
+        ];
+        for (pivot, expected) in pivots {
+            let mut mesh = mesh();
+            let point = mesh.recenter(&pivot);
+            assert_eq!(math::from_vector(&point), expected, "{pivot:?}");
+            for (moved, original) in mesh.positions().iter().zip(points) {
+                assert_eq!(math::from_vector(moved), math::sub(original, expected));
+            }
+        }
+
+        let mut empty = ObjMesh::empty();
+        assert_eq!(
+            math::from_vector(&empty.recenter(&Pivot::Centroid)),
+            [0.0; 3]
+        );
+    }
+}
@@ -0,0 +1,809 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::face::FaceElement;
+use crate::math::{self, Vec3};
+use crate::obj_mesh::ObjMesh;
+
+/**
+`obj::VertexAttribute`
+---
+The vertex data buffers of an `ObjMesh` that faces index into
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexAttribute {
+    Position,
+    Uv,
+    Normal,
+}
+
+/**
+`obj::MeshIssue`
+---
+A problem found by `ObjMesh.validate`. Faces are indices into
+`ObjMesh.faces`, vertex data indices count from `0` within the
+buffers of the mesh.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshIssue {
+    /// A face corner refers to an element outside the buffers of the mesh
+    IndexOutOfRange {
+        face: usize,
+        corner: usize,
+        attribute: VertexAttribute,
+    },
+    /// A face with fewer than three distinct positions, or without area
+    DegenerateFace { face: usize },
+    /// A face made of the same positions as the earlier face `original`
+    DuplicateFace { face: usize, original: usize },
+    /// An element that no face or free-form element refers to
+    UnreferencedVertex {
+        attribute: VertexAttribute,
+        index: usize,
+    },
+    /// An element with a NaN or infinite component
+    NonFiniteVertex {
+        attribute: VertexAttribute,
+        index: usize,
+    },
+    /// The edge between two positions is shared by more than two faces
+    NonManifoldEdge { edge: [usize; 2], faces: Vec<usize> },
+    /// Two faces run along their shared edge in the same direction
+    InconsistentWinding { face: usize, neighbour: usize },
+    /// A normal too short to be normalized
+    ZeroLengthNormal { index: usize },
+}
+
+/**
+`obj::ValidationReport`
+---
+Every `MeshIssue` found in a mesh, in the order of the checks
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    issues: Vec<MeshIssue>,
+}
+
+impl ValidationReport {
+    /// Immutable getter for `issues` of `ValidationReport`
+    pub fn issues(&self) -> &Vec<MeshIssue> {
+        &self.issues
+    }
+
+    /// Returns `true` when no issue was found
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/**
+`obj::RepairOptions`
+---
+Repairs applied by `ObjMesh.repair`, one for each kind of `MeshIssue`:
+
+- `drop_invalid_faces` drops faces with out-of-range or non-finite
+  positions and degenerate faces, and clears references to out-of-range
+  uvs and normals and to non-finite uvs
+- `drop_duplicate_faces` drops duplicate faces, keeping the first one
+- `drop_non_manifold_faces` drops the faces after the first two on
+  every non-manifold edge
+- `weld_epsilon` welds positions closer than the given distance, which
+  reconnects faces that exporters split along seams
+- `flip_winding` flips faces to match the winding of their neighbours
+- `renormalize` scales normals to unit length, replacing zero-length
+  and non-finite normals with the normal of the faces using them
+- `drop_unreferenced` removes vertex data no face refers to
+
+Everything but welding is enabled by default.
+*/
+#[derive(Debug, Clone)]
+pub struct RepairOptions {
+    drop_invalid_faces: bool,
+    drop_duplicate_faces: bool,
+    drop_non_manifold_faces: bool,
+    weld_epsilon: Option<f32>,
+    flip_winding: bool,
+    renormalize: bool,
+    drop_unreferenced: bool,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            drop_invalid_faces: true,
+            drop_duplicate_faces: true,
+            drop_non_manifold_faces: true,
+            weld_epsilon: None,
+            flip_winding: true,
+            renormalize: true,
+            drop_unreferenced: true,
+        }
+    }
+}
+
+impl RepairOptions {
+    /// Immutable getter for `drop_invalid_faces` of `RepairOptions`
+    pub fn drop_invalid_faces(&self) -> bool {
+        self.drop_invalid_faces
+    }
+
+    /// Setter for `drop_invalid_faces` of `RepairOptions`
+    pub fn set_drop_invalid_faces(&mut self, value: bool) {
+        self.drop_invalid_faces = value;
+    }
+
+    /// Immutable getter for `drop_duplicate_faces` of `RepairOptions`
+    pub fn drop_duplicate_faces(&self) -> bool {
+        self.drop_duplicate_faces
+    }
+
+    /// Setter for `drop_duplicate_faces` of `RepairOptions`
+    pub fn set_drop_duplicate_faces(&mut self, value: bool) {
+        self.drop_duplicate_faces = value;
+    }
+
+    /// Immutable getter for `drop_non_manifold_faces` of `RepairOptions`
+    pub fn drop_non_manifold_faces(&self) -> bool {
+        self.drop_non_manifold_faces
+    }
+
+    /// Setter for `drop_non_manifold_faces` of `RepairOptions`
+    pub fn set_drop_non_manifold_faces(&mut self, value: bool) {
+        self.drop_non_manifold_faces = value;
+    }
+
+    /// Immutable getter for `weld_epsilon` of `RepairOptions`
+    pub fn weld_epsilon(&self) -> Option<f32> {
+        self.weld_epsilon
+    }
+
+    /// Setter for `weld_epsilon` of `RepairOptions`, `None` disables welding
+    pub fn set_weld_epsilon(&mut self, value: Option<f32>) {
+        self.weld_epsilon = value;
+    }
+
+    /// Immutable getter for `flip_winding` of `RepairOptions`
+    pub fn flip_winding(&self) -> bool {
+        self.flip_winding
+    }
+
+    /// Setter for `flip_winding` of `RepairOptions`
+    pub fn set_flip_winding(&mut self, value: bool) {
+        self.flip_winding = value;
+    }
+
+    /// Immutable getter for `renormalize` of `RepairOptions`
+    pub fn renormalize(&self) -> bool {
+        self.renormalize
+    }
+
+    /// Setter for `renormalize` of `RepairOptions`
+    pub fn set_renormalize(&mut self, value: bool) {
+        self.renormalize = value;
+    }
+
+    /// Immutable getter for `drop_unreferenced` of `RepairOptions`
+    pub fn drop_unreferenced(&self) -> bool {
+        self.drop_unreferenced
+    }
+
+    /// Setter for `drop_unreferenced` of `RepairOptions`
+    pub fn set_drop_unreferenced(&mut self, value: bool) {
+        self.drop_unreferenced = value;
+    }
+}
+
+/// Undirected edges between two positions, with the faces using them
+/// and whether each face runs from the lower to the higher position
+type EdgeMap = HashMap<[usize; 2], Vec<(usize, bool)>>;
+
+impl ObjMesh {
+    /**
+    `ObjMesh.validate`
+    ---
+    Checks `self` for out-of-range indices, degenerate and duplicate faces,
+    unreferenced and non-finite vertex data, non-manifold edges, inconsistent
+    winding and zero-length normals. Indices must refer to the mesh's own
+    buffers, see `ObjMesh.set_index_base`.
+    */
+    pub fn validate(&self) -> ValidationReport {
+        let mut issues = vec![];
+
+        for attribute in [
+            VertexAttribute::Position,
+            VertexAttribute::Uv,
+            VertexAttribute::Normal,
+        ] {
+            for (index, value) in self.attribute_values(attribute).iter().enumerate() {
+                if !math::is_finite(*value) {
+                    issues.push(MeshIssue::NonFiniteVertex { attribute, index });
+                }
+            }
+        }
+        for (index, normal) in self.normals().iter().enumerate() {
+            let normal = math::from_vector(normal);
+            if math::is_finite(normal) && math::normalize(normal).is_none() {
+                issues.push(MeshIssue::ZeroLengthNormal { index });
+            }
+        }
+
+        for (face, corners) in self.faces().iter().enumerate() {
+            for (corner, element) in corners.elements().iter().enumerate() {
+                for attribute in self.out_of_range(element) {
+                    issues.push(MeshIssue::IndexOutOfRange {
+                        face,
+                        corner,
+                        attribute,
+                    });
+                }
+            }
+        }
+
+        let positions = self.face_positions();
+        let degenerate = self.degenerate_faces(&positions);
+        let duplicates = Self::duplicate_faces(&positions, &degenerate);
+        for (face, &degenerate) in degenerate.iter().enumerate() {
+            if degenerate {
+                issues.push(MeshIssue::DegenerateFace { face });
+            }
+        }
+        for (face, duplicate) in duplicates.iter().enumerate() {
+            if let Some(original) = *duplicate {
+                issues.push(MeshIssue::DuplicateFace { face, original });
+            }
+        }
+
+        let skip: Vec<bool> = (0..positions.len())
+            .map(|face| degenerate[face] || duplicates[face].is_some())
+            .collect();
+        let edges = Self::edge_map(&positions, &skip);
+        let mut keys: Vec<&[usize; 2]> = edges.keys().collect();
+        keys.sort();
+        for edge in keys {
+            let faces = &edges[edge];
+            match faces.len() {
+                0..=1 => (),
+                2 if faces[0].1 == faces[1].1 => issues.push(MeshIssue::InconsistentWinding {
+                    face: faces[1].0,
+                    neighbour: faces[0].0,
+                }),
+                2 => (),
+                _ => issues.push(MeshIssue::NonManifoldEdge {
+                    edge: *edge,
+                    faces: faces.iter().map(|&(face, _)| face).collect(),
+                }),
+            }
+        }
+
+        for attribute in [
+            VertexAttribute::Position,
+            VertexAttribute::Uv,
+            VertexAttribute::Normal,
+        ] {
+            for (index, referenced) in self.referenced(attribute).into_iter().enumerate() {
+                if !referenced {
+                    issues.push(MeshIssue::UnreferencedVertex { attribute, index });
+                }
+            }
+        }
+
+        ValidationReport { issues }
+    }
+
+    /**
+    `ObjMesh.repair`
+    ---
+    Applies the repairs enabled in `options`, in order: welding, dropping
+    invalid, duplicate and non-manifold faces, flipping, renormalizing and
+    dropping unreferenced data. Returns the issues left after the repair,
+    such as the winding of non-orientable surfaces.
+    */
+    pub fn repair(&mut self, options: &RepairOptions) -> ValidationReport {
+        if let Some(epsilon) = options.weld_epsilon() {
+            self.weld_attribute(VertexAttribute::Position, epsilon);
+        }
+
+        if options.drop_invalid_faces() {
+            self.drop_invalid_faces();
+        }
+
+        if options.drop_duplicate_faces() {
+            let positions = self.face_positions();
+            let degenerate = self.degenerate_faces(&positions);
+            let duplicates = Self::duplicate_faces(&positions, &degenerate);
+            let keep: Vec<bool> = duplicates.iter().map(Option::is_none).collect();
+            self.retain_faces(&keep);
+        }
+
+        if options.drop_non_manifold_faces() {
+            let positions = self.face_positions();
+            let skip = self.degenerate_faces(&positions);
+            let mut keep = vec![true; positions.len()];
+            for faces in Self::edge_map(&positions, &skip).values() {
+                for &(face, _) in faces.iter().skip(2) {
+                    keep[face] = false;
+                }
+            }
+            self.retain_faces(&keep);
+        }
+
+        if options.flip_winding() {
+            self.orient_faces();
+        }
+
+        if options.renormalize() {
+            self.renormalize();
+        }
+
+        if options.drop_unreferenced() {
+            self.compact();
+        }
+
+        self.validate()
+    }
+
+    /// Returns the values of an `attribute` buffer as `Vec3`s, with uvs padded by `0`
+    pub(crate) fn attribute_values(&self, attribute: VertexAttribute) -> Vec<Vec3> {
+        match attribute {
+            VertexAttribute::Position => self.positions().iter().map(math::from_vector).collect(),
+            VertexAttribute::Uv => self.uvs().iter().map(|uv| [uv.x, uv.y, 0.0]).collect(),
+            VertexAttribute::Normal => self.normals().iter().map(math::from_vector).collect(),
+        }
+    }
+
+    /**
+    `ObjMesh.local_index`
+    ---
+    Converts a face index of `attribute` into an index into the buffer of
+    `self`, or `None` if it is out of range or, for uvs and normals, missing
+    */
+    pub(crate) fn local_index(&self, attribute: VertexAttribute, index: u32) -> Option<usize> {
+        let (base, len) = match attribute {
+            VertexAttribute::Position => (self.position_base(), self.positions().len()),
+            VertexAttribute::Uv => (self.uv_base(), self.uvs().len()),
+            VertexAttribute::Normal => (self.normal_base(), self.normals().len()),
+        };
+
+        let local = index.checked_sub(base + 1)? as usize;
+        (local < len).then_some(local)
+    }
+
+    /// Attributes of `element` that refer to elements outside the buffers of `self`
+    fn out_of_range(&self, element: &FaceElement) -> Vec<VertexAttribute> {
+        let mut attributes = vec![];
+        if self
+            .local_index(VertexAttribute::Position, element.position_index())
+            .is_none()
+        {
+            attributes.push(VertexAttribute::Position);
+        }
+        let uv = element.uv_index();
+        if uv != 0 && self.local_index(VertexAttribute::Uv, uv).is_none() {
+            attributes.push(VertexAttribute::Uv);
+        }
+        let normal = element.normal_index();
+        if normal != 0 && self.local_index(VertexAttribute::Normal, normal).is_none() {
+            attributes.push(VertexAttribute::Normal);
+        }
+
+        attributes
+    }
+
+    /// Local position indices of every face, or `None` for faces with an invalid index
+    fn face_positions(&self) -> Vec<Option<Vec<usize>>> {
+        self.faces()
+            .iter()
+            .map(|face| {
+                face.elements()
+                    .iter()
+                    .map(|element| {
+                        self.local_index(VertexAttribute::Position, element.position_index())
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Newell normal of the polygon through `positions`, twice its area in length
+    fn polygon_normal(&self, positions: &[usize]) -> Vec3 {
+        let points: Vec<Vec3> = positions
+            .iter()
+            .map(|&i| math::from_vector(&self.positions()[i]))
+            .collect();
+
+        let mut normal = [0.0; 3];
+        for (i, &a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            normal = math::add(normal, math::cross(a, b));
+        }
+        normal
+    }
+
+    /**
+    `ObjMesh.degenerate_faces`
+    ---
+    Flags the faces with valid, finite positions that have fewer than three
+    distinct positions, or an area that is negligible next to their size
+    */
+    fn degenerate_faces(&self, positions: &[Option<Vec<usize>>]) -> Vec<bool> {
+        positions
+            .iter()
+            .map(|face| {
+                let Some(face) = face else {
+                    return false;
+                };
+                let points: Vec<Vec3> = face
+                    .iter()
+                    .map(|&i| math::from_vector(&self.positions()[i]))
+                    .collect();
+                if !points.iter().all(|&p| math::is_finite(p)) {
+                    return false;
+                }
+
+                let mut distinct = face.clone();
+                distinct.sort_unstable();
+                distinct.dedup();
+                if distinct.len() < 3 {
+                    return true;
+                }
+
+                let size = points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &a)| {
+                        let edge = math::sub(points[(i + 1) % points.len()], a);
+                        math::dot(edge, edge)
+                    })
+                    .fold(0.0, f32::max);
+                math::length(self.polygon_normal(face)) <= size * f32::EPSILON
+            })
+            .collect()
+    }
+
+    /// For each face, the first earlier face made of the same positions
+    fn duplicate_faces(positions: &[Option<Vec<usize>>], skip: &[bool]) -> Vec<Option<usize>> {
+        let mut seen: HashMap<Vec<usize>, usize> = HashMap::new();
+
+        positions
+            .iter()
+            .enumerate()
+            .map(|(face, positions)| {
+                let mut key = positions.clone().filter(|_| !skip[face])?;
+                key.sort_unstable();
+                match seen.get(&key) {
+                    Some(&original) => Some(original),
+                    None => {
+                        seen.insert(key, face);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Builds the `EdgeMap` of the faces with valid positions that aren't skipped
+    fn edge_map(positions: &[Option<Vec<usize>>], skip: &[bool]) -> EdgeMap {
+        let mut edges = EdgeMap::new();
+
+        for (face, positions) in positions.iter().enumerate() {
+            let Some(positions) = positions.as_ref().filter(|_| !skip[face]) else {
+                continue;
+            };
+            for (i, &a) in positions.iter().enumerate() {
+                let b = positions[(i + 1) % positions.len()];
+                if a != b {
+                    edges
+                        .entry([a.min(b), a.max(b)])
+                        .or_default()
+                        .push((face, a < b));
+                }
+            }
+        }
+
+        edges
+    }
+
+    /// Flags the elements of an `attribute` buffer referred to by faces or free-form elements
+    pub(crate) fn referenced(&self, attribute: VertexAttribute) -> Vec<bool> {
+        let len = self.attribute_values(attribute).len();
+        let mut referenced = vec![false; len];
+        let mut mark = |index: u32| {
+            if let Some(local) = self.local_index(attribute, index) {
+                referenced[local] = true;
+            }
+        };
+        let index_of = |element: &FaceElement| match attribute {
+            VertexAttribute::Position => element.position_index(),
+            VertexAttribute::Uv => element.uv_index(),
+            VertexAttribute::Normal => element.normal_index(),
+        };
+
+        for face in self.faces() {
+            face.elements()
+                .iter()
+                .for_each(|element| mark(index_of(element)));
+        }
+        for surface in self.surfaces() {
+            surface
+                .control_points()
+                .iter()
+                .for_each(|element| mark(index_of(element)));
+        }
+        if attribute == VertexAttribute::Position {
+            for curve in self.curves() {
+                curve.control_points().iter().for_each(|&index| mark(index));
+            }
+        }
+
+        referenced
+    }
+
+    /**
+    `ObjMesh.drop_invalid_faces`
+    ---
+    Drops the faces with out-of-range or non-finite positions and
+    the degenerate ones, and clears the references of the remaining
+    faces to out-of-range uvs and normals and to non-finite uvs
+    */
+    fn drop_invalid_faces(&mut self) {
+        let positions = self.face_positions();
+        let degenerate = self.degenerate_faces(&positions);
+        let keep: Vec<bool> = positions
+            .iter()
+            .zip(&degenerate)
+            .map(|(positions, &degenerate)| {
+                positions.as_ref().is_some_and(|positions| {
+                    positions
+                        .iter()
+                        .all(|&i| math::is_finite(math::from_vector(&self.positions()[i])))
+                }) && !degenerate
+            })
+            .collect();
+        self.retain_faces(&keep);
+
+        let uvs: Vec<bool> = self
+            .attribute_values(VertexAttribute::Uv)
+            .into_iter()
+            .map(math::is_finite)
+            .collect();
+        let (uv_base, normal_base) = (self.uv_base(), self.normal_base());
+        let normal_count = self.normals().len();
+        let clear = |index: u32, base: u32, valid: &dyn Fn(usize) -> bool| match index
+            .checked_sub(base + 1)
+        {
+            Some(local) if valid(local as usize) => index,
+            _ => 0,
+        };
+
+        for face in self.faces_mut() {
+            for element in face.elements_mut() {
+                *element = FaceElement::new(
+                    element.position_index(),
+                    clear(element.normal_index(), normal_base, &|i| i < normal_count),
+                    clear(element.uv_index(), uv_base, &|i| uvs.get(i) == Some(&true)),
+                );
+            }
+        }
+    }
+
+    /**
+    `ObjMesh.orient_faces`
+    ---
+    Walks every connected patch of faces across their manifold edges
+    and makes the winding of its faces consistent. Of the two consistent
+    windings, closed patches keep the one enclosing a positive volume,
+    and open ones the one that flips the fewest faces. Patches that can't
+    be oriented, like a Möbius strip, keep the orientation found first.
+    */
+    fn orient_faces(&mut self) {
+        let positions = self.face_positions();
+        let degenerate = self.degenerate_faces(&positions);
+        let edges = Self::edge_map(&positions, &degenerate);
+
+        let mut neighbours: Vec<Vec<(usize, bool)>> = vec![vec![]; positions.len()];
+        for faces in edges.values() {
+            if let [(a, forward_a), (b, forward_b)] = faces[..] {
+                // `b` must be flipped relative to `a` if they run the same way
+                neighbours[a].push((b, forward_a == forward_b));
+                neighbours[b].push((a, forward_a == forward_b));
+            }
+        }
+
+        let mut flip: Vec<Option<bool>> = vec![None; positions.len()];
+        let mut queue = VecDeque::new();
+        for start in 0..positions.len() {
+            if flip[start].is_some() {
+                continue;
+            }
+            flip[start] = Some(false);
+            queue.push_back(start);
+
+            let mut patch = vec![];
+            while let Some(face) = queue.pop_front() {
+                patch.push(face);
+                let flipped = flip[face].unwrap_or(false);
+                for &(neighbour, opposite) in &neighbours[face] {
+                    if flip[neighbour].is_none() {
+                        flip[neighbour] = Some(flipped ^ opposite);
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+
+            // every edge of a closed patch is shared by exactly two faces
+            let closed = patch.iter().all(|&face| {
+                let Some(face) = positions[face].as_ref().filter(|_| !degenerate[face]) else {
+                    return false;
+                };
+                face.iter().enumerate().all(|(i, &a)| {
+                    let b = face[(i + 1) % face.len()];
+                    a == b
+                        || edges
+                            .get(&[a.min(b), a.max(b)])
+                            .is_some_and(|faces| faces.len() == 2)
+                })
+            });
+            let volume: f32 = match closed {
+                true => patch
+                    .iter()
+                    .filter_map(|&face| Some((face, positions[face].as_ref()?)))
+                    .map(|(face, points)| {
+                        let first = math::from_vector(&self.positions()[points[0]]);
+                        let volume = math::dot(first, self.polygon_normal(points));
+                        match flip[face] {
+                            Some(true) => -volume,
+                            _ => volume,
+                        }
+                    })
+                    .sum(),
+                false => 0.0,
+            };
+            let flips = patch
+                .iter()
+                .filter(|&&face| flip[face] == Some(true))
+                .count();
+
+            let invert = match volume != 0.0 {
+                true => volume < 0.0,
+                false => flips * 2 > patch.len(),
+            };
+            if invert {
+                for &face in &patch {
+                    flip[face] = flip[face].map(|flipped| !flipped);
+                }
+            }
+        }
+
+        for (face, flip) in self.faces_mut().iter_mut().zip(flip) {
+            if flip == Some(true) {
+                face.elements_mut().reverse();
+            }
+        }
+    }
+
+    /**
+    `ObjMesh.renormalize`
+    ---
+    Scales every normal to unit length. Zero-length and non-finite
+    normals are replaced by the normalized sum of the normals of the
+    faces using them, and left as they are if no face does.
+    */
+    fn renormalize(&mut self) {
+        let mut sums: Vec<Vec3> = vec![[0.0; 3]; self.normals().len()];
+        for face in self.faces() {
+            let positions: Option<Vec<usize>> = face
+                .elements()
+                .iter()
+                .map(|element| {
+                    self.local_index(VertexAttribute::Position, element.position_index())
+                })
+                .collect();
+            let Some(positions) = positions else {
+                continue;
+            };
+
+            let normal = self.polygon_normal(&positions);
+            for element in face.elements() {
+                if let Some(local) =
+                    self.local_index(VertexAttribute::Normal, element.normal_index())
+                {
+                    sums[local] = math::add(sums[local], normal);
+                }
+            }
+        }
+
+        for (normal, sum) in self.normals_mut().iter_mut().zip(sums) {
+            let value = math::from_vector(normal);
+            let renormalized = match math::is_finite(value) {
+                true => math::normalize(value).or_else(|| math::normalize(sum)),
+                false => math::normalize(sum),
+            };
+            if let Some(renormalized) = renormalized {
+                *normal = math::to_vector(renormalized);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::Face;
+    use suoi_types::Vector3;
+
+    fn mesh(positions: &[[f32; 3]], faces: &[&[u32]]) -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for &[x, y, z] in positions {
+            mesh.load_position(Vector3::new(x, y, z));
+        }
+        for face in faces {
+            let elements = face.iter().map(|&i| FaceElement::new(i, 0, 0)).collect();
+            mesh.load_face(Face::new(elements));
+        }
+        mesh
+    }
+
+    fn windings(mesh: &ObjMesh) -> Vec<Vec<u32>> {
+        mesh.faces()
+            .iter()
+            .map(|face| face.elements().iter().map(|e| e.position_index()).collect())
+            .collect()
+    }
+
+    fn orient(mesh: &mut ObjMesh) {
+        let mut options = RepairOptions::default();
+        options.set_renormalize(false);
+        options.set_drop_unreferenced(false);
+        mesh.repair(&options);
+    }
+
+    const TETRAHEDRON: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ];
+
+    #[test]
+    fn flips_an_inverted_first_face_of_a_closed_patch() {
+        let outward: [&[u32]; 4] = [&[1, 3, 2], &[1, 2, 4], &[1, 4, 3], &[2, 3, 4]];
+        let mut mesh = mesh(
+            &TETRAHEDRON,
+            &[&[1, 2, 3], outward[1], outward[2], outward[3]],
+        );
+        orient(&mut mesh);
+        assert_eq!(
+            windings(&mesh),
+            vec![vec![3, 2, 1], vec![1, 2, 4], vec![1, 4, 3], vec![2, 3, 4]]
+        );
+        assert!(mesh.validate().issues().is_empty());
+    }
+
+    #[test]
+    fn turns_closed_patches_outwards() {
+        let mut mesh = mesh(
+            &TETRAHEDRON,
+            &[&[1, 2, 3], &[1, 4, 2], &[1, 3, 4], &[2, 4, 3]],
+        );
+        orient(&mut mesh);
+        assert_eq!(
+            windings(&mesh),
+            vec![vec![3, 2, 1], vec![2, 4, 1], vec![4, 3, 1], vec![3, 4, 2]]
+        );
+    }
+
+    #[test]
+    fn flips_the_fewest_faces_of_an_open_patch() {
+        let mut strip = vec![];
+        for y in [0.0, 1.0] {
+            for x in [0.0, 1.0, 2.0, 3.0] {
+                strip.push([x, y, 0.0]);
+            }
+        }
+        let mut mesh = mesh(&strip, &[&[5, 6, 2, 1], &[2, 3, 7, 6], &[3, 4, 8, 7]]);
+        orient(&mut mesh);
+        assert_eq!(
+            windings(&mesh),
+            vec![vec![1, 2, 6, 5], vec![2, 3, 7, 6], vec![3, 4, 8, 7]]
+        );
+    }
+}
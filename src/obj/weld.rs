@@ -0,0 +1,251 @@
+use crate::math;
+use crate::obj_mesh::ObjMesh;
+use crate::VertexAttribute;
+
+/**
+`obj::WeldOptions`
+---
+How close positions, uvs and normals must be to be merged by
+`ObjMesh.weld`, as a distance per buffer. `None` leaves a buffer
+as it is, and `0` only merges identical values, which is the default.
+*/
+#[derive(Debug, Clone)]
+pub struct WeldOptions {
+    position_epsilon: Option<f32>,
+    uv_epsilon: Option<f32>,
+    normal_epsilon: Option<f32>,
+}
+
+impl Default for WeldOptions {
+    fn default() -> Self {
+        Self {
+            position_epsilon: Some(0.0),
+            uv_epsilon: Some(0.0),
+            normal_epsilon: Some(0.0),
+        }
+    }
+}
+
+impl WeldOptions {
+    /// Immutable getter for `position_epsilon` of `WeldOptions`
+    pub fn position_epsilon(&self) -> Option<f32> {
+        self.position_epsilon
+    }
+
+    /// Setter for `position_epsilon` of `WeldOptions`
+    pub fn set_position_epsilon(&mut self, value: Option<f32>) {
+        self.position_epsilon = value;
+    }
+
+    /// Immutable getter for `uv_epsilon` of `WeldOptions`
+    pub fn uv_epsilon(&self) -> Option<f32> {
+        self.uv_epsilon
+    }
+
+    /// Setter for `uv_epsilon` of `WeldOptions`
+    pub fn set_uv_epsilon(&mut self, value: Option<f32>) {
+        self.uv_epsilon = value;
+    }
+
+    /// Immutable getter for `normal_epsilon` of `WeldOptions`
+    pub fn normal_epsilon(&self) -> Option<f32> {
+        self.normal_epsilon
+    }
+
+    /// Setter for `normal_epsilon` of `WeldOptions`
+    pub fn set_normal_epsilon(&mut self, value: Option<f32>) {
+        self.normal_epsilon = value;
+    }
+}
+
+impl ObjMesh {
+    /**
+    `ObjMesh.weld`
+    ---
+    Merges the positions, uvs and normals of `self` that lie within the
+    epsilons of `options`, then drops the data no longer referenced, see
+    `ObjMesh.compact`. Every face and free-form element is remapped, and
+    the first of each group of merged values is the one kept. Rational
+    weights are ignored when comparing positions.
+    */
+    pub fn weld(&mut self, options: &WeldOptions) {
+        let epsilons = [
+            (VertexAttribute::Position, options.position_epsilon()),
+            (VertexAttribute::Uv, options.uv_epsilon()),
+            (VertexAttribute::Normal, options.normal_epsilon()),
+        ];
+        for (attribute, epsilon) in epsilons {
+            if let Some(epsilon) = epsilon {
+                self.weld_attribute(attribute, epsilon);
+            }
+        }
+
+        self.compact();
+    }
+
+    /**
+    `ObjMesh.compact`
+    ---
+    Drops the positions, uvs and normals that no face or free-form element
+    refers to, keeping the order of the rest. Afterwards, the lowest index
+    used by the faces of `self` is the one following its index base.
+    */
+    pub fn compact(&mut self) {
+        for attribute in [
+            VertexAttribute::Position,
+            VertexAttribute::Uv,
+            VertexAttribute::Normal,
+        ] {
+            let referenced = self.referenced(attribute);
+            if referenced.iter().all(|&referenced| referenced) {
+                continue;
+            }
+
+            let mut count = 0;
+            let map: Vec<Option<u32>> = referenced
+                .into_iter()
+                .map(|referenced| {
+                    referenced.then(|| {
+                        count += 1;
+                        count - 1
+                    })
+                })
+                .collect();
+            self.remap(attribute, &map);
+        }
+    }
+
+    /// Merges the values of the `attribute` buffer within `epsilon` of each other
+    pub(crate) fn weld_attribute(&mut self, attribute: VertexAttribute, epsilon: f32) {
+        let map = math::weld(&self.attribute_values(attribute), epsilon);
+        if map
+            .iter()
+            .enumerate()
+            .any(|(index, &target)| target as usize != index)
+        {
+            let map: Vec<Option<u32>> = map.into_iter().map(Some).collect();
+            self.remap(attribute, &map);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::face::{Face, FaceElement};
+    use suoi_types::{Vector2, Vector3};
+
+    /// Two triangles sharing an edge, with the shared corners loaded twice
+    fn split_quad(offset: f32) -> ObjMesh {
+        let mut mesh = ObjMesh::empty();
+        for [x, y] in [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]] {
+            mesh.load_position(Vector3::new(x, y, 0.0));
+        }
+        for [x, y] in [[0.0, 0.0], [1.0, 1.0], [0.0, 1.0]] {
+            mesh.load_position(Vector3::new(x + offset, y, 0.0));
+        }
+        for face in [[1, 2, 3], [4, 5, 6]] {
+            let elements = face.iter().map(|&i| FaceElement::new(i, 0, 0)).collect();
+            mesh.load_face(Face::new(elements));
+        }
+        mesh
+    }
+
+    fn corners(mesh: &ObjMesh, index: impl Fn(&FaceElement) -> u32) -> Vec<Vec<u32>> {
+        mesh.faces()
+            .iter()
+            .map(|face| face.elements().iter().map(&index).collect())
+            .collect()
+    }
+
+    #[test]
+    fn default_epsilon_merges_only_exact_duplicates() {
+        let mut exact = split_quad(0.0);
+        exact.weld(&WeldOptions::default());
+        assert_eq!(exact.positions().len(), 4);
+        assert_eq!(
+            corners(&exact, FaceElement::position_index),
+            vec![vec![1, 2, 3], vec![1, 3, 4]]
+        );
+
+        let mut near = split_quad(1e-4);
+        near.weld(&WeldOptions::default());
+        assert_eq!(near.positions().len(), 6);
+    }
+
+    #[test]
+    fn epsilon_merges_near_duplicates() {
+        let mut mesh = split_quad(1e-4);
+        let mut options = WeldOptions::default();
+        options.set_position_epsilon(Some(1e-3));
+        mesh.weld(&options);
+
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(
+            corners(&mesh, FaceElement::position_index),
+            vec![vec![1, 2, 3], vec![1, 3, 4]]
+        );
+        // The first of the merged values is the one kept
+        assert_eq!(mesh.positions()[0].x, 0.0);
+    }
+
+    #[test]
+    fn welding_positions_keeps_uv_seams() {
+        let mut mesh = ObjMesh::empty();
+        for [x, y] in [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]] {
+            mesh.load_position(Vector3::new(x, y, 0.0));
+        }
+        // The second triangle has its own uvs along the shared edge
+        for [x, y] in [[0.0, 0.0], [0.5, 0.0], [0.5, 0.5], [0.6, 0.0], [0.6, 0.5]] {
+            mesh.load_uv(Vector2 { x, y });
+        }
+        for face in [[(1, 1), (2, 2), (3, 3)], [(1, 4), (3, 5), (4, 5)]] {
+            let elements = face
+                .iter()
+                .map(|&(position, uv)| FaceElement::new(position, 0, uv))
+                .collect();
+            mesh.load_face(Face::new(elements));
+        }
+
+        let mut options = WeldOptions::default();
+        options.set_uv_epsilon(None);
+        mesh.weld(&options);
+
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(mesh.uvs().len(), 5);
+        assert_eq!(
+            corners(&mesh, FaceElement::uv_index),
+            vec![vec![1, 2, 3], vec![4, 5, 5]]
+        );
+    }
+
+    #[test]
+    fn compact_drops_unreferenced_data_and_remaps_faces() {
+        let mut mesh = ObjMesh::empty();
+        for x in 0..5 {
+            mesh.load_position(Vector3::new(x as f32, (x % 2) as f32, 0.0));
+            mesh.load_normal(Vector3::new(0.0, 0.0, x as f32));
+        }
+        let elements = [2, 4, 5]
+            .iter()
+            .map(|&i| FaceElement::new(i, i, 0))
+            .collect();
+        mesh.load_face(Face::new(elements));
+        mesh.compact();
+
+        assert_eq!(mesh.positions().len(), 3);
+        assert_eq!(mesh.normals().len(), 3);
+        assert_eq!(
+            corners(&mesh, FaceElement::position_index),
+            vec![vec![1, 2, 3]]
+        );
+        assert_eq!(
+            corners(&mesh, FaceElement::normal_index),
+            vec![vec![1, 2, 3]]
+        );
+        let kept: Vec<f32> = mesh.positions().iter().map(|p| p.x).collect();
+        assert_eq!(kept, vec![1.0, 3.0, 4.0]);
+        let normals: Vec<f32> = mesh.normals().iter().map(|n| n.z).collect();
+        assert_eq!(normals, vec![1.0, 3.0, 4.0]);
+    }
+}